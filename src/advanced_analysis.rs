@@ -1,91 +1,197 @@
 use anyhow::{Context, Result};
 use log::{debug, info, warn};
 use petgraph::{
-    dot::{Config, Dot},
     graph::{DiGraph, NodeIndex},
     visit::EdgeRef,
 };
+use petgraph::algo::{condensation, tarjan_scc, toposort};
 use petgraph::visit::Dfs;
 use petgraph::Direction;
 use pubgrub::{
     error::PubGrubError,
     range::Range,
+    report::{DefaultStringReporter, Reporter},
     solver::{Dependencies, DependencyProvider},
-    version::{SemanticVersion as PubgrubVersion, Version as PubgrubVersionTrait},
+    version::Version as PubgrubVersionTrait,
 };
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use walkdir::WalkDir;
-use semver;
 use reqwest;
 use serde_json;
 use lazy_static::lazy_static;
 
-use crate::models::Package;
+use crate::analysis::{dot_id, write_channel_clusters, DotOptions};
+use crate::conda_api::{self, LookupOptions};
+use crate::version::{self, CondaVersion};
+use crate::models::{GraphMetrics, Package, PinStatus, Priority, Recommendation, RecommendationCategory, Severity, Vulnerability};
 
 // Initialize a thread-safe cache for the Safety DB
 lazy_static! {
     static ref SAFETY_DB_CACHE: Mutex<Option<serde_json::Value>> = Mutex::new(None);
 }
 
+const SAFETY_DB_URL: &str = "https://raw.githubusercontent.com/pyupio/safety-db/master/data/insecure_full.json";
+/// File name of the Safety DB copy kept in the cache directory
+const SAFETY_DB_FILE: &str = "safety-db.json";
+
+/// Kind of relationship an edge of the advanced dependency graph represents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// The source package declares the target as a dependency
+    Direct,
+    /// The source package reaches the target only through other dependencies
+    Transitive,
+}
+
 /// Advanced dependency graph with rich information
 #[derive(Debug)]
 pub struct AdvancedDependencyGraph {
-    /// The underlying petgraph DiGraph
-    pub graph: DiGraph<String, String>,
+    /// The underlying petgraph DiGraph; each package pair has at most one edge
+    pub graph: DiGraph<String, EdgeKind>,
     /// Mapping from package names to node indices
     pub node_map: HashMap<String, NodeIndex>,
     /// Direct dependencies (not transitive)
     pub direct_deps: HashSet<String>,
     /// Packages with conflicts
     pub conflicts: Vec<(String, String, String)>,
+    /// Dependency cycles, each as an ordered list of package names
+    /// (the last package depends on the first)
+    pub cycles: Vec<Vec<String>>,
 }
 
-/// Create an advanced dependency graph with transitive dependencies
+impl AdvancedDependencyGraph {
+    /// Number of packages `package` depends on directly or transitively (its full
+    /// dependency closure, not counting itself), or `None` if it isn't in the graph
+    pub fn transitive_dependency_count(&self, package: &str) -> Option<usize> {
+        let node = *self.node_map.get(package)?;
+        Some(self.graph.neighbors(node).filter(|&dep| dep != node).count())
+    }
+    
+    /// Packages `package` declares as direct dependencies, sorted by name, or `None` if it
+    /// isn't in the graph
+    pub fn direct_dependencies(&self, package: &str) -> Option<Vec<String>> {
+        let node = *self.node_map.get(package)?;
+        let mut dependencies: Vec<String> = self.graph.edges(node)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct && edge.target() != node)
+            .map(|edge| self.graph[edge.target()].clone())
+            .collect();
+        dependencies.sort();
+        Some(dependencies)
+    }
+    
+    /// Packages that declare `package` as a direct dependency, sorted by name, or `None`
+    /// if it isn't in the graph. An empty list means nothing in the environment requires it
+    pub fn direct_dependents(&self, package: &str) -> Option<Vec<String>> {
+        let node = *self.node_map.get(package)?;
+        let mut dependents: Vec<String> = self.graph.edges_directed(node, Direction::Incoming)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct && edge.source() != node)
+            .map(|edge| self.graph[edge.source()].clone())
+            .collect();
+        dependents.sort();
+        Some(dependents)
+    }
+    
+    /// Complexity metrics over the direct dependency edges. Ties for the highest
+    /// fan-in or fan-out go to the alphabetically first package
+    pub fn metrics(&self) -> GraphMetrics {
+        let direct = self.graph.filter_map(
+            |_, name| Some(name.clone()),
+            |_, kind| (*kind == EdgeKind::Direct).then_some(()),
+        );
+        
+        let node_count = direct.node_count();
+        if node_count == 0 {
+            return GraphMetrics::default();
+        }
+        
+        // Highest degree in one direction, preferring the alphabetically first name on ties
+        let leader = |dir: Direction| {
+            direct.node_indices()
+                .map(|node| (direct.neighbors_directed(node, dir).count(), &direct[node]))
+                .filter(|(count, _)| *count > 0)
+                .min_by(|(ca, na), (cb, nb)| cb.cmp(ca).then_with(|| na.cmp(nb)))
+                .map(|(count, name)| (Some(name.clone()), count))
+                .unwrap_or((None, 0))
+        };
+        let (most_depended_upon, max_fan_in) = leader(Direction::Incoming);
+        let (most_dependencies, max_fan_out) = leader(Direction::Outgoing);
+        
+        // Longest chain over the condensed graph, where each cycle is a single node
+        let condensed = condensation(direct.clone(), true);
+        let mut depth = vec![0usize; condensed.node_count()];
+        if let Ok(order) = toposort(&condensed, None) {
+            for node in order.into_iter().rev() {
+                depth[node.index()] = condensed.neighbors(node)
+                    .map(|next| depth[next.index()] + 1)
+                    .max()
+                    .unwrap_or(0);
+            }
+        }
+        
+        GraphMetrics {
+            max_depth: depth.into_iter().max().unwrap_or(0),
+            most_depended_upon,
+            max_fan_in,
+            most_dependencies,
+            max_fan_out,
+            average_degree: 2.0 * direct.edge_count() as f64 / node_count as f64,
+        }
+    }
+}
+
+/// Create an advanced dependency graph with transitive dependencies.
+/// Nodes and edges are added in package name order, so the node order and the exported
+/// graph are the same on every run for the same input. `max_depth` limits the transitive
+/// edges to dependencies at most that many levels away (1 keeps only the direct ones);
+/// `None` or 0 expands the full closure
 pub fn create_advanced_dependency_graph(
     packages: &[Package],
     dependency_map: &HashMap<String, Vec<String>>,
+    max_depth: Option<usize>,
 ) -> AdvancedDependencyGraph {
     info!("Creating advanced dependency graph");
-    let mut graph = DiGraph::<String, String>::new();
+    let mut graph = DiGraph::<String, EdgeKind>::new();
     let mut node_map = HashMap::new();
     let mut direct_deps = HashSet::new();
     
     // Add all packages as nodes
-    for package in packages {
-        let node_idx = graph.add_node(package.name.clone());
-        node_map.insert(package.name.clone(), node_idx);
-        direct_deps.insert(package.name.clone());
+    let mut names: Vec<&String> = packages.iter().map(|p| &p.name).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let node_idx = graph.add_node(name.clone());
+        node_map.insert(name.clone(), node_idx);
+        direct_deps.insert(name.clone());
     }
     
     // Add direct dependency edges
-    for (pkg_name, deps) in dependency_map {
+    for (pkg_name, deps) in sorted_dependencies(dependency_map) {
         if let Some(&from_idx) = node_map.get(pkg_name) {
             for dep in deps {
-                if let Some(&to_idx) = node_map.get(dep) {
-                    // Create the edge with version requirement as the label
-                    graph.add_edge(from_idx, to_idx, "depends on".to_string());
+                if let Some(&to_idx) = node_map.get(crate::parsers::spec_name(dep)) {
+                    graph.update_edge(from_idx, to_idx, EdgeKind::Direct);
                 }
             }
         }
     }
     
     // Find transitive dependencies
-    let transitive_deps = find_transitive_dependencies(packages, dependency_map);
+    let transitive_deps = find_transitive_dependencies(packages, dependency_map, max_depth);
     
     // Add transitive dependency edges
-    for (pkg_name, deps) in &transitive_deps {
+    for (pkg_name, deps) in sorted_dependencies(&transitive_deps) {
         if let Some(&from_idx) = node_map.get(pkg_name) {
             for dep in deps {
                 if let Some(&to_idx) = node_map.get(dep) {
-                    // Only add if not a direct dependency
-                    if !direct_edge_exists(&graph, from_idx, to_idx) {
-                        graph.add_edge(from_idx, to_idx, "transitive".to_string());
+                    // Never downgrade a direct edge, and keep a single edge per pair
+                    if graph.find_edge(from_idx, to_idx).is_none() {
+                        graph.add_edge(from_idx, to_idx, EdgeKind::Transitive);
                     }
                 }
             }
@@ -93,28 +199,225 @@ pub fn create_advanced_dependency_graph(
     }
     
     // Find conflicts
-    let conflicts = detect_conflicts(packages, dependency_map);
+    let conflicts = detect_conflicts(dependency_map);
     
-    AdvancedDependencyGraph {
+    let mut advanced = AdvancedDependencyGraph {
         graph,
         node_map,
         direct_deps,
         conflicts,
+        cycles: Vec::new(),
+    };
+    advanced.cycles = detect_cycles(&advanced);
+    
+    advanced
+}
+
+/// Entries of a dependency map sorted by package name, each with its dependencies sorted
+fn sorted_dependencies<'a, D>(dependency_map: &'a HashMap<String, D>) -> Vec<(&'a String, Vec<&'a String>)>
+where
+    &'a D: IntoIterator<Item = &'a String>,
+{
+    let mut entries: Vec<(&String, Vec<&String>)> = dependency_map.iter()
+        .map(|(name, deps)| {
+            let mut deps: Vec<&String> = deps.into_iter().collect();
+            deps.sort();
+            deps.dedup();
+            (name, deps)
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Detect circular dependencies. Each strongly connected component of the direct
+/// dependency edges yields one cycle, ordered so that each package depends on the next
+/// and the last one depends on the first
+pub fn detect_cycles(graph: &AdvancedDependencyGraph) -> Vec<Vec<String>> {
+    let direct = graph.graph.filter_map(
+        |_, name| Some(name.clone()),
+        |_, kind| (*kind == EdgeKind::Direct).then_some(()),
+    );
+    
+    let mut cycles: Vec<Vec<String>> = tarjan_scc(&direct)
+        .into_iter()
+        .filter(|component| component.len() > 1 || direct.contains_edge(component[0], component[0]))
+        .filter_map(|component| {
+            let start = *component.iter().min_by_key(|&&node| &direct[node])?;
+            let members: HashSet<NodeIndex> = component.into_iter().collect();
+            shortest_cycle(&direct, start, &members)
+                .map(|nodes| nodes.into_iter().map(|node| direct[node].clone()).collect())
+        })
+        .collect();
+    
+    cycles.sort();
+    if !cycles.is_empty() {
+        warn!("Found {} dependency cycle(s)", cycles.len());
     }
+    
+    cycles
 }
 
-/// Check if a direct edge exists between two nodes
-fn direct_edge_exists(graph: &DiGraph<String, String>, from: NodeIndex, to: NodeIndex) -> bool {
-    graph.edges_connecting(from, to).next().is_some()
+/// Breadth-first search for the shortest path from `start` back to itself within a component
+fn shortest_cycle(
+    graph: &DiGraph<String, ()>,
+    start: NodeIndex,
+    members: &HashSet<NodeIndex>,
+) -> Option<Vec<NodeIndex>> {
+    let mut parents: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+    let mut queue = std::collections::VecDeque::from([start]);
+    
+    while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors(node) {
+            if next == start {
+                let mut path = vec![node];
+                while let Some(&parent) = parents.get(path.last()?) {
+                    path.push(parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if members.contains(&next) && !parents.contains_key(&next) {
+                parents.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    
+    None
+}
+
+/// Builds a recommendation for each dependency cycle in the graph
+pub fn cycle_recommendations(graph: &AdvancedDependencyGraph) -> Vec<Recommendation> {
+    graph.cycles.iter()
+        .map(|cycle| Recommendation {
+            description: format!(
+                "Circular dependency: {} -> {}",
+                cycle.join(" -> "), cycle[0]
+            ),
+            value: cycle.len().to_string(),
+            details: Some("Packages in a dependency loop must be installed and upgraded together".to_string()),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::Low,
+        })
+        .collect()
+}
+
+/// One line of the indented dependency tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Nesting level (0 for top-level packages)
+    pub depth: usize,
+    /// Package name
+    pub name: String,
+    /// Whether this package's dependencies were already listed earlier in the tree
+    pub repeated: bool,
 }
 
-/// Find transitive dependencies using graph traversal
+/// Flattens the direct dependency edges into an indented tree. Top-level entries are
+/// packages no other package depends on (or every package, if they all form cycles);
+/// children are sorted by name, and a package is only expanded the first time it appears
+pub fn dependency_tree(graph: &AdvancedDependencyGraph) -> Vec<TreeEntry> {
+    let children = |node: NodeIndex| {
+        let mut children: Vec<NodeIndex> = graph.graph.edges(node)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct)
+            .map(|edge| edge.target())
+            .collect();
+        children.sort_by(|a, b| graph.graph[*a].cmp(&graph.graph[*b]));
+        children.dedup();
+        children
+    };
+    
+    let mut roots: Vec<NodeIndex> = graph.graph.node_indices()
+        .filter(|&node| {
+            !graph.graph.edges_directed(node, Direction::Incoming)
+                .any(|edge| *edge.weight() == EdgeKind::Direct && edge.source() != node)
+        })
+        .collect();
+    if roots.is_empty() {
+        roots = graph.graph.node_indices().collect();
+    }
+    roots.sort_by(|a, b| graph.graph[*a].cmp(&graph.graph[*b]));
+    
+    let mut entries = Vec::new();
+    let mut expanded = HashSet::new();
+    // Depth-first, so push children in reverse to visit them in name order
+    let mut stack: Vec<(NodeIndex, usize)> = roots.into_iter().rev().map(|node| (node, 0)).collect();
+    while let Some((node, depth)) = stack.pop() {
+        let repeated = !expanded.insert(node);
+        entries.push(TreeEntry {
+            depth,
+            name: graph.graph[node].clone(),
+            repeated: repeated && !children(node).is_empty(),
+        });
+        if !repeated {
+            stack.extend(children(node).into_iter().rev().map(|child| (child, depth + 1)));
+        }
+    }
+    
+    entries
+}
+
+/// Most dependency chains [`dependency_chains`] returns, since their number can grow
+/// exponentially with the depth of the graph
+pub const MAX_DEPENDENCY_CHAINS: usize = 100;
+
+/// Every chain of direct dependency edges that pulls `package` into the environment, like
+/// `pipdeptree --reverse`: each chain starts at a package nothing else depends on (or where
+/// a cycle closes) and ends with `package`, e.g. `["scikit-learn", "scipy", "numpy"]`.
+/// Returns `None` if the package isn't in the graph, and no chains if nothing depends on it.
+/// Chains are sorted, and at most [`MAX_DEPENDENCY_CHAINS`] are returned
+pub fn dependency_chains(graph: &AdvancedDependencyGraph, package: &str) -> Option<Vec<Vec<String>>> {
+    let target = graph.node_map.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(package))
+        .map(|(_, &node)| node)?;
+    
+    let dependents = |node: NodeIndex| {
+        graph.graph.edges_directed(node, Direction::Incoming)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct && edge.source() != node)
+            .map(|edge| edge.source())
+            .collect::<Vec<_>>()
+    };
+    
+    // Walk up from the package, keeping the current path (package first) to skip cycles
+    let mut chains = Vec::new();
+    let mut stack = vec![vec![target]];
+    while let Some(path) = stack.pop() {
+        if chains.len() >= MAX_DEPENDENCY_CHAINS {
+            break;
+        }
+        let last = *path.last().unwrap();
+        let next: Vec<NodeIndex> = dependents(last).into_iter()
+            .filter(|dependent| !path.contains(dependent))
+            .collect();
+        
+        if next.is_empty() {
+            if path.len() > 1 {
+                chains.push(path.iter().rev().map(|&node| graph.graph[node].clone()).collect::<Vec<_>>());
+            }
+            continue;
+        }
+        for dependent in next {
+            let mut longer = path.clone();
+            longer.push(dependent);
+            stack.push(longer);
+        }
+    }
+    
+    chains.sort();
+    Some(chains)
+}
+
+/// Find each package's transitive dependencies: everything it reaches that is neither
+/// itself nor one of its direct dependencies. Reachable sets are computed once per strongly
+/// connected component in post order (dependencies before dependents) and reused, so shared
+/// subtrees such as a diamond (A->B->D, A->C->D) are walked only once. With a `max_depth`
+/// (other than 0), each package is instead expanded breadth-first up to that many levels
 fn find_transitive_dependencies(
     packages: &[Package],
     dependency_map: &HashMap<String, Vec<String>>,
+    max_depth: Option<usize>,
 ) -> HashMap<String, HashSet<String>> {
-    let mut transitive_deps: HashMap<String, HashSet<String>> = HashMap::new();
-    
     // Build a temporary graph for traversal
     let mut graph = DiGraph::<String, ()>::new();
     let mut node_map = HashMap::new();
@@ -129,89 +432,103 @@ fn find_transitive_dependencies(
     for (pkg_name, deps) in dependency_map {
         if let Some(&from_idx) = node_map.get(pkg_name) {
             for dep in deps {
-                if let Some(&to_idx) = node_map.get(dep) {
-                    graph.add_edge(from_idx, to_idx, ());
+                if let Some(&to_idx) = node_map.get(crate::parsers::spec_name(dep)) {
+                    graph.update_edge(from_idx, to_idx, ());
                 }
             }
         }
     }
     
-    // Find transitive deps for each package
+    let reachable = match max_depth.filter(|&depth| depth > 0) {
+        Some(depth) => reachable_within(&graph, depth),
+        None => reachable_sets(&graph),
+    };
+    
+    let mut transitive_deps: HashMap<String, HashSet<String>> = HashMap::new();
     for package in packages {
-        let mut visited = HashSet::new();
-        let mut deps = HashSet::new();
-        
-        if let Some(&node_idx) = node_map.get(&package.name) {
-            dfs_collect_deps(&graph, node_idx, &mut visited, &mut deps, &node_map);
-        }
-        
-        // Remove self from deps
-        deps.remove(&package.name);
-        
-        // Insert direct dependencies to ensure they're not counted as transitive
-        if let Some(direct_deps) = dependency_map.get(&package.name) {
-            for dep in direct_deps {
-                deps.remove(dep);
-            }
-        }
-        
+        let Some(&node_idx) = node_map.get(&package.name) else { continue };
+        let direct: HashSet<NodeIndex> = graph.neighbors(node_idx).collect();
+        let deps = reachable[node_idx.index()].iter()
+            .filter(|&&dep| dep != node_idx && !direct.contains(&dep))
+            .map(|&dep| graph[dep].clone())
+            .collect();
         transitive_deps.insert(package.name.clone(), deps);
     }
     
     transitive_deps
 }
 
-/// Depth-first search to collect all dependencies
-fn dfs_collect_deps(
-    graph: &DiGraph<String, ()>,
-    node: NodeIndex,
-    visited: &mut HashSet<NodeIndex>,
-    deps: &mut HashSet<String>,
-    node_map: &HashMap<String, NodeIndex>,
-) {
-    if visited.contains(&node) {
-        return;
+/// Every node reachable from each node in at most `max_depth` steps (indexed by node index),
+/// including the node itself only when a cycle leads back to it within that distance
+fn reachable_within(graph: &DiGraph<String, ()>, max_depth: usize) -> Vec<HashSet<NodeIndex>> {
+    graph.node_indices()
+        .map(|start| {
+            let mut reach = HashSet::new();
+            let mut frontier = vec![start];
+            for _ in 0..max_depth {
+                frontier = frontier.iter()
+                    .flat_map(|&node| graph.neighbors(node))
+                    .filter(|&next| reach.insert(next))
+                    .collect();
+                if frontier.is_empty() {
+                    break;
+                }
+            }
+            reach
+        })
+        .collect()
+}
+
+/// Every node reachable from each node (indexed by node index), including the node itself
+/// only when it is part of a cycle. `tarjan_scc` yields components in post order, so each
+/// component's successors are already resolved when it is reached
+fn reachable_sets(graph: &DiGraph<String, ()>) -> Vec<HashSet<NodeIndex>> {
+    let components = tarjan_scc(graph);
+    let mut component_of = vec![0; graph.node_count()];
+    for (index, component) in components.iter().enumerate() {
+        for node in component {
+            component_of[node.index()] = index;
+        }
     }
     
-    visited.insert(node);
-    let pkg_name = &graph[node];
-    deps.insert(pkg_name.clone());
-    
-    // Recursively visit neighbors
-    for edge in graph.edges(node) {
-        let neighbor = edge.target();
-        dfs_collect_deps(graph, neighbor, visited, deps, node_map);
+    let mut component_reach: Vec<HashSet<NodeIndex>> = Vec::with_capacity(components.len());
+    for (index, component) in components.iter().enumerate() {
+        let mut reach = HashSet::new();
+        for &node in component {
+            for next in graph.neighbors(node) {
+                let next_component = component_of[next.index()];
+                if next_component == index {
+                    // Inside a cycle (or a self-loop): every member reaches every other
+                    reach.extend(component.iter().copied());
+                } else {
+                    reach.insert(next);
+                    reach.extend(component_reach[next_component].iter().copied());
+                }
+            }
+        }
+        component_reach.push(reach);
     }
+    
+    graph.node_indices()
+        .map(|node| component_reach[component_of[node.index()]].clone())
+        .collect()
 }
 
-/// Detect version conflicts
-fn detect_conflicts(
-    packages: &[Package],
+/// Detect version conflicts: pairs of packages whose requirements on a shared dependency
+/// can't both be met, as (first package, second package, description)
+pub fn detect_conflicts(
     dependency_map: &HashMap<String, Vec<String>>,
 ) -> Vec<(String, String, String)> {
     let mut conflicts = Vec::new();
     
-    // Create a version map
-    let version_map: HashMap<_, _> = packages
-        .iter()
-        .filter_map(|p| {
-            p.version.as_ref().map(|v| (p.name.clone(), v.clone()))
-        })
-        .collect();
+    // Check each pair of packages that depend on the same package, in name order. Requirements
+    // are grouped by the bare name, so "libz >=2.0" and "libz <1.5" are compared
+    let mut shared_deps = std::collections::BTreeMap::new();
     
-    // Initialize dependency provider (used for debugging)
-    let _mock_provider = MockDependencyProvider {
-        packages: version_map.clone(),
-        dependencies: dependency_map.clone(),
-    };
-    
-    // Check each pair of packages that depend on the same package
-    let mut shared_deps = HashMap::new();
-    
-    for (pkg, deps) in dependency_map {
+    for (pkg, deps) in sorted_dependencies(dependency_map) {
         for dep in deps {
             shared_deps
-                .entry(dep.clone())
+                .entry(crate::parsers::spec_name(dep).to_string())
                 .or_insert_with(Vec::new)
                 .push(pkg.clone());
         }
@@ -236,7 +553,10 @@ fn detect_conflicts(
                         conflicts.push((
                             pkg1.clone(),
                             pkg2.clone(),
-                            format!("{} ({}≠{})", dep, ver1, ver2),
+                            format!(
+                                "{}: {} requires {}, {} requires {}; no version satisfies both",
+                                dep, pkg1, ver1.trim(), pkg2, ver2.trim()
+                            ),
                         ));
                     }
                 }
@@ -247,99 +567,156 @@ fn detect_conflicts(
     conflicts
 }
 
-/// Find version requirement for a dependency
+/// Find the version requirement `pkg` places on `dep`: the constraint of the dependency
+/// whose spec name is exactly `dep` ("*" when it has none), so `numpy` never matches `numpy-base`
 fn find_version_requirement(
     dependency_map: &HashMap<String, Vec<String>>,
     pkg: &str,
     dep: &str,
 ) -> Option<String> {
-    if let Some(deps) = dependency_map.get(pkg) {
-        // Find the dependency in the list
-        for dep_str in deps {
-            // Check if this dependency string corresponds to the dep we're looking for
-            if dep_str == dep {
-                // No version constraint specified
-                return Some("*".to_string());
-            } else if dep_str.starts_with(dep) {
-                // Parse version constraint - formats like "numpy>=1.0", "pandas==1.1.0"
-                let version_part = &dep_str[dep.len()..];
-                if !version_part.is_empty() {
-                    return Some(version_part.to_string());
-                }
-            } else if dep_str.contains(dep) {
-                // More complex format like "python-numpy>=1.0"
-                let mut parts = dep_str.split(&['=', '>', '<', '~', '^'][..]);
-                let dep_name = parts.next().unwrap_or("");
-                
-                if dep_name.contains(dep) {
-                    // Get the version part
-                    let version_op = dep_str.chars().find(|&c| c == '=' || c == '>' || c == '<' || c == '~' || c == '^');
-                    if let Some(op_char) = version_op {
-                        let op_pos = dep_str.find(op_char).unwrap();
-                        return Some(dep_str[op_pos..].to_string());
-                    }
-                }
-            }
-        }
-    }
-    None
+    dependency_map.get(pkg)?.iter()
+        .find(|dep_str| crate::parsers::spec_name(dep_str) == dep)
+        .map(|dep_str| {
+            let spec = dep_str.trim();
+            let spec = spec.split_once("::").map_or(spec, |(_, spec)| spec);
+            let constraint = spec[dep.len()..].trim();
+            if constraint.is_empty() { "*".to_string() } else { constraint.to_string() }
+        })
 }
 
-/// Check if two version requirements are compatible
+/// Check if two version requirements are compatible, i.e. some version satisfies both.
+/// A conda build string after the version (`3.9.* *_cpython`) is ignored
 fn versions_compatible(ver1: &str, ver2: &str) -> bool {
-    // Parse version requirements using semver if possible
-    if let (Ok(v1), Ok(v2)) = (semver::VersionReq::parse(ver1), semver::VersionReq::parse(ver2)) {
-        // Check if there's a version that satisfies both requirements
-        // We'll check a range of common versions to see if any satisfy both requirements
-        let test_versions = [
-            "0.1.0", "1.0.0", "1.1.0", "2.0.0", "3.0.0", "4.0.0", 
-            "1.2.3", "2.3.4", "3.4.5", "4.5.6"
-        ];
-        
-        for version_str in &test_versions {
-            if let Ok(version) = semver::Version::parse(version_str) {
-                if v1.matches(&version) && v2.matches(&version) {
-                    return true;
-                }
-            }
-        }
-        return false;
+    let spec = |requirement: &str| requirement.split_whitespace().next().unwrap_or("").to_string();
+    let (spec1, spec2) = (spec(ver1), spec(ver2));
+    
+    if let Some(overlap) = version::specs_overlap(&spec1, &spec2) {
+        return overlap;
     }
     
-    // If we can't parse as semver, check for exact equality
+    // If a bound isn't a valid version, check for exact equality
     // or if one is "any" (which means compatible with anything)
-    ver1 == ver2 || ver1 == "any" || ver2 == "any"
+    spec1 == spec2 || spec1 == "any" || spec2 == "any"
 }
 
-/// Export advanced dependency graph to DOT format
+/// Export advanced dependency graph to DOT format. `packages` are the environment's packages,
+/// used to group the nodes by channel when `options` ask for it
 pub fn export_advanced_dependency_graph<P: AsRef<Path>>(
     graph: &AdvancedDependencyGraph,
+    packages: &[Package],
     output_path: P,
+    options: &DotOptions,
 ) -> Result<()> {
     let mut file = File::create(output_path)
         .with_context(|| "Failed to create advanced graph file")?;
     
-    // Highlight direct dependencies
-    let dot = Dot::with_config(&graph.graph, &[Config::EdgeNoLabel]);
+    writeln!(file, "digraph {{")?;
+    writeln!(file, "  rankdir={};", options.rankdir)?;
+    for node in graph.graph.node_indices() {
+        writeln!(file, "  {};", dot_id(&graph.graph[node]))?;
+    }
+    
+    if options.cluster_by_channel {
+        write_channel_clusters(&mut file, graph.graph.node_weights().map(String::as_str), packages)?;
+    }
     
-    write!(file, "{:?}", dot)?;
+    // Direct dependencies are drawn solid, transitive ones dashed and grey
+    for edge in graph.graph.edge_references() {
+        let style = match edge.weight() {
+            EdgeKind::Direct => "color=black",
+            EdgeKind::Transitive => "color=gray, style=dashed",
+        };
+        writeln!(
+            file, "  {} -> {} [{}];",
+            dot_id(&graph.graph[edge.source()]), dot_id(&graph.graph[edge.target()]), style
+        )?;
+    }
+    
+    writeln!(file, "}}")?;
     
     Ok(())
 }
 
-/// Mock dependency provider for pubgrub solver
-struct MockDependencyProvider {
-    packages: HashMap<String, String>,
-    dependencies: HashMap<String, Vec<String>>,
+/// Name of the virtual package the solver starts from; it depends on every root package
+const SOLVER_ROOT: &str = "<environment>";
+
+/// A package version as seen by the PubGrub solver: its position among the package's
+/// available versions, oldest first. PubGrub needs every version to have a successor,
+/// which conda versions don't have but positions do. Only the position is compared;
+/// the version list is carried along so that conflict reports show real version strings
+#[derive(Debug, Clone)]
+pub struct SolverVersion {
+    /// Position counted from 1; 0 is PubGrub's lowest version, below every real one
+    index: u32,
+    /// The package's available versions, oldest first
+    versions: Option<Arc<Vec<String>>>,
+    /// Text shown instead of the version string
+    label: Option<String>,
 }
 
-/// Real dependency provider for PubGrub solver
+impl SolverVersion {
+    fn new(position: usize, versions: &Arc<Vec<String>>) -> Self {
+        SolverVersion { index: position as u32 + 1, versions: Some(Arc::clone(versions)), label: None }
+    }
+    
+    /// Version string this position stands for
+    fn as_str(&self) -> Option<&str> {
+        let position = (self.index as usize).checked_sub(1)?;
+        self.versions.as_ref()?.get(position).map(String::as_str)
+    }
+}
+
+impl PartialEq for SolverVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for SolverVersion {}
+
+impl PartialOrd for SolverVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SolverVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl std::fmt::Display for SolverVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.label, self.as_str()) {
+            (Some(label), _) => write!(f, "{}", label),
+            (None, Some(version)) => write!(f, "{}", version),
+            (None, None) if self.index == 0 => write!(f, "*"),
+            (None, None) => write!(f, "#{}", self.index),
+        }
+    }
+}
+
+impl PubgrubVersionTrait for SolverVersion {
+    fn lowest() -> Self {
+        SolverVersion { index: 0, versions: None, label: None }
+    }
+
+    fn bump(&self) -> Self {
+        SolverVersion { index: self.index + 1, versions: self.versions.clone(), label: None }
+    }
+}
+
+/// Dependency provider for the PubGrub solver, built from the versions and dependency
+/// constraints known for the environment
 #[derive(Clone)]
 pub struct CondaDependencyProvider {
-    /// Map of package names to their available versions
-    packages: HashMap<String, Vec<String>>,
+    /// Map of package names to their available versions, oldest first
+    packages: HashMap<String, Arc<Vec<String>>>,
     /// Map of package names and versions to their dependencies
     dependencies: HashMap<(String, String), Vec<(String, String)>>,
+    /// Root packages of the current `solve` call
+    roots: Vec<String>,
 }
 
 impl CondaDependencyProvider {
@@ -348,26 +725,37 @@ impl CondaDependencyProvider {
         let mut provider = CondaDependencyProvider {
             packages: HashMap::new(),
             dependencies: HashMap::new(),
+            roots: Vec::new(),
         };
         
         // Populate available packages and versions
+        let mut available: HashMap<String, Vec<String>> = HashMap::new();
         for package in packages {
             if let Some(version) = &package.version {
-                provider.packages
+                available
                     .entry(package.name.clone())
-                    .or_insert_with(Vec::new)
+                    .or_default()
                     .push(version.clone());
             }
         }
         
+        for (name, mut versions) in available {
+            versions.sort_by(|a, b| match (CondaVersion::parse(a), CondaVersion::parse(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            });
+            versions.dedup_by(|a, b| version::versions_match(a, b));
+            provider.packages.insert(name, Arc::new(versions));
+        }
+        
         // Populate dependencies
         for (pkg_name, deps) in dependency_map {
             if let Some(versions) = provider.packages.get(pkg_name) {
-                for version in versions {
+                for version in versions.iter() {
                     let mut parsed_deps = Vec::new();
                     
                     for dep_str in deps {
-                        // Parse dependencies like "numpy>=1.19.0"
+                        // Parse dependencies like "numpy>=1.19.0" or "python >=3.8,<3.12"
                         if let Some((dep_name, constraint)) = parse_dependency(dep_str) {
                             parsed_deps.push((dep_name, constraint));
                         }
@@ -381,72 +769,141 @@ impl CondaDependencyProvider {
         provider
     }
     
-    /// Solve dependencies for a set of root packages
-    pub fn solve(&self, root_packages: &[String]) -> Result<HashMap<String, String>, String> {
-        let mut solution = HashMap::new();
-        let mut visited = HashSet::new();
+    /// Resolve a set of root packages to one version each that satisfies every dependency
+    /// constraint. Constraints on packages that aren't part of the environment are ignored.
+    /// Returns `PubGrubError::NoSolution` with the derivation tree when the constraints conflict
+    /// (boxed, as the error is several hundred bytes).
+    ///
+    /// Library-only: the CLI's conflict checks use `detect_conflicts`, which compares the
+    /// requirements pairwise and doesn't call the solver
+    pub fn solve(&self, root_packages: &[String]) -> Result<HashMap<String, String>, Box<PubGrubError<String, SolverVersion>>> {
+        let provider = CondaDependencyProvider {
+            roots: root_packages.to_vec(),
+            ..self.clone()
+        };
+        
+        let selected = pubgrub::solver::resolve(&provider, SOLVER_ROOT.to_string(), SolverVersion::lowest())
+            .map_err(Box::new)?;
+        
+        Ok(selected.into_iter()
+            .filter(|(name, _)| name != SOLVER_ROOT)
+            .filter_map(|(name, version)| Some((name, version.as_str()?.to_string())))
+            .collect())
+    }
+    
+    /// Range of positions of `package`'s versions that satisfy `constraint`
+    fn constraint_range(&self, package: &str, constraint: &str) -> Range<SolverVersion> {
+        let versions = &self.packages[package];
+        let mut range = Range::none();
+        let mut index = 0;
         
-        // For each root package, add it and its dependencies
-        for pkg in root_packages {
-            if visited.contains(pkg) {
+        // Each run of consecutive matching versions becomes one interval
+        while index < versions.len() {
+            if !version::matches_spec(&versions[index], constraint) {
+                index += 1;
                 continue;
             }
             
-            if let Err(e) = self.add_package_to_solution(pkg, &mut solution, &mut visited) {
-                return Err(format!("Failed to resolve dependencies: {}", e));
+            let start = index;
+            while index < versions.len() && version::matches_spec(&versions[index], constraint) {
+                index += 1;
             }
+            
+            let interval = if index == versions.len() {
+                Range::higher_than(SolverVersion::new(start, versions))
+            } else {
+                Range::between(SolverVersion::new(start, versions), SolverVersion::new(index, versions))
+            };
+            range = range.union(&interval);
         }
         
-        Ok(solution)
-    }
-    
-    /// Add a package and its dependencies to the solution
-    fn add_package_to_solution(
-        &self, 
-        pkg: &str, 
-        solution: &mut HashMap<String, String>,
-        visited: &mut HashSet<String>
-    ) -> Result<(), String> {
-        if visited.contains(pkg) {
-            return Ok(());
-        }
-        
-        visited.insert(pkg.to_string());
-        
-        // If the package is already in the solution, we're done
-        if solution.contains_key(pkg) {
-            return Ok(());
+        // Nothing matches: point past the newest version, so conflict reports name the constraint
+        if range == Range::none() {
+            return Range::exact(SolverVersion {
+                label: Some(constraint.to_string()),
+                ..SolverVersion::new(versions.len(), versions)
+            });
         }
         
-        // Find the latest version of the package
-        let versions = self.packages.get(pkg)
-            .ok_or_else(|| format!("Package {} not found", pkg))?;
+        range
+    }
+}
+
+impl DependencyProvider<String, SolverVersion> for CondaDependencyProvider {
+    fn choose_package_version<T: std::borrow::Borrow<String>, U: std::borrow::Borrow<Range<SolverVersion>>>(
+        &self,
+        potential_packages: impl Iterator<Item = (T, U)>,
+    ) -> Result<(T, Option<SolverVersion>), Box<dyn std::error::Error>> {
+        // Prefer the newest version of the most constrained package
+        Ok(pubgrub::solver::choose_package_with_fewest_versions(
+            |package: &String| -> std::vec::IntoIter<SolverVersion> {
+                if package == SOLVER_ROOT {
+                    return vec![SolverVersion::lowest()].into_iter();
+                }
+                let Some(versions) = self.packages.get(package) else {
+                    return Vec::new().into_iter();
+                };
+                (0..versions.len())
+                    .rev()
+                    .map(|position| SolverVersion::new(position, versions))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+            },
+            potential_packages,
+        ))
+    }
+    
+    fn get_dependencies(
+        &self,
+        package: &String,
+        version: &SolverVersion,
+    ) -> Result<Dependencies<String, SolverVersion>, Box<dyn std::error::Error>> {
+        let mut constraints: pubgrub::type_aliases::Map<String, Range<SolverVersion>> = Default::default();
         
-        if versions.is_empty() {
-            return Err(format!("No versions available for package {}", pkg));
+        if package == SOLVER_ROOT {
+            for root in &self.roots {
+                if self.packages.contains_key(root) {
+                    constraints.insert(root.clone(), Range::any());
+                } else {
+                    debug!("Ignoring root package {} with no known version", root);
+                }
+            }
+            return Ok(Dependencies::Known(constraints));
         }
         
-        // Sort versions in descending order (latest first)
-        let mut sorted_versions = versions.clone();
-        sorted_versions.sort_by(|a, b| {
-            let a_semver = semver::Version::parse(a).unwrap_or_else(|_| semver::Version::new(0, 0, 0));
-            let b_semver = semver::Version::parse(b).unwrap_or_else(|_| semver::Version::new(0, 0, 0));
-            b_semver.cmp(&a_semver)
-        });
-        
-        let latest_version = &sorted_versions[0];
-        
-        // Add the package to the solution
-        solution.insert(pkg.to_string(), latest_version.clone());
+        let Some(label) = version.as_str() else {
+            return Ok(Dependencies::Unknown);
+        };
         
-        // Add dependencies
-        if let Some(deps) = self.dependencies.get(&(pkg.to_string(), latest_version.clone())) {
-            for (dep_name, _) in deps {
-                self.add_package_to_solution(dep_name, solution, visited)?;
+        // Packages without dependency information are treated as having no dependencies
+        if let Some(deps) = self.dependencies.get(&(package.clone(), label.to_string())) {
+            for (dep_name, constraint) in deps {
+                if !self.packages.contains_key(dep_name) {
+                    debug!("Ignoring dependency {} of {} {}: not in the environment", dep_name, package, label);
+                    continue;
+                }
+                let range = self.constraint_range(dep_name, constraint);
+                let range = match constraints.get(dep_name) {
+                    Some(existing) => existing.intersection(&range),
+                    None => range,
+                };
+                constraints.insert(dep_name.clone(), range);
             }
         }
         
-        Ok(())
+        Ok(Dependencies::Known(constraints))
+    }
+}
+
+/// Human-readable explanation of a failed resolution
+pub fn describe_solver_error(error: &PubGrubError<String, SolverVersion>) -> String {
+    match error {
+        PubGrubError::NoSolution(tree) => {
+            let mut tree = tree.clone();
+            tree.collapse_no_versions();
+            DefaultStringReporter::report(&tree)
+        }
+        other => other.to_string(),
     }
 }
 
@@ -455,29 +912,187 @@ fn parse_dependency(dep_str: &str) -> Option<(String, String)> {
     // Handle different formats:
     // - "numpy>=1.19.0"
     // - "pandas==1.3.0"
+    // - "python >=3.8,<3.12" (conda match spec; a trailing build string is dropped)
     // - "python"
     
-    let re = Regex::new(r"^([a-zA-Z0-9_-]+)([<>=~^]+.+)?$").ok()?;
-    let captures = re.captures(dep_str)?;
+    let dep_str = dep_str.trim();
+    let name_end = dep_str
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+        .unwrap_or(dep_str.len());
+    let name = &dep_str[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+    
+    let constraint = dep_str[name_end..].split_whitespace().next().unwrap_or("");
+    
+    Some((name.to_string(), constraint.to_string()))
+}
+
+/// File listing accepted vulnerabilities, looked up next to the environment file and
+/// then in the current directory
+pub const VULNERABILITY_IGNORE_FILE: &str = ".conda-inspect-ignore";
+
+/// Vulnerabilities accepted as known risks, which are left out of scan results.
+/// Entries are advisory IDs (`CVE-2021-44228`, compared case-insensitively) or
+/// `package@version` pairs that suppress every finding for that exact package version
+#[derive(Debug, Clone, Default)]
+pub struct VulnerabilityIgnoreList {
+    ids: HashSet<String>,
+    package_versions: Vec<(String, String)>,
+}
+
+impl VulnerabilityIgnoreList {
+    /// Parse an ignore file: one entry per line, with `#` starting a comment
+    pub fn parse(content: &str) -> Self {
+        let mut list = Self::default();
+        for line in content.lines() {
+            let entry = line.split('#').next().unwrap_or("").trim();
+            if !entry.is_empty() {
+                list.add(entry);
+            }
+        }
+        list
+    }
+    
+    /// Read an ignore file from disk
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = crate::parsers::read_text_file(&path)
+            .with_context(|| format!("Failed to read vulnerability ignore file: {:?}", path.as_ref()))?;
+        Ok(Self::parse(&content))
+    }
+    
+    /// Add a single advisory ID or `package@version` entry
+    pub fn add(&mut self, entry: &str) {
+        let entry = entry.trim();
+        match entry.split_once('@') {
+            Some((package, version)) if !package.is_empty() && !version.is_empty() => {
+                self.package_versions.push((package.trim().to_lowercase(), version.trim().to_string()));
+            },
+            _ => {
+                self.ids.insert(entry.to_uppercase());
+            },
+        }
+    }
+    
+    /// Whether the list has no entries
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.package_versions.is_empty()
+    }
     
-    let name = captures.get(1)?.as_str().to_string();
-    let constraint = captures.get(2)
-        .map(|m| m.as_str().to_string())
-        .unwrap_or_else(|| "".to_string());
+    /// Whether a finding is accepted by this list
+    pub fn matches(&self, vulnerability: &Vulnerability) -> bool {
+        let id_ignored = vulnerability.id.as_ref()
+            .is_some_and(|id| self.ids.contains(&id.to_uppercase()));
+        id_ignored || self.package_versions.iter().any(|(package, version)| {
+            *package == vulnerability.package.to_lowercase()
+                && version::versions_match(version, &vulnerability.version)
+        })
+    }
+}
+
+/// Bundled local vulnerability database, checked offline before any network source
+const BUILTIN_LOCAL_VULNERABILITY_DB: &str = include_str!("../data/local-vulnerabilities.json");
+
+/// File names of a user's local vulnerability database in the config directory
+/// (e.g. ~/.config/conda-env-inspect/vulnerabilities.json), in lookup order
+pub const LOCAL_VULNERABILITY_DB_FILES: &[&str] = &["vulnerabilities.json", "vulnerabilities.yml", "vulnerabilities.yaml"];
+
+/// One known vulnerability in the local database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalVulnerability {
+    /// Affected package name (compared case-insensitively)
+    pub name: String,
+    /// Affected versions as a conda/pip version spec, e.g. `<1.19.1` or `>=2.0,<2.4|>=3.0,<3.0.4`
+    pub affected: String,
+    /// Human-readable description of the issue
+    pub description: String,
+    /// CVE identifier, if any
+    #[serde(default)]
+    pub cve: Option<String>,
+    /// Severity, if known
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+/// Parse a local vulnerability database: a JSON or YAML list of entries
+pub fn parse_local_vulnerability_db(content: &str, yaml: bool) -> Result<Vec<LocalVulnerability>> {
+    if yaml {
+        serde_yaml::from_str(content).context("Invalid YAML vulnerability database")
+    } else {
+        serde_json::from_str(content).context("Invalid JSON vulnerability database")
+    }
+}
+
+/// Read a local vulnerability database from disk; `.yml` and `.yaml` files are read as YAML
+pub fn load_local_vulnerability_db<P: AsRef<Path>>(path: P) -> Result<Vec<LocalVulnerability>> {
+    let path = path.as_ref();
+    let content = crate::parsers::read_text_file(path)
+        .with_context(|| format!("Failed to read vulnerability database: {:?}", path))?;
+    let yaml = matches!(path.extension().and_then(|ext| ext.to_str()), Some("yml" | "yaml"));
+    parse_local_vulnerability_db(&content, yaml)
+        .with_context(|| format!("Failed to load vulnerability database: {:?}", path))
+}
+
+/// Read a user's vulnerability database: `path` if given (`--vuln-db`), otherwise the first
+/// [`LOCAL_VULNERABILITY_DB_FILES`] found in the config directory. Empty when there is none.
+/// Pass the entries in [`LookupOptions::local_vulnerabilities`] to check them
+pub fn load_user_vulnerability_db(path: Option<&Path>) -> Result<Vec<LocalVulnerability>> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let config_dir = dirs::config_dir().map(|dir| dir.join("conda-env-inspect"));
+            match config_dir.and_then(|dir| {
+                LOCAL_VULNERABILITY_DB_FILES.iter().map(|file| dir.join(file)).find(|path| path.is_file())
+            }) {
+                Some(path) => path,
+                None => return Ok(Vec::new()),
+            }
+        },
+    };
     
-    Some((name, constraint))
+    let entries = load_local_vulnerability_db(&path)?;
+    info!("Using {} vulnerability database entries from {:?}", entries.len(), path);
+    Ok(entries)
+}
+
+/// The local vulnerability database: the bundled entries with a user's entries merged in.
+/// A user entry for the same package and CVE as a bundled one replaces it; others are added
+fn local_vulnerability_db(user_entries: &[LocalVulnerability]) -> Vec<LocalVulnerability> {
+    let mut db: Vec<LocalVulnerability> = serde_json::from_str(BUILTIN_LOCAL_VULNERABILITY_DB)
+        .expect("bundled vulnerability database is valid JSON");
+    for entry in user_entries.iter().cloned() {
+        let existing = db.iter().position(|known| {
+            known.name.eq_ignore_ascii_case(&entry.name)
+                && (known.cve.is_some() && known.cve == entry.cve || *known == entry)
+        });
+        match existing {
+            Some(index) => db[index] = entry,
+            None => db.push(entry),
+        }
+    }
+    
+    db
 }
 
 /// Find environment-wide vulnerability issues using multiple security databases
-pub fn find_vulnerabilities(packages: &[Package]) -> Vec<(String, String, String)> {
+pub fn find_vulnerabilities(packages: &[Package], lookup: &LookupOptions) -> Vec<Vulnerability> {
+    find_vulnerabilities_with_ignore(packages, &VulnerabilityIgnoreList::default(), lookup).0
+}
+
+/// Find vulnerabilities like [`find_vulnerabilities`], leaving out the findings accepted by
+/// `ignore`. Returns the remaining findings and the number that were suppressed
+pub fn find_vulnerabilities_with_ignore(
+    packages: &[Package],
+    ignore: &VulnerabilityIgnoreList,
+    lookup: &LookupOptions,
+) -> (Vec<Vulnerability>, usize) {
     info!("Scanning {} packages for security vulnerabilities", packages.len());
     let mut vulnerabilities = Vec::new();
     
     // Set up HTTP client for API requests
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .unwrap_or_default();
+    let client = conda_api::build_http_client(&lookup.network);
+    let local_db = local_vulnerability_db(&lookup.local_vulnerabilities);
 
     // For each package, check multiple vulnerability sources
     for package in packages {
@@ -485,125 +1100,230 @@ pub fn find_vulnerabilities(packages: &[Package]) -> Vec<(String, String, String
             debug!("Checking vulnerabilities for {} {}", package.name, version);
             
             // 1. Check local vulnerability database first (fast and doesn't require network)
-            check_local_vulnerability_db(package, version, &mut vulnerabilities);
-            
-            // 2. Check OSV database (Open Source Vulnerabilities)
-            if let Err(e) = check_osv_database(&client, package, version, &mut vulnerabilities) {
-                warn!("OSV API error for {}: {}", package.name, e);
-            }
+            check_local_vulnerability_db(package, version, &local_db, &mut vulnerabilities);
             
-            // 3. Check PyPI Security Advisories for Python packages
+            // 2. Check PyPI Security Advisories for Python packages
             if package.channel.as_deref().map_or(false, |c| c == "pip" || c == "conda-forge") {
-                if let Err(e) = check_pypi_security(&client, package, version, &mut vulnerabilities) {
+                if let Err(e) = check_pypi_security(&client, package, version, &mut vulnerabilities, lookup) {
                     warn!("PyPI security API error for {}: {}", package.name, e);
                 }
             }
             
+            // 3. Check whether the exact pip release was yanked from PyPI
+            if package.channel.as_deref() == Some("pip") && package.pin_status == PinStatus::Exact {
+                if let Err(e) = check_pypi_yanked(&client, package, version, &mut vulnerabilities, lookup) {
+                    warn!("PyPI yanked check failed for {} {}: {}", package.name, version, e);
+                }
+            }
+            
             // 4. Check for significantly outdated packages that might be vulnerable
             check_version_gap(package, version, &mut vulnerabilities);
         }
     }
     
+    // 5. Check OSV database (Open Source Vulnerabilities), many packages per request
+    let versioned: Vec<(&Package, &str)> = packages.iter()
+        .filter_map(|package| package.version.as_deref().map(|version| (package, version)))
+        .collect();
+    if let Err(e) = check_osv_database_batch(&client, &versioned, &mut vulnerabilities, lookup) {
+        warn!("OSV API error: {}", e);
+    }
+    
     // Deduplicate vulnerabilities
     deduplicate_vulnerabilities(&mut vulnerabilities);
     
+    // Drop accepted findings
+    let found = vulnerabilities.len();
+    vulnerabilities.retain(|v| !ignore.matches(v));
+    let suppressed = found - vulnerabilities.len();
+    if suppressed > 0 {
+        info!("Suppressed {} vulnerabilities listed in the ignore list", suppressed);
+    }
+    
     info!("Found {} vulnerabilities across {} packages", 
           vulnerabilities.len(), packages.len());
     
-    vulnerabilities
+    (vulnerabilities, suppressed)
 }
 
 /// Check the local vulnerability database (known vulnerabilities stored locally)
 fn check_local_vulnerability_db(
     package: &Package, 
     version: &str, 
-    vulnerabilities: &mut Vec<(String, String, String)>
+    local_db: &[LocalVulnerability],
+    vulnerabilities: &mut Vec<Vulnerability>
 ) {
-    // Define a local database of known vulnerabilities for offline checking
-    // This could be expanded to read from a local file or database
-    let known_vulnerabilities = [
-        ("log4j", "2.0", "Log4Shell vulnerability, CVE-2021-44228"),
-        ("numpy", "1.19.0", "Buffer overflow in numpy.lib.arraypad, CVE-2021-33430"),
-        ("tensorflow", "2.4.0", "Integer overflow in TensorFlow, CVE-2021-37678"),
-        ("torch", "1.4", "Improper size validation in older PyTorch, CVE-2022-45907"),
-        ("pillow", "8.3.0", "Multiple buffer overflow vulnerabilities, CVE-2021-34552"),
-        ("django", "2.0", "XSS vulnerability in Django admin, CVE-2019-19844"),
-        ("django", "1.11", "Potential SQL injection in Django, CVE-2020-9402"),
-        ("requests", "2.2", "SSRF vulnerability in Requests, CVE-2018-18074"),
-        ("flask", "0.12", "Session fixation in Flask, CVE-2018-1000656"),
-        ("jinja2", "2.10", "Sandbox bypass in Jinja2, CVE-2019-10906"),
-        ("sqlalchemy", "1.3.0", "SQL injection in SQLAlchemy, CVE-2019-7164"),
-        ("cryptography", "2.8", "Improper certificate validation, CVE-2020-25659"),
-        ("werkzeug", "0.14", "Open redirect vulnerability, CVE-2019-14806"),
-        ("click", "7.0", "Command argument injection, CVE-2021-29622"),
-        ("pandas", "0.24", "Use-after-free in read_stata, CVE-2020-13091"),
-        ("nltk", "3.4", "Arbitrary code execution in nltk, CVE-2019-14751"),
-        ("lxml", "4.6.2", "XML external entity vulnerability, CVE-2021-28957"),
-        ("psycopg2", "2.8.5", "SQL injection vulnerability, CVE-2022-31116"),
-        ("scipy", "1.5.0", "Buffer overflow in scipy.special, CVE-2020-15864"),
-        ("tornado", "6.0.3", "Improper certificate validation, CVE-2020-28476"),
-    ];
-    
-    for &(pkg, ver, desc) in &known_vulnerabilities {
-        if package.name == pkg && is_vulnerable_version(version, ver) {
-            vulnerabilities.push((
-                package.name.clone(),
-                version.to_string(),
-                desc.to_string(),
-            ));
-        }
-    }
-}
-
-/// Check if a version is vulnerable based on a version pattern
-fn is_vulnerable_version(version: &str, vulnerable_pattern: &str) -> bool {
-    // Simple check: if the version starts with the vulnerable pattern
-    if version.starts_with(vulnerable_pattern) {
-        return true;
-    }
-    
-    // Try to parse as semver
-    if let (Ok(version_semver), Ok(pattern_semver)) = 
-        (semver::Version::parse(version), semver::Version::parse(vulnerable_pattern)) {
-        // Check if version is the same or older than the vulnerable version
-        version_semver <= pattern_semver
+    for entry in local_db {
+        if package.name.eq_ignore_ascii_case(&entry.name) && is_version_affected(version, &entry.affected) {
+            vulnerabilities.push(Vulnerability {
+                package: package.name.clone(),
+                version: version.to_string(),
+                id: entry.cve.clone(),
+                severity: entry.severity.map(|severity| severity.to_string()),
+                description: entry.description.clone(),
+                source: "local-db".to_string(),
+            });
+        }
+    }
+}
+
+/// Number of packages sent per OSV `querybatch` request
+const OSV_BATCH_SIZE: usize = 100;
+
+/// How often a request is retried after the OSV API answers HTTP 429 (Too Many Requests)
+const OSV_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// OSV ecosystem of a package
+fn osv_ecosystem(package: &Package) -> &'static str {
+    if package.channel.as_deref() == Some("pip") {
+        "PyPI"
     } else {
-        // If parsing fails, do a fallback string compare
-        version.trim() == vulnerable_pattern.trim()
+        "Conda"
+    }
+}
+
+/// Send a request to the OSV API, backing off when it is rate limited (HTTP 429).
+/// The wait honors a `Retry-After` header in seconds, and otherwise doubles from 1s
+fn send_rate_limited(
+    request: reqwest::blocking::RequestBuilder,
+    network: &conda_api::NetworkConfig,
+) -> Result<reqwest::blocking::Response, String> {
+    let mut attempt = 0;
+    
+    loop {
+        let current = request.try_clone()
+            .ok_or_else(|| "OSV request cannot be retried".to_string())?;
+        let response = conda_api::send_with_retry(current, network)
+            .map_err(|e| format!("OSV API request failed: {}", e))?;
+        
+        if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || attempt >= OSV_RATE_LIMIT_RETRIES {
+            return Ok(response);
+        }
+        
+        let wait = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_secs(2u64.pow(attempt)));
+        warn!("OSV API rate limit hit, retrying in {:?} ({}/{})", wait, attempt + 1, OSV_RATE_LIMIT_RETRIES);
+        std::thread::sleep(wait);
+        attempt += 1;
+    }
+}
+
+/// Check the OSV database for many packages at once using the `querybatch` endpoint.
+/// Batch results only carry advisory IDs, so the details of each distinct advisory are
+/// fetched afterwards; packages with more results than fit in one page are queried on their own
+fn check_osv_database_batch(
+    client: &reqwest::blocking::Client,
+    packages: &[(&Package, &str)],
+    vulnerabilities: &mut Vec<Vulnerability>,
+    lookup: &LookupOptions,
+) -> Result<(), String> {
+    if lookup.offline || packages.is_empty() {
+        return Ok(());
+    }
+    
+    let url = "https://api.osv.dev/v1/querybatch";
+    let mut details: HashMap<String, Option<serde_json::Value>> = HashMap::new();
+    
+    for chunk in packages.chunks(OSV_BATCH_SIZE) {
+        debug!("Querying OSV for a batch of {} packages", chunk.len());
+        let queries: Vec<serde_json::Value> = chunk.iter()
+            .map(|(package, version)| serde_json::json!({
+                "package": {
+                    "name": package.name,
+                    "ecosystem": osv_ecosystem(package)
+                },
+                "version": version
+            }))
+            .collect();
+        
+        let response = send_rate_limited(client.post(url).json(&serde_json::json!({ "queries": queries })), &lookup.network)?;
+        if !response.status().is_success() {
+            return Err(format!("OSV API error: HTTP {}", response.status()));
+        }
+        
+        let batch_response: serde_json::Value = response.json()
+            .map_err(|e| format!("Failed to parse OSV batch response: {}", e))?;
+        let results = batch_response["results"].as_array()
+            .ok_or_else(|| "OSV batch response has no results".to_string())?;
+        
+        // Results are returned in the order of the queries
+        for (&(package, version), result) in chunk.iter().zip(results) {
+            if result["next_page_token"].as_str().is_some() {
+                if let Err(e) = check_osv_database(client, package, version, vulnerabilities, lookup) {
+                    warn!("OSV API error for {}: {}", package.name, e);
+                }
+                continue;
+            }
+            
+            let ids = result["vulns"].as_array().into_iter().flatten()
+                .filter_map(|vuln| vuln["id"].as_str());
+            for id in ids {
+                let vuln = details.entry(id.to_string())
+                    .or_insert_with(|| fetch_osv_vulnerability(client, id, lookup));
+                let summary = vuln.as_ref()
+                    .and_then(|vuln| vuln["summary"].as_str().or_else(|| vuln["details"].as_str()))
+                    .unwrap_or("See advisory for details");
+                vulnerabilities.push(Vulnerability {
+                    package: package.name.clone(),
+                    version: version.to_string(),
+                    id: Some(id.to_string()),
+                    severity: vuln.as_ref().and_then(osv_severity),
+                    description: summary.to_string(),
+                    source: "osv".to_string(),
+                });
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Fetch the full record of an OSV advisory, or `None` if it can't be retrieved
+fn fetch_osv_vulnerability(client: &reqwest::blocking::Client, id: &str, lookup: &LookupOptions) -> Option<serde_json::Value> {
+    let url = format!("https://api.osv.dev/v1/vulns/{}", id);
+    match send_rate_limited(client.get(&url), &lookup.network) {
+        Ok(response) if response.status().is_success() => response.json().ok(),
+        Ok(response) => {
+            warn!("OSV API error for advisory {}: HTTP {}", id, response.status());
+            None
+        },
+        Err(e) => {
+            warn!("OSV API error for advisory {}: {}", id, e);
+            None
+        },
     }
 }
 
-/// Check the OSV (Open Source Vulnerabilities) database
+/// Check the OSV (Open Source Vulnerabilities) database for a single package
 fn check_osv_database(
     client: &reqwest::blocking::Client,
     package: &Package,
     version: &str,
-    vulnerabilities: &mut Vec<(String, String, String)>
+    vulnerabilities: &mut Vec<Vulnerability>,
+    lookup: &LookupOptions,
 ) -> Result<(), String> {
-    debug!("Checking OSV database for {} {}", package.name, version);
+    if lookup.offline {
+        return Ok(());
+    }
     
-    // Determine the proper ecosystem
-    let ecosystem = if package.channel.as_deref() == Some("pip") {
-        "PyPI"
-    } else {
-        "Conda"
-    };
+    debug!("Checking OSV database for {} {}", package.name, version);
     
     // Prepare the API request
     let url = "https://api.osv.dev/v1/query";
     let request_body = serde_json::json!({
         "package": {
             "name": package.name,
-            "ecosystem": ecosystem
+            "ecosystem": osv_ecosystem(package)
         },
         "version": version
     });
     
     // Make the API request
-    let response = client.post(url)
-        .json(&request_body)
-        .send()
-        .map_err(|e| format!("OSV API request failed: {}", e))?;
+    let response = send_rate_limited(client.post(url).json(&request_body), &lookup.network)?;
     
     if !response.status().is_success() {
         return Err(format!("OSV API error: HTTP {}", response.status()));
@@ -617,12 +1337,14 @@ fn check_osv_database(
     if let Some(vulns) = osv_response["vulns"].as_array() {
         for vuln in vulns {
             if let (Some(id), Some(summary)) = (vuln["id"].as_str(), vuln["summary"].as_str()) {
-                let description = format!("{} ({})", summary, id);
-                vulnerabilities.push((
-                    package.name.clone(),
-                    version.to_string(),
-                    description,
-                ));
+                vulnerabilities.push(Vulnerability {
+                    package: package.name.clone(),
+                    version: version.to_string(),
+                    id: Some(id.to_string()),
+                    severity: osv_severity(vuln),
+                    description: summary.to_string(),
+                    source: "osv".to_string(),
+                });
             }
         }
     }
@@ -630,40 +1352,110 @@ fn check_osv_database(
     Ok(())
 }
 
+/// Read the severity of an OSV advisory from its database-specific data (as reported by
+/// GitHub advisories), normalized to "low", "medium", "high" or "critical"
+fn osv_severity(vuln: &serde_json::Value) -> Option<String> {
+    let severity = vuln["database_specific"]["severity"].as_str()
+        .or_else(|| {
+            vuln["affected"].as_array()?.iter()
+                .find_map(|affected| affected["ecosystem_specific"]["severity"].as_str())
+        })?;
+    
+    Severity::from_name(severity).map(|severity| severity.to_string())
+}
+
+/// Load the Safety DB from the on-disk cache when it is fresh, otherwise download it and
+/// store it for later runs. Offline, any downloaded copy is used regardless of its age,
+/// and `None` is returned if there is none
+fn load_safety_db(client: &reqwest::blocking::Client, lookup: &LookupOptions) -> Result<Option<serde_json::Value>, String> {
+    let offline = lookup.offline;
+    let path = crate::performance::CacheManager::new().map(|cache| cache.file(SAFETY_DB_FILE));
+    
+    if let Some(path) = &path {
+        let age = std::fs::metadata(path).and_then(|m| m.modified()).ok()
+            .map(|modified| modified.elapsed().unwrap_or_default());
+        
+        let usable = match age {
+            Some(_) if offline => true,
+            Some(age) => age < lookup.safety_db_ttl && !lookup.refresh_safety_db,
+            None => false,
+        };
+        
+        if usable {
+            match std::fs::read_to_string(path).map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string())) {
+                Ok(db) => {
+                    debug!("Using cached Safety DB from {:?}", path);
+                    return Ok(Some(db));
+                },
+                Err(e) => warn!("Ignoring unreadable Safety DB cache {:?}: {}", path, e),
+            }
+        }
+    }
+    
+    if offline {
+        debug!("Offline mode: no cached Safety DB available");
+        return Ok(None);
+    }
+    
+    info!("Downloading Safety DB");
+    let response = conda_api::send_with_retry(client.get(SAFETY_DB_URL), &lookup.network)
+        .map_err(|e| format!("Safety DB request failed: {}", e))?;
+    
+    if !response.status().is_success() {
+        return Err(format!("Safety DB error: HTTP {}", response.status()));
+    }
+    
+    let content = response.text()
+        .map_err(|e| format!("Failed to read Safety DB: {}", e))?;
+    let db: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Safety DB: {}", e))?;
+    
+    if let Some(path) = &path {
+        if let Err(e) = write_safety_db_cache(path, &content) {
+            warn!("Failed to cache Safety DB at {:?}: {}", path, e);
+        }
+    }
+    
+    Ok(Some(db))
+}
+
+/// Write the Safety DB to the cache directory, replacing any previous copy
+fn write_safety_db_cache(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first so a concurrent run never reads a partial copy
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)
+}
+
 /// Check PyPI security advisories
 fn check_pypi_security(
     client: &reqwest::blocking::Client,
     package: &Package,
     version: &str,
-    vulnerabilities: &mut Vec<(String, String, String)>
+    vulnerabilities: &mut Vec<Vulnerability>,
+    lookup: &LookupOptions,
 ) -> Result<(), String> {
     debug!("Checking PyPI security advisories for {} {}", package.name, version);
     
     // PyPI doesn't have a direct security API, so we use the Safety DB as a proxy
     // In a production app, you could subscribe to the Safety DB service
-    let url = format!("https://raw.githubusercontent.com/pyupio/safety-db/master/data/insecure_full.json");
-    
-    // Make the API request (with thread-safe caching)
     let safety_db = {
         let mut cache = SAFETY_DB_CACHE.lock().map_err(|e| format!("Failed to lock cache: {}", e))?;
         
         if cache.is_none() {
-            debug!("Safety DB not cached, fetching from source");
-            let response = client.get(&url)
-                .send()
-                .map_err(|e| format!("Safety DB request failed: {}", e))?;
-            
-            if !response.status().is_success() {
-                return Err(format!("Safety DB error: HTTP {}", response.status()));
-            }
-            
-            let db: serde_json::Value = response.json()
-                .map_err(|e| format!("Failed to parse Safety DB: {}", e))?;
-                
-            *cache = Some(db);
+            debug!("Safety DB not cached in memory, loading it");
+            *cache = load_safety_db(client, lookup)?;
         }
         
-        cache.as_ref().unwrap().clone()
+        match cache.as_ref() {
+            Some(db) => db.clone(),
+            // Offline without a downloaded copy: nothing to check against
+            None => return Ok(()),
+        }
     };
     
     // Check if the package is in the Safety DB
@@ -676,12 +1468,14 @@ fn check_pypi_security(
                 for v_ver in vuln_versions {
                     if let Some(v_ver_str) = v_ver.as_str() {
                         if is_version_affected(version, v_ver_str) {
-                            let desc = format!("{} ({})", vuln_desc, vuln_id);
-                            vulnerabilities.push((
-                                package.name.clone(),
-                                version.to_string(),
-                                desc,
-                            ));
+                            vulnerabilities.push(Vulnerability {
+                                package: package.name.clone(),
+                                version: version.to_string(),
+                                id: Some(vuln_id.to_string()),
+                                severity: None,
+                                description: vuln_desc.trim().to_string(),
+                                source: "safety-db".to_string(),
+                            });
                             break;
                         }
                     }
@@ -693,85 +1487,90 @@ fn check_pypi_security(
     Ok(())
 }
 
-/// Check if a version is affected by a vulnerability spec
-fn is_version_affected(version: &str, spec: &str) -> bool {
-    // Handle specs like "<=1.2.3", ">=1.0.0,<2.0.0"
+/// Check whether a pip package's exact release has been yanked from PyPI. Yanked releases
+/// were recalled by their maintainers (often for a security or data-loss bug) and may
+/// not be covered by any advisory database
+fn check_pypi_yanked(
+    client: &reqwest::blocking::Client,
+    package: &Package,
+    version: &str,
+    vulnerabilities: &mut Vec<Vulnerability>,
+    lookup: &LookupOptions,
+) -> Result<(), String> {
+    if lookup.offline {
+        return Ok(());
+    }
+    debug!("Checking whether {} {} was yanked from PyPI", package.name, version);
     
-    // Simple contains check for exact version match
-    if spec.contains(version) {
-        return true;
+    let url = format!("https://pypi.org/pypi/{}/{}/json", package.name, version);
+    let response = conda_api::send_with_retry(client.get(&url), &lookup.network)
+        .map_err(|e| format!("Network error: {}", e))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        debug!("{} {} is not on PyPI", package.name, version);
+        return Ok(());
+    }
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
     }
     
-    // Try to parse as semver for comparison operators
-    if let Ok(version_semver) = semver::Version::parse(version) {
-        // Split spec by commas for multiple conditions
-        for part in spec.split(',') {
-            let part = part.trim();
-            
-            // Parse operators like <, >, <=, >=, ==
-            if part.starts_with("<=") {
-                if let Ok(spec_ver) = semver::Version::parse(&part[2..]) {
-                    if version_semver <= spec_ver {
-                        return true;
-                    }
-                }
-            } else if part.starts_with("<") {
-                if let Ok(spec_ver) = semver::Version::parse(&part[1..]) {
-                    if version_semver < spec_ver {
-                        return true;
-                    }
-                }
-            } else if part.starts_with(">=") {
-                if let Ok(spec_ver) = semver::Version::parse(&part[2..]) {
-                    if version_semver >= spec_ver {
-                        return true;
-                    }
-                }
-            } else if part.starts_with(">") {
-                if let Ok(spec_ver) = semver::Version::parse(&part[1..]) {
-                    if version_semver > spec_ver {
-                        return true;
-                    }
-                }
-            } else if part.starts_with("==") {
-                if let Ok(spec_ver) = semver::Version::parse(&part[2..]) {
-                    if version_semver == spec_ver {
-                        return true;
-                    }
-                }
-            }
-        }
+    let json: serde_json::Value = response.json()
+        .map_err(|e| format!("Failed to parse PyPI response: {}", e))?;
+    if json["info"]["yanked"].as_bool() != Some(true) {
+        return Ok(());
     }
     
-    false
+    let reason = json["info"]["yanked_reason"].as_str()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .unwrap_or("no reason given");
+    vulnerabilities.push(Vulnerability {
+        package: package.name.clone(),
+        version: version.to_string(),
+        id: None,
+        severity: Some("medium".to_string()),
+        description: format!("Release {} was yanked from PyPI: {}", version, reason),
+        source: "pypi-yanked".to_string(),
+    });
+    
+    Ok(())
+}
+
+/// Check if a version is affected by a vulnerability spec such as `<=1.2.3` or
+/// `>=1.0.0,<2.0.0` (`,` joins bounds that must all hold, `|` separates alternative ranges)
+fn is_version_affected(version: &str, spec: &str) -> bool {
+    version::matches_spec(version, spec)
 }
 
 /// Check for significantly outdated packages
 fn check_version_gap(
     package: &Package,
     version: &str,
-    vulnerabilities: &mut Vec<(String, String, String)>
+    vulnerabilities: &mut Vec<Vulnerability>
 ) {
     // For any outdated packages with a large version gap, add a general security notice
     if let Some(latest) = &package.latest_version {
         if package.is_outdated && version_gap_significant(version, latest) {
-            vulnerabilities.push((
-                package.name.clone(),
-                version.to_string(),
-                format!(
+            vulnerabilities.push(Vulnerability {
+                package: package.name.clone(),
+                version: version.to_string(),
+                id: None,
+                severity: Some("low".to_string()),
+                description: format!(
                     "Potentially vulnerable due to being significantly outdated (current: {}, latest: {})",
                     version, latest
                 ),
-            ));
+                source: "version-gap".to_string(),
+            });
         }
     }
 }
 
 /// Remove duplicate vulnerability entries
-fn deduplicate_vulnerabilities(vulnerabilities: &mut Vec<(String, String, String)>) {
+fn deduplicate_vulnerabilities(vulnerabilities: &mut Vec<Vulnerability>) {
     let mut seen = HashSet::new();
-    vulnerabilities.retain(|(name, version, description)| {
-        let key = format!("{}:{}:{}", name, version, description);
+    // The same advisory is often reported by several sources; keep the first report
+    vulnerabilities.retain(|v| {
+        let key = format!("{}:{}:{}", v.package, v.version, v.id.as_deref().unwrap_or(&v.description));
         seen.insert(key)
     });
 }
@@ -800,4 +1599,71 @@ fn version_gap_significant(current: &str, latest: &str) -> bool {
         // If we can't parse the versions properly, be conservative
         false
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency_map(entries: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        entries.iter()
+            .map(|(name, deps)| (name.to_string(), deps.iter().map(|dep| dep.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn detects_disjoint_requirements_on_a_shared_dependency() {
+        let map = dependency_map(&[("pkga", &["libz >=2.0"]), ("pkgb", &["libz <1.5", "python"])]);
+        let conflicts = detect_conflicts(&map);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].0.as_str(), conflicts[0].1.as_str()), ("pkga", "pkgb"));
+    }
+
+    #[test]
+    fn compatible_requirements_are_not_conflicts() {
+        let map = dependency_map(&[("pkga", &["numpy >=1.21,<2"]), ("pkgb", &["numpy >=1.5"])]);
+        assert!(detect_conflicts(&map).is_empty());
+    }
+
+    #[test]
+    fn affected_ranges_respect_their_boundaries() {
+        assert!(is_version_affected("1.19.0", "<1.19.1"));
+        assert!(is_version_affected("1.19.0.1", "<1.19.1"));
+        assert!(!is_version_affected("1.19.1", "<1.19.1"));
+        assert!(is_version_affected("2.0", ">=2.0,<2.4"));
+        assert!(!is_version_affected("2.4.0", ">=2.0,<2.4"));
+        assert!(!is_version_affected("1.20.0", "1.2.*"));
+    }
+
+    #[test]
+    fn requirements_match_on_the_exact_name() {
+        let map = dependency_map(&[("pkga", &["numpy-base >=2"]), ("pkgb", &["numpy <1.5"])]);
+        assert!(detect_conflicts(&map).is_empty());
+        assert_eq!(find_version_requirement(&map, "pkga", "numpy"), None);
+        assert_eq!(find_version_requirement(&map, "pkgb", "numpy").as_deref(), Some("<1.5"));
+    }
+
+    #[test]
+    fn user_vulnerabilities_override_bundled_entries_per_call() {
+        let user = vec![
+            LocalVulnerability {
+                name: "NumPy".to_string(),
+                affected: "<1.22.0".to_string(),
+                description: "Patched description".to_string(),
+                cve: Some("CVE-2021-33430".to_string()),
+                severity: None,
+            },
+            LocalVulnerability {
+                name: "internal-lib".to_string(),
+                affected: "<2.0".to_string(),
+                description: "Known issue".to_string(),
+                cve: None,
+                severity: None,
+            },
+        ];
+        let bundled = local_vulnerability_db(&[]);
+        let merged = local_vulnerability_db(&user);
+        assert_eq!(merged.len(), bundled.len() + 1);
+        assert!(merged.contains(&user[0]) && merged.contains(&user[1]));
+        assert!(!local_vulnerability_db(&[]).contains(&user[1]));
+    }
+}
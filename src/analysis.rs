@@ -1,13 +1,16 @@
 use anyhow::{Context, Result};
 use log::{debug, info, warn, error};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
-use std::process::Command;
+use rayon::prelude::*;
 use serde_json::Value;
 
-use crate::models::{CondaEnvironment, Dependency, Package};
+use crate::conda_api::{self, LookupOptions};
+use crate::performance;
+use crate::models::{Channel, CondaEnvironment, Dependency, Package, PinStatus, Priority, Recommendation, RecommendationCategory};
 
 /// Dependency graph representation
 #[derive(Debug)]
@@ -19,7 +22,7 @@ pub struct DependencyGraph {
 }
 
 /// Creates a dependency graph from environment packages by querying conda metadata
-pub fn create_dependency_graph(packages: &[Package]) -> DependencyGraph {
+pub fn create_dependency_graph(packages: &[Package], lookup: &LookupOptions) -> DependencyGraph {
     let mut graph = DependencyGraph {
         nodes: Vec::new(),
         edges: Vec::new(),
@@ -33,113 +36,113 @@ pub fn create_dependency_graph(packages: &[Package]) -> DependencyGraph {
     }
     
     // Get real dependencies using conda metadata
-    let dependency_map = get_real_package_dependencies(packages);
+    let dependency_map = get_real_package_dependencies(packages, lookup);
     
     // Add real dependency edges
     for package in packages {
         if let Some(deps) = dependency_map.get(&package.name) {
-            for dep in deps {
-                if graph.nodes.contains(dep) {
+            for dep in deps.iter().map(|dep| crate::parsers::spec_name(dep)) {
+                if graph.nodes.iter().any(|node| node == dep) {
                     debug!("Adding dependency edge: {} -> {}", package.name, dep);
-                    graph.edges.push((package.name.clone(), dep.clone()));
+                    graph.edges.push((package.name.clone(), dep.to_string()));
                 }
             }
         }
     }
     
+    // Sorted so the exported graph is the same on every run for the same input
+    graph.nodes.sort();
+    graph.edges.sort();
+    graph.edges.dedup();
+    
     graph
 }
 
-/// Get real package dependencies using Conda and PyPI APIs
-pub fn get_real_package_dependencies(packages: &[Package]) -> HashMap<String, Vec<String>> {
+/// Get real package dependencies using Conda and PyPI APIs, as conda match specs that keep
+/// their version constraint (`numpy >=1.21,<2`); [`crate::parsers::spec_name`] gives the bare
+/// name. Packages are looked up concurrently, and each lookup goes through the package metadata
+/// cache, including the on-disk dependency cache shared between runs
+pub fn get_real_package_dependencies(packages: &[Package], lookup: &LookupOptions) -> HashMap<String, Vec<String>> {
     info!("Getting real package dependencies for {} packages", packages.len());
-    let mut dependency_map: HashMap<String, Vec<String>> = HashMap::new();
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .unwrap_or_default();
     
-    for package in packages {
-        let mut dependencies = Vec::new();
-        let mut success = false;
-        
-        // Method 1: Try conda info command directly (most accurate for conda packages)
-        match get_package_depends_info(&package.name) {
+    let mut dependency_map: HashMap<String, Vec<String>> = packages.par_iter()
+        .map(|package| {
+            let dependencies = performance::package_dependencies(
+                &package.name, package.version.as_deref(), package.channel.as_deref(), lookup);
+            (package.name.clone(), dependencies)
+        })
+        .collect();
+    performance::save_dependency_cache(lookup);
+    
+    // Analyze and enhance the dependency map by checking transitive dependencies
+    enhance_dependency_map(&mut dependency_map);
+    
+    dependency_map
+}
+
+/// Look up the direct dependencies of a single package, trying each source in turn
+/// (PyPI for pip packages, conda info and the Anaconda API for the others, then conda-meta
+/// and known defaults). Returns an empty list if every source fails
+pub fn lookup_package_dependencies(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Vec<String> {
+    if channel == Some("pip") {
+        // Method 1: Try PyPI API for pip packages; a conda package of the same name may differ
+        let client = conda_api::build_http_client(&lookup.network);
+        match get_pypi_dependencies(&client, name, lookup) {
             Ok(deps) => {
-                debug!("Found dependencies for {} via conda info: {:?}", package.name, deps);
-                dependencies = deps;
-                success = true;
+                debug!("Found dependencies for {} via PyPI API: {:?}", name, deps);
+                return deps;
             },
-            Err(e) => debug!("Conda info failed for {}: {}", package.name, e)
-        }
-        
-        // Method 2: Try using Anaconda API if conda command failed
-        if !success {
-            match get_package_depends_api(&package.name, package.channel.as_deref()) {
-                Ok(deps) => {
-                    debug!("Found dependencies for {} via Anaconda API: {:?}", package.name, deps);
-                    dependencies = deps;
-                    success = true;
-                },
-                Err(e) => debug!("Anaconda API failed for {}: {}", package.name, e)
-            }
-        }
-        
-        // Method 3: Try PyPI API for pip packages
-        if !success && package.channel.as_deref() == Some("pip") {
-            match get_pypi_dependencies(&client, &package.name) {
-                Ok(deps) => {
-                    debug!("Found dependencies for {} via PyPI API: {:?}", package.name, deps);
-                    dependencies = deps;
-                    success = true;
-                },
-                Err(e) => debug!("PyPI API failed for {}: {}", package.name, e)
-            }
+            Err(e) => debug!("PyPI API failed for {}: {}", name, e)
         }
-        
-        // Method 4: Use conda-meta JSON files in environment (if available)
-        if !success {
-            match get_conda_meta_dependencies(&package.name) {
-                Ok(deps) => {
-                    debug!("Found dependencies for {} via conda-meta: {:?}", package.name, deps);
-                    dependencies = deps;
-                    success = true;
-                },
-                Err(e) => debug!("Conda-meta failed for {}: {}", package.name, e)
-            }
-        }
-        
-        // Method 5: Use known dependencies for common packages as fallback
-        if !success {
-            if let Some(deps) = get_common_package_dependencies(&package.name) {
-                debug!("Using known dependencies for {}: {:?}", package.name, deps);
-                dependencies = deps;
-                success = true;
-            }
+    } else {
+        // Method 2: Try conda info command directly (most accurate for conda packages)
+        match get_package_depends_info(name, lookup) {
+            Ok(deps) => {
+                debug!("Found dependencies for {} via conda info: {:?}", name, deps);
+                return deps;
+            },
+            Err(e) => debug!("Conda info failed for {}: {}", name, e)
         }
         
-        // If all methods failed, log a warning
-        if !success {
-            warn!("Could not determine dependencies for {}", package.name);
+        // Method 3: Try using Anaconda API if conda command failed
+        match get_package_depends_api(name, channel, lookup) {
+            Ok(deps) => {
+                debug!("Found dependencies for {} via Anaconda API: {:?}", name, deps);
+                return deps;
+            },
+            Err(e) => debug!("Anaconda API failed for {}: {}", name, e)
         }
-        
-        // Store whatever dependencies we found (even if empty)
-        dependency_map.insert(package.name.clone(), dependencies);
     }
     
-    // Analyze and enhance the dependency map by checking transitive dependencies
-    enhance_dependency_map(&mut dependency_map);
+    // Method 4: Use conda-meta JSON files in environment (if available)
+    match get_conda_meta_dependencies(name, lookup) {
+        Ok(Some(deps)) => {
+            debug!("Found dependencies for {} via conda-meta: {:?}", name, deps);
+            return deps;
+        },
+        Ok(None) => debug!("No conda-meta record found for {}", name),
+        Err(e) => debug!("Conda-meta failed for {}: {}", name, e)
+    }
     
-    dependency_map
+    // Method 5: Use known dependencies for common packages as fallback
+    if let Some(deps) = get_common_package_dependencies(name) {
+        debug!("Using known dependencies for {}: {:?}", name, deps);
+        return deps;
+    }
+    
+    // If all methods failed, log a warning and store an empty list
+    warn!("Could not determine dependencies for {}", name);
+    Vec::new()
 }
 
 /// Get dependencies from PyPI API for pip packages
-fn get_pypi_dependencies(client: &reqwest::blocking::Client, package_name: &str) -> Result<Vec<String>> {
+fn get_pypi_dependencies(client: &reqwest::blocking::Client, package_name: &str, lookup: &LookupOptions) -> Result<Vec<String>> {
+    lookup.ensure_online(&format!("PyPI dependency lookup for {}", package_name))?;
     info!("Getting dependencies for {} via PyPI API", package_name);
     
     let url = format!("https://pypi.org/pypi/{}/json", package_name);
     
-    let response = match client.get(&url).send() {
+    let response = match conda_api::send_with_retry(client.get(&url), &lookup.network) {
         Ok(resp) => resp,
         Err(e) => {
             warn!("Network error querying PyPI API: {}", e);
@@ -193,15 +196,16 @@ fn extract_pypi_package_name(dep_str: &str) -> Option<String> {
     }
 }
 
-/// Get dependencies from conda-meta JSON files
-fn get_conda_meta_dependencies(package_name: &str) -> Result<Vec<String>> {
-    info!("Getting dependencies for {} via conda-meta files", package_name);
+/// Prefix of the active conda environment: `$CONDA_PREFIX` if set, otherwise the
+/// `active_prefix` reported by `conda info`. `None` when no environment is active
+fn active_conda_prefix(lookup: &LookupOptions) -> Result<Option<String>> {
+    if let Some(prefix) = std::env::var("CONDA_PREFIX").ok().filter(|prefix| !prefix.is_empty()) {
+        return Ok(Some(prefix));
+    }
     
-    // First, find the active conda environment path
-    let output = Command::new("conda")
-        .args(["info", "--json"])
-        .output()
-        .with_context(|| "Failed to execute conda info command")?;
+    let output = conda_api::run_command(lookup.backend_command()
+        .args(["info", "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} info command", lookup.backend().binary()))?;
         
     if !output.status.success() {
         return Err(anyhow::anyhow!("conda info command failed"));
@@ -209,18 +213,35 @@ fn get_conda_meta_dependencies(package_name: &str) -> Result<Vec<String>> {
         
     let json: serde_json::Value = serde_json::from_slice(&output.stdout)
         .with_context(|| "Failed to parse JSON output from conda info")?;
-        
-    let active_prefix = json["active_prefix"].as_str()
-        .ok_or_else(|| anyhow::anyhow!("Failed to get active conda environment"))?;
+    
+    // `active_prefix` is null when no environment is activated
+    Ok(json["active_prefix"].as_str().map(str::to_string))
+}
+
+/// Get dependencies from the conda-meta JSON files of the active environment.
+/// Returns `Ok(None)` when there is no active environment, its conda-meta directory is
+/// missing or unreadable, or it has no record of the package, so the caller can move on
+/// to the next source
+fn get_conda_meta_dependencies(package_name: &str, lookup: &LookupOptions) -> Result<Option<Vec<String>>> {
+    info!("Getting dependencies for {} via conda-meta files", package_name);
+    
+    let Some(active_prefix) = active_conda_prefix(lookup)? else {
+        debug!("No active conda environment to read conda-meta files from");
+        return Ok(None);
+    };
         
     // Look for the package's meta file
-    let meta_dir = format!("{}/conda-meta", active_prefix);
-    let meta_files = std::fs::read_dir(&meta_dir)
-        .with_context(|| format!("Failed to read conda-meta directory at {}", meta_dir))?;
+    let meta_dir = Path::new(&active_prefix).join("conda-meta");
+    let meta_files = match std::fs::read_dir(&meta_dir) {
+        Ok(meta_files) => meta_files,
+        Err(e) => {
+            debug!("Can't read conda-meta directory at {}: {}", meta_dir.display(), e);
+            return Ok(None);
+        }
+    };
         
-    // Find the meta file for our package
-    for file_result in meta_files {
-        let file = file_result?;
+    // Find the meta file for our package, skipping entries that can't be read
+    for file in meta_files.filter_map(|entry| entry.ok()) {
         let filename = file.file_name().to_string_lossy().to_string();
         
         // Check if this file is for our package (format: name-version-build.json)
@@ -238,25 +259,26 @@ fn get_conda_meta_dependencies(package_name: &str) -> Result<Vec<String>> {
             if let Some(deps) = json["depends"].as_array() {
                 for dep in deps {
                     if let Some(dep_str) = dep.as_str() {
-                        if let Some(pkg_name) = extract_package_name(dep_str) {
-                            depends.push(pkg_name);
+                        if let Some(spec) = extract_dependency_spec(dep_str) {
+                            depends.push(spec);
                         }
                     }
                 }
             }
             
-            return Ok(depends);
+            return Ok(Some(depends));
         }
     }
     
-    Err(anyhow::anyhow!("Could not find conda-meta file for {}", package_name))
+    Ok(None)
 }
 
 /// Enhance dependency map by resolving transitive dependencies
 fn enhance_dependency_map(dependency_map: &mut HashMap<String, Vec<String>>) {
     debug!("Enhancing dependency map with transitive dependencies");
     
-    let packages: Vec<String> = dependency_map.keys().cloned().collect();
+    let mut packages: Vec<String> = dependency_map.keys().cloned().collect();
+    packages.sort();
     
     // Process each package to ensure its dependencies are properly represented
     for package in &packages {
@@ -264,11 +286,12 @@ fn enhance_dependency_map(dependency_map: &mut HashMap<String, Vec<String>>) {
         if let Some(deps) = dependency_map.get(package) {
             // Ensure each dependency has its dependencies populated
             for dep in deps.clone() {
+                let dep = crate::parsers::spec_name(&dep).to_string();
                 // If this dependency exists in our map but has no dependencies yet,
                 // try to find them from the common packages list if we don't have them yet
                 if dependency_map.get(&dep).map_or(true, |deps| deps.is_empty()) {
                     if let Some(common_deps) = get_common_package_dependencies(&dep) {
-                        dependency_map.insert(dep.clone(), common_deps);
+                        dependency_map.insert(dep, common_deps);
                     }
                 }
             }
@@ -279,13 +302,13 @@ fn enhance_dependency_map(dependency_map: &mut HashMap<String, Vec<String>>) {
 }
 
 /// Get package dependencies using conda info command
-fn get_package_depends_info(package_name: &str) -> Result<Vec<String>> {
+fn get_package_depends_info(package_name: &str, lookup: &LookupOptions) -> Result<Vec<String>> {
+    lookup.ensure_online(&format!("conda info lookup for {}", package_name))?;
     info!("Getting dependencies for {} via conda info", package_name);
     
-    let output = Command::new("conda")
-        .args(["info", package_name, "--json"])
-        .output()
-        .with_context(|| format!("Failed to execute conda info command for {}", package_name))?;
+    let output = conda_api::run_command(lookup.backend_command()
+        .args(["info", package_name, "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} info command for {}", lookup.backend().binary(), package_name))?;
         
     if !output.status.success() {
         return Err(anyhow::anyhow!("conda info command failed"));
@@ -301,8 +324,8 @@ fn get_package_depends_info(package_name: &str) -> Result<Vec<String>> {
             if let Some(deps) = pkg_info["depends"].as_array() {
                 for dep in deps {
                     if let Some(dep_str) = dep.as_str() {
-                        if let Some(pkg_name) = extract_package_name(dep_str) {
-                            depends.push(pkg_name);
+                        if let Some(spec) = extract_dependency_spec(dep_str) {
+                            depends.push(spec);
                         }
                     }
                 }
@@ -314,19 +337,19 @@ fn get_package_depends_info(package_name: &str) -> Result<Vec<String>> {
 }
 
 /// Get package dependencies using Anaconda API
-fn get_package_depends_api(package_name: &str, channel: Option<&str>) -> Result<Vec<String>> {
+fn get_package_depends_api(package_name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Result<Vec<String>> {
+    lookup.ensure_online(&format!("Anaconda API dependency lookup for {}", package_name))?;
     info!("Getting dependencies for {} via API", package_name);
     
-    let channel = channel.unwrap_or("conda-forge");
+    // The declared channel by its anaconda.org name, else conda-forge
+    let channel = conda_api::api_channels(package_name, channel)?.into_iter().next()
+        .unwrap_or_else(|| "conda-forge".to_string());
     // Use a timeout to avoid hanging on slow connections
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .unwrap_or_default();
+    let client = conda_api::build_http_client(&lookup.network);
     
     let url = format!("https://api.anaconda.org/package/{}/{}", channel, package_name);
     
-    let response = match client.get(&url).send() {
+    let response = match conda_api::send_with_retry(client.get(&url), &lookup.network) {
         Ok(resp) => resp,
         Err(e) => {
             warn!("Network error querying API for dependencies: {}", e);
@@ -356,8 +379,8 @@ fn get_package_depends_api(package_name: &str, channel: Option<&str>) -> Result<
             if let Some(deps) = latest_file["dependencies"].as_array() {
                 for dep in deps {
                     if let Some(dep_str) = dep.as_str() {
-                        if let Some(pkg_name) = extract_package_name(dep_str) {
-                            depends.push(pkg_name);
+                        if let Some(spec) = extract_dependency_spec(dep_str) {
+                            depends.push(spec);
                         }
                     }
                 }
@@ -369,11 +392,11 @@ fn get_package_depends_api(package_name: &str, channel: Option<&str>) -> Result<
     Ok(depends)
 }
 
-/// Extract package name from dependency specification
-fn extract_package_name(dep_str: &str) -> Option<String> {
-    dep_str.split_whitespace()
-        .next()
-        .map(|s| s.trim().to_string())
+/// Normalize a dependency from package metadata, keeping its version constraint
+/// (`numpy >=1.21,<2`); empty entries are skipped
+fn extract_dependency_spec(dep_str: &str) -> Option<String> {
+    let spec = dep_str.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!spec.is_empty()).then_some(spec)
 }
 
 /// Get common dependencies for well-known packages as a fallback
@@ -391,18 +414,126 @@ fn get_common_package_dependencies(package_name: &str) -> Option<Vec<String>> {
         .map(|deps| deps.iter().map(|&s| s.to_string()).collect())
 }
 
-/// Exports the dependency graph to DOT format for visualization
-pub fn export_dependency_graph<P: AsRef<Path>>(graph: &DependencyGraph, output_path: P) -> Result<()> {
+/// Direction of a DOT graph's layout (graphviz `rankdir`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RankDir {
+    /// Top to bottom (graphviz's default)
+    #[default]
+    #[clap(name = "TB")]
+    TopBottom,
+    /// Left to right, usually more readable for large graphs
+    #[clap(name = "LR")]
+    LeftRight,
+    /// Bottom to top
+    #[clap(name = "BT")]
+    BottomTop,
+    /// Right to left
+    #[clap(name = "RL")]
+    RightLeft,
+}
+
+impl fmt::Display for RankDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rankdir = match self {
+            RankDir::TopBottom => "TB",
+            RankDir::LeftRight => "LR",
+            RankDir::BottomTop => "BT",
+            RankDir::RightLeft => "RL",
+        };
+        write!(f, "{}", rankdir)
+    }
+}
+
+/// Layout options for DOT graph exports
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotOptions {
+    /// Direction of the layout
+    pub rankdir: RankDir,
+    /// Group the environment's packages into one `subgraph cluster_` block per channel
+    /// (pip packages together, conda packages without a channel under "conda")
+    pub cluster_by_channel: bool,
+}
+
+/// Quotes a package name as a DOT node ID
+pub(crate) fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes one `subgraph cluster_` block per channel, holding the graph nodes declared in the
+/// environment from that channel. Nodes that aren't packages of the environment stay outside
+pub(crate) fn write_channel_clusters<'a>(
+    out: &mut impl Write,
+    nodes: impl IntoIterator<Item = &'a str>,
+    packages: &[Package],
+) -> std::io::Result<()> {
+    let channels: HashMap<&str, &str> = packages.iter()
+        .map(|p| (p.name.as_str(), p.channel.as_deref().unwrap_or("conda")))
+        .collect();
+    
+    let mut clusters: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for node in nodes {
+        if let Some(channel) = channels.get(node) {
+            clusters.entry(channel).or_default().push(node);
+        }
+    }
+    
+    for (i, (channel, members)) in clusters.iter().enumerate() {
+        writeln!(out, "  subgraph cluster_{} {{", i)?;
+        writeln!(out, "    label={};", dot_id(channel))?;
+        writeln!(out, "    style=rounded;")?;
+        for member in members {
+            writeln!(out, "    {};", dot_id(member))?;
+        }
+        writeln!(out, "  }}")?;
+    }
+    
+    Ok(())
+}
+
+/// Exports the dependency graph to DOT format for visualization. Nodes are colored by
+/// package status: outdated red, pinned yellow, other declared packages green, and
+/// dependencies not declared in the environment grey
+pub fn export_dependency_graph<P: AsRef<Path>>(
+    graph: &DependencyGraph,
+    packages: &[Package],
+    output_path: P,
+    options: &DotOptions,
+) -> Result<()> {
     let mut file = File::create(output_path)
         .with_context(|| "Failed to create graph file")?;
     
+    let status: HashMap<&str, &Package> = packages.iter()
+        .map(|p| (p.name.as_str(), p))
+        .collect();
+    
     // Write DOT header
     writeln!(file, "digraph conda_dependencies {{")?;
-    writeln!(file, "  node [shape=box, style=filled, fillcolor=lightblue];")?;
+    writeln!(file, "  rankdir={};", options.rankdir)?;
+    writeln!(file, "  node [shape=box, style=filled, fillcolor=lightgrey];")?;
+    
+    // Legend
+    writeln!(file, "  subgraph cluster_legend {{")?;
+    writeln!(file, "    label=\"Legend\";")?;
+    writeln!(file, "    rank=source;")?;
+    writeln!(file, "    \"legend_outdated\" [label=\"outdated\", fillcolor=red];")?;
+    writeln!(file, "    \"legend_pinned\" [label=\"pinned\", fillcolor=yellow];")?;
+    writeln!(file, "    \"legend_ok\" [label=\"up to date\", fillcolor=green];")?;
+    writeln!(file, "    \"legend_outdated\" -> \"legend_pinned\" -> \"legend_ok\" [style=invis];")?;
+    writeln!(file, "  }}")?;
     
     // Write nodes with attributes
     for node in &graph.nodes {
-        writeln!(file, "  \"{}\" [label=\"{}\"];", node, node)?;
+        let color = match status.get(node.as_str()) {
+            Some(p) if p.is_outdated => "red",
+            Some(p) if p.is_pinned() => "yellow",
+            Some(_) => "green",
+            None => "lightgrey",
+        };
+        writeln!(file, "  \"{}\" [label=\"{}\", fillcolor={}];", node, node, color)?;
+    }
+    
+    if options.cluster_by_channel {
+        write_channel_clusters(&mut file, graph.nodes.iter().map(String::as_str), packages)?;
     }
     
     // Write edges
@@ -416,10 +547,199 @@ pub fn export_dependency_graph<P: AsRef<Path>>(graph: &DependencyGraph, output_p
     Ok(())
 }
 
+/// Channels that make up conda's `defaults` channel (`main` is also reachable as `pkgs/main`)
+const DEFAULTS_CHANNELS: &[&str] = &["defaults", "main", "anaconda", "r", "msys2"];
+
+/// Warns when an environment mixes the `defaults` and `conda-forge` channels without
+/// `channel_priority: strict`: the solver may then combine packages from both, which are
+/// built against different compilers and library versions
+pub fn channel_mixing_recommendation(env: &CondaEnvironment) -> Option<Recommendation> {
+    // Compare by anaconda.org name, so URLs and `pkgs/main` style names match too
+    let channels: Vec<Channel> = env.channels.iter().map(|c| Channel::parse(c)).collect();
+    let names: Vec<&str> = channels.iter().filter_map(Channel::anaconda_name).collect();
+    let has_defaults = names.iter().any(|name| DEFAULTS_CHANNELS.contains(name));
+    let has_conda_forge = names.contains(&"conda-forge");
+    if !(has_defaults && has_conda_forge) {
+        return None;
+    }
+    
+    let strict = env.extra.get("channel_priority")
+        .and_then(|value| value.as_str())
+        .is_some_and(|priority| priority.trim().eq_ignore_ascii_case("strict"));
+    if strict {
+        return None;
+    }
+    
+    Some(Recommendation {
+        description: "The environment mixes the defaults and conda-forge channels without strict channel priority".to_string(),
+        value: env.channels.join(", "),
+        details: Some(
+            "Packages from defaults and conda-forge are built against different compilers and libraries, \
+             so mixing them can produce ABI-incompatible environments. Set `channel_priority: strict` \
+             (e.g. `conda config --set channel_priority strict`) or use conda-forge alone".to_string()
+        ),
+        category: RecommendationCategory::Reproducibility,
+        priority: Priority::High,
+    })
+}
+
+/// Packages skipped by [`availability_recommendations`]: the interpreter and pip are
+/// published on both sides by design
+const AVAILABILITY_EXEMPT: &[&str] = &["python", "pip"];
+
+/// Checks where each package is published, on anaconda.org (its own channel, then
+/// conda-forge and main) and on PyPI, and recommends where it should be declared: conda
+/// packages published only on PyPI belong in the pip block, and pip packages that conda also
+/// publishes can move to the conda dependencies so the solver checks them. Conda packages
+/// also on PyPI are listed for teams migrating to pip. Packages whose lookups fail are skipped
+pub fn availability_recommendations(packages: &[Package], lookup: &LookupOptions) -> Vec<Recommendation> {
+    let checked: Vec<_> = packages.par_iter()
+        .filter(|p| !p.name.starts_with("__") && !AVAILABILITY_EXEMPT.contains(&p.name.to_lowercase().as_str()))
+        .map(|package| {
+            let is_pip = package.channel.as_deref() == Some("pip");
+            let name = package.name.to_lowercase();
+            let conda_channel = if is_pip { None } else { package.channel.as_deref() };
+            let availability = conda_api::find_conda_channel(&name, conda_channel, lookup)
+                .and_then(|channel| Ok((channel, conda_api::is_on_pypi(&name, lookup)?)));
+            (package, availability)
+        })
+        .collect();
+    
+    let mut only_on_pypi = Vec::new();
+    let mut pip_on_conda = Vec::new();
+    let mut conda_on_pypi = Vec::new();
+    let mut unchecked = Vec::new();
+    for (package, availability) in checked {
+        let is_pip = package.channel.as_deref() == Some("pip");
+        match availability {
+            Ok((None, true)) if !is_pip => only_on_pypi.push(package.name.clone()),
+            Ok((Some(channel), _)) if is_pip => pip_on_conda.push(format!("{} ({})", package.name, channel)),
+            Ok((Some(channel), true)) => conda_on_pypi.push(format!("{} ({})", package.name, channel)),
+            Ok(_) => {},
+            Err(e) => {
+                debug!("Could not check where {} is published: {}", package.name, e);
+                unchecked.push(package.name.clone());
+            },
+        }
+    }
+    if !unchecked.is_empty() {
+        warn!("Could not check conda and PyPI availability for {} packages: {}", unchecked.len(), unchecked.join(", "));
+    }
+    
+    let mut recommendations: Vec<Recommendation> = only_on_pypi.iter()
+        .map(|name| Recommendation {
+            description: format!("Package {} is only on PyPI, not on a conda channel; consider moving it to the pip block", name),
+            value: name.clone(),
+            details: Some("conda can't install it from the dependencies list, so the environment won't resolve as written".to_string()),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::High,
+        })
+        .collect();
+    
+    if !pip_on_conda.is_empty() {
+        recommendations.push(Recommendation {
+            description: format!(
+                "{} pip packages are also available from conda: {}",
+                pip_on_conda.len(), pip_on_conda.join(", ")
+            ),
+            value: pip_on_conda.len().to_string(),
+            details: Some(
+                "Declaring them as conda dependencies lets the solver check them against the rest of the \
+                 environment; keep them in the pip block only if you need PyPI-specific builds".to_string()
+            ),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::Medium,
+        });
+    }
+    
+    if !conda_on_pypi.is_empty() {
+        recommendations.push(Recommendation {
+            description: format!(
+                "{} conda packages are available in both conda and PyPI: {}",
+                conda_on_pypi.len(), conda_on_pypi.join(", ")
+            ),
+            value: conda_on_pypi.len().to_string(),
+            details: Some("These could move to the pip block when migrating the environment to pip".to_string()),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::Low,
+        });
+    }
+    
+    recommendations
+}
+
+/// Whether a path is absolute on Unix or Windows, whichever machine this runs on
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || path.starts_with("\\\\")
+        || (bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/'))
+}
+
+/// Warns when an environment file hard-codes an absolute `prefix:`. `conda env export`
+/// writes one, and it makes `conda env create` install to that exact path on every machine
+pub fn hardcoded_prefix_recommendation(env: &CondaEnvironment) -> Option<Recommendation> {
+    let prefix = env.prefix.as_deref().map(str::trim).filter(|prefix| is_absolute_path(prefix))?;
+    
+    Some(Recommendation {
+        description: "The environment file hard-codes an absolute install prefix".to_string(),
+        value: prefix.to_string(),
+        details: Some(
+            "The prefix is specific to the machine the file was exported on, so the environment \
+             installs to that path (or fails) everywhere else. Remove the `prefix:` line and pass \
+             `--prefix` or `--name` to `conda env create` instead".to_string()
+        ),
+        category: RecommendationCategory::Reproducibility,
+        priority: Priority::Medium,
+    })
+}
+
+/// Most conda packages a `--from-history` export is expected to list; full exports of
+/// even small environments list dozens of packages
+const HISTORY_EXPORT_MAX_PACKAGES: usize = 40;
+
+/// Low-level runtime packages that a full `conda env export` always lists, but that are
+/// almost never requested explicitly
+const RUNTIME_PACKAGES: &[&str] = &[
+    "_libgcc_mutex", "_openmp_mutex", "ca-certificates", "openssl", "libgcc-ng", "libstdcxx-ng",
+    "libgomp", "libffi", "ld_impl_linux-64", "ncurses", "readline", "sqlite", "tk", "tzdata",
+    "xz", "zlib", "libzlib", "bzip2", "libuuid", "libnsl", "libsqlite", "libexpat", "libcxx",
+    "ucrt", "vc", "vs2015_runtime",
+];
+
+/// Heuristically detects a `conda env export --from-history` style spec, which lists only
+/// the explicitly requested packages with loose pins: a handful of conda packages, none
+/// with a build string, and none of the low-level runtime packages a full export includes
+pub fn is_history_export(packages: &[Package]) -> bool {
+    let conda: Vec<&Package> = packages.iter()
+        .filter(|p| p.channel.as_deref() != Some("pip"))
+        .collect();
+    
+    !conda.is_empty()
+        && conda.len() <= HISTORY_EXPORT_MAX_PACKAGES
+        && conda.iter().all(|p| p.build.is_none())
+        && !conda.iter().any(|p| RUNTIME_PACKAGES.contains(&p.name.to_lowercase().as_str()))
+}
+
 /// Generate environment recommendations based on the analysis
-pub fn generate_recommendations(packages: &[Package], check_outdated: bool) -> Vec<String> {
+pub fn generate_recommendations(
+    env: &CondaEnvironment,
+    packages: &[Package],
+    check_outdated: bool,
+    lookup: &LookupOptions,
+) -> Vec<String> {
     let mut recommendations = Vec::new();
     
+    // Check for mixed channels without strict priority
+    if let Some(recommendation) = channel_mixing_recommendation(env) {
+        recommendations.push(recommendation.description);
+    }
+    
+    // Check for a machine-specific install prefix
+    if let Some(recommendation) = hardcoded_prefix_recommendation(env) {
+        recommendations.push(recommendation.description);
+    }
+    
     // Check for outdated packages
     let outdated: Vec<&Package> = packages.iter()
         .filter(|p| p.is_outdated)
@@ -450,7 +770,7 @@ pub fn generate_recommendations(packages: &[Package], check_outdated: bool) -> V
     
     // Check for pinned versions
     let pinned_count = packages.iter()
-        .filter(|p| p.is_pinned)
+        .filter(|p| p.is_pinned())
         .count();
     
     if pinned_count > 0 {
@@ -461,14 +781,22 @@ pub fn generate_recommendations(packages: &[Package], check_outdated: bool) -> V
                 "{:.1}% of packages have pinned versions. This ensures reproducibility but may prevent updates.",
                 percentage
             ));
-        } else if percentage < 30.0 {
-            recommendations.push(format!(
-                "Only {:.1}% of packages have pinned versions. Consider pinning more packages for better reproducibility.",
-                percentage
-            ));
         }
     }
     
+    // Packages without any version constraint are the reproducibility risk; ranges are deliberate
+    let unpinned: Vec<&str> = packages.iter()
+        .filter(|p| p.pin_status == PinStatus::Unpinned)
+        .map(|p| p.name.as_str())
+        .collect();
+    if !unpinned.is_empty() {
+        recommendations.push(format!(
+            "{} packages have no version constraint ({}). Pin them or constrain them to a range for better reproducibility.",
+            unpinned.len(),
+            unpinned.join(", ")
+        ));
+    }
+    
     // Check environment size
     let total_size: u64 = packages.iter()
         .filter_map(|p| p.size)
@@ -481,7 +809,7 @@ pub fn generate_recommendations(packages: &[Package], check_outdated: bool) -> V
     }
     
     // Check for redundant packages
-    let redundant_packages = identify_redundant_packages(packages);
+    let redundant_packages = identify_redundant_packages(packages, lookup);
     if !redundant_packages.is_empty() {
         recommendations.push(format!(
             "Found {} potentially redundant packages that might be removed to streamline your environment.",
@@ -496,10 +824,17 @@ pub fn generate_recommendations(packages: &[Package], check_outdated: bool) -> V
     recommendations
 }
 
+/// Commonly used dev packages that should not be flagged as redundant: nothing depends on
+/// them, but they are installed on purpose
+const DEV_PACKAGES: &[&str] = &[
+    "pytest", "black", "flake8", "mypy", "isort", "pylint", 
+    "jupyter", "ipython", "notebook", "ipykernel", "jupyterlab"
+];
+
 /// Identify potentially redundant packages in the environment
-fn identify_redundant_packages(packages: &[Package]) -> Vec<String> {
+fn identify_redundant_packages(packages: &[Package], lookup: &LookupOptions) -> Vec<String> {
     // Get real dependencies
-    let dependency_map = get_real_package_dependencies(packages);
+    let dependency_map = get_real_package_dependencies(packages, lookup);
     
     // Find packages that are not direct dependencies of any other package
     // and have no direct Python imports (common in dev dependencies)
@@ -509,21 +844,15 @@ fn identify_redundant_packages(packages: &[Package]) -> Vec<String> {
     let mut is_dependency = HashSet::new();
     for deps in dependency_map.values() {
         for dep in deps {
-            is_dependency.insert(dep.clone());
+            is_dependency.insert(crate::parsers::spec_name(dep).to_string());
         }
     }
     
-    // Commonly used dev packages that should not be flagged as redundant
-    let dev_packages = [
-        "pytest", "black", "flake8", "mypy", "isort", "pylint", 
-        "jupyter", "ipython", "notebook", "ipykernel", "jupyterlab"
-    ];
-    
     // Check each package
     for package in packages {
         // Skip if it's a dependency or a common dev package
         if is_dependency.contains(&package.name) || 
-           dev_packages.contains(&package.name.as_str()) {
+           DEV_PACKAGES.contains(&package.name.as_str()) {
             continue;
         }
         
@@ -532,4 +861,72 @@ fn identify_redundant_packages(packages: &[Package]) -> Vec<String> {
     }
     
     potentially_redundant
-} 
\ No newline at end of file
+} 
+/// Builds a minimal version of an environment that lists only the packages nothing else in it
+/// depends on: conda installs the rest anyway. Channels, pins of the kept packages, the pip
+/// section and every other key are preserved. To stay on the safe side, a package is only
+/// dropped when a kept package requires it (directly or through other dropped packages), and
+/// packages with a version constraint, pip (when there is a pip section), the dev tools and
+/// packages without dependency information are always kept. Returns the minimal environment
+/// and the specs that were dropped
+pub fn minimize_environment(env: &CondaEnvironment, lookup: &LookupOptions) -> (CondaEnvironment, Vec<String>) {
+    let packages: Vec<Package> = env.dependencies.iter()
+        .filter_map(|dep| match dep {
+            Dependency::Simple(spec) => Some(crate::parsers::parse_package_spec(spec)),
+            _ => None,
+        })
+        .collect();
+    let dependency_map = get_real_package_dependencies(&packages, lookup);
+    let requirements = |name: &str| -> Vec<String> {
+        dependency_map.get(name)
+            .map(|deps| deps.iter().map(|dep| crate::parsers::spec_name(dep).to_string()).collect())
+            .unwrap_or_default()
+    };
+    
+    let has_pip_section = env.dependencies.iter().any(|dep| matches!(dep, Dependency::Complex(_)));
+    let declared: HashSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    let required: HashSet<String> = packages.iter()
+        .flat_map(|p| requirements(&p.name).into_iter().filter(|dep| dep != &p.name))
+        .collect();
+    let pinned: HashSet<&str> = packages.iter()
+        .filter(|p| p.pin_status != PinStatus::Unpinned)
+        .map(|p| p.name.as_str())
+        .collect();
+    let always_kept = |name: &str| {
+        pinned.contains(name)
+            || (name == "pip" && has_pip_section)
+            || DEV_PACKAGES.contains(&name)
+            || requirements(name).is_empty()
+    };
+    
+    // Drop what the kept packages pull in, so a dependency cycle with nothing above it stays
+    let mut kept: HashSet<&str> = declared.iter()
+        .copied()
+        .filter(|name| !required.contains(*name) || always_kept(name))
+        .collect();
+    let mut pulled_in = HashSet::new();
+    let mut queue: Vec<String> = kept.iter().map(|name| name.to_string()).collect();
+    while let Some(name) = queue.pop() {
+        for dep in requirements(&name) {
+            if declared.contains(dep.as_str()) && pulled_in.insert(dep.clone()) {
+                queue.push(dep);
+            }
+        }
+    }
+    kept.extend(declared.iter().copied().filter(|name| !pulled_in.contains(*name)));
+    
+    let mut dropped = Vec::new();
+    let mut minimal = env.clone();
+    minimal.dependencies.retain(|dep| match dep {
+        Dependency::Simple(spec) => {
+            let keep = kept.contains(crate::parsers::spec_name(spec));
+            if !keep {
+                dropped.push(spec.clone());
+            }
+            keep
+        },
+        Dependency::Complex(_) => true,
+    });
+    
+    (minimal, dropped)
+}
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::advanced_analysis::{self, VulnerabilityIgnoreList};
+use crate::analysis;
+use crate::conda_api::LookupOptions;
+use crate::exporters::ExportFormat;
+use crate::models::{EnvironmentAnalysis, Severity, Vulnerability};
+
+/// Overall risk of an environment, from A (no findings) to F (high or critical vulnerabilities)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum)]
+pub enum RiskGrade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl RiskGrade {
+    /// Grade the findings of an audit by the worst of them:
+    /// F for high or critical vulnerabilities, D for medium vulnerabilities (or ones without a
+    /// reported severity) and version conflicts, C for low vulnerabilities or more than a
+    /// quarter of the packages outdated, B for any outdated package, and A otherwise
+    pub fn assess(packages_scanned: usize, outdated: usize, conflicts: usize, vulnerabilities: &[Vulnerability]) -> Self {
+        let worst = vulnerabilities.iter()
+            .map(|v| v.severity.as_deref().and_then(Severity::from_name).unwrap_or(Severity::Medium))
+            .max();
+
+        match worst {
+            Some(Severity::High | Severity::Critical) => RiskGrade::F,
+            Some(Severity::Medium) => RiskGrade::D,
+            _ if conflicts > 0 => RiskGrade::D,
+            Some(Severity::Low) => RiskGrade::C,
+            None if outdated * 4 > packages_scanned => RiskGrade::C,
+            None if outdated > 0 => RiskGrade::B,
+            None => RiskGrade::A,
+        }
+    }
+}
+
+impl fmt::Display for RiskGrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grade = match self {
+            RiskGrade::A => "A",
+            RiskGrade::B => "B",
+            RiskGrade::C => "C",
+            RiskGrade::D => "D",
+            RiskGrade::F => "F",
+        };
+        write!(f, "{}", grade)
+    }
+}
+
+/// A package with a newer version available
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    /// Version declared in the environment, if any
+    pub version: Option<String>,
+    /// Newest version available
+    pub latest_version: Option<String>,
+}
+
+/// Packages whose version requirements can't all be met
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    /// Packages involved in the conflict
+    pub packages: Vec<String>,
+    /// What the conflicting requirements are
+    pub description: String,
+}
+
+/// Outdated packages, version conflicts and vulnerabilities of an environment with an overall grade
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// Name of the environment
+    pub environment: Option<String>,
+    /// Overall risk grade
+    pub grade: RiskGrade,
+    /// Number of packages audited
+    pub packages_scanned: usize,
+    /// Packages with a newer version available, sorted by name
+    pub outdated: Vec<OutdatedPackage>,
+    /// Conflicts between dependency requirements, and packages declared in both the conda and
+    /// pip sections with different versions
+    pub conflicts: Vec<Conflict>,
+    /// Vulnerabilities not on the ignore list
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Number of vulnerabilities left out because they are on the ignore list
+    pub suppressed: usize,
+    /// Packages whose outdated check failed, with the reason, so they don't pass as up to date
+    pub unchecked: Vec<String>,
+}
+
+/// Audit an analyzed environment in one pass over its packages: the outdated packages found by
+/// the analysis, conflicts between the dependency requirements and the conda and pip sections,
+/// and the vulnerabilities not accepted by `ignore`. The analysis should have been run with
+/// the outdated check, otherwise no package is reported as outdated
+pub fn build_audit_report(
+    analysis: &EnvironmentAnalysis,
+    ignore: &VulnerabilityIgnoreList,
+    lookup: &LookupOptions,
+) -> AuditReport {
+    let mut outdated: Vec<OutdatedPackage> = analysis.packages.iter()
+        .filter(|p| p.is_outdated)
+        .map(|p| OutdatedPackage {
+            name: p.name.clone(),
+            version: p.version.clone(),
+            latest_version: p.latest_version.clone(),
+        })
+        .collect();
+    outdated.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let dependencies = analysis::get_real_package_dependencies(&analysis.packages, lookup);
+    let mut conflicts: Vec<Conflict> = advanced_analysis::detect_conflicts(&dependencies)
+        .into_iter()
+        .map(|(first, second, description)| Conflict {
+            packages: vec![first, second],
+            description,
+        })
+        .collect();
+    conflicts.extend(analysis.packages.iter()
+        .filter(|p| p.has_conflicting_pip_version())
+        .map(|p| Conflict {
+            packages: vec![p.name.clone()],
+            description: format!(
+                "{}: conda {}, pip {}",
+                p.name,
+                p.version.as_deref().unwrap_or("unknown"),
+                p.pip_version.as_deref().unwrap_or("unknown")
+            ),
+        }));
+
+    let (vulnerabilities, suppressed) = advanced_analysis::find_vulnerabilities_with_ignore(&analysis.packages, ignore, lookup);
+
+    let unchecked = analysis.check_failures.iter()
+        .map(|(package, reason)| format!("{} ({})", package, reason))
+        .collect();
+
+    AuditReport {
+        environment: analysis.name.clone(),
+        grade: RiskGrade::assess(analysis.packages.len(), outdated.len(), conflicts.len(), &vulnerabilities),
+        packages_scanned: analysis.packages.len(),
+        outdated,
+        conflicts,
+        vulnerabilities,
+        suppressed,
+        unchecked,
+    }
+}
+
+/// Format an audit report as JSON, YAML, Markdown or text (the default for other formats)
+pub fn format_audit_report(report: &AuditReport, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(report)
+            .with_context(|| "Failed to serialize audit report to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(report)
+            .with_context(|| "Failed to serialize audit report to YAML"),
+        ExportFormat::Markdown => Ok(format_audit_markdown(report)),
+        _ => Ok(format_audit_text(report)),
+    }
+}
+
+/// One line per outdated package, e.g. "numpy 1.21.0 -> 1.26.4"
+fn outdated_line(package: &OutdatedPackage) -> String {
+    format!(
+        "{} {} -> {}",
+        package.name,
+        package.version.as_deref().unwrap_or("unknown"),
+        package.latest_version.as_deref().unwrap_or("unknown")
+    )
+}
+
+fn format_audit_text(report: &AuditReport) -> String {
+    let mut output = format!(
+        "Audit of {}: grade {} ({} packages scanned)\n",
+        report.environment.as_deref().unwrap_or("environment"),
+        report.grade,
+        report.packages_scanned
+    );
+
+    output.push_str(&format!("\nOutdated packages ({}):\n", report.outdated.len()));
+    if report.outdated.is_empty() {
+        output.push_str("  none\n");
+    }
+    for package in &report.outdated {
+        output.push_str(&format!("  - {}\n", outdated_line(package)));
+    }
+
+    output.push_str(&format!("\nConflicts ({}):\n", report.conflicts.len()));
+    if report.conflicts.is_empty() {
+        output.push_str("  none\n");
+    }
+    for conflict in &report.conflicts {
+        output.push_str(&format!("  - {}\n", conflict.description));
+    }
+
+    output.push_str(&format!("\nVulnerabilities ({}):\n", report.vulnerabilities.len()));
+    if report.vulnerabilities.is_empty() {
+        output.push_str("  none\n");
+    }
+    for vulnerability in &report.vulnerabilities {
+        output.push_str(&format!("  - {}\n", vulnerability));
+    }
+    if report.suppressed > 0 {
+        output.push_str(&format!("  ({} accepted vulnerabilities suppressed by the ignore list)\n", report.suppressed));
+    }
+
+    if !report.unchecked.is_empty() {
+        output.push_str(&format!(
+            "\n{} packages could not be checked for updates: {}\n",
+            report.unchecked.len(), report.unchecked.join(", ")
+        ));
+    }
+
+    output
+}
+
+fn format_audit_markdown(report: &AuditReport) -> String {
+    let mut output = String::from("# Audit Report\n\n");
+
+    if let Some(name) = &report.environment {
+        output.push_str(&format!("Environment: **{}**\n\n", name));
+    }
+    output.push_str(&format!(
+        "Grade: **{}** ({} packages scanned)\n",
+        report.grade, report.packages_scanned
+    ));
+
+    output.push_str(&format!("\n## Outdated packages ({})\n\n", report.outdated.len()));
+    if report.outdated.is_empty() {
+        output.push_str("None.\n");
+    }
+    for package in &report.outdated {
+        output.push_str(&format!("- {}\n", outdated_line(package)));
+    }
+
+    output.push_str(&format!("\n## Conflicts ({})\n\n", report.conflicts.len()));
+    if report.conflicts.is_empty() {
+        output.push_str("None.\n");
+    }
+    for conflict in &report.conflicts {
+        output.push_str(&format!("- {}\n", conflict.description));
+    }
+
+    output.push_str(&format!("\n## Vulnerabilities ({})\n\n", report.vulnerabilities.len()));
+    if report.vulnerabilities.is_empty() {
+        output.push_str("None.\n");
+    } else {
+        output.push_str("| Package | Version | ID | Severity | Source | Description |\n");
+        output.push_str("|---------|---------|----|----------|--------|-------------|\n");
+        for v in &report.vulnerabilities {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                v.package, v.version, v.id.as_deref().unwrap_or(""),
+                v.severity.as_deref().unwrap_or(""), v.source, v.description.replace('|', "\\|")
+            ));
+        }
+    }
+    if report.suppressed > 0 {
+        output.push_str(&format!("\n_{} accepted vulnerabilities suppressed by the ignore list._\n", report.suppressed));
+    }
+
+    if !report.unchecked.is_empty() {
+        output.push_str(&format!(
+            "\n_{} packages could not be checked for updates: {}_\n",
+            report.unchecked.len(), report.unchecked.join(", ")
+        ));
+    }
+
+    output
+}
@@ -1,6 +1,11 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
+use crate::analysis::RankDir;
+use crate::audit::RiskGrade;
+use crate::conda_api::CondaBackend;
+use crate::models::{RecommendationCategory, Severity, SortKey};
+
 #[derive(Debug, Copy, Clone, ValueEnum)]
 pub enum OutputFormat {
     #[clap(name = "text")]
@@ -13,8 +18,14 @@ pub enum OutputFormat {
     Csv,
     #[clap(name = "markdown")]
     Markdown,
+    #[clap(name = "html")]
+    Html,
     #[clap(name = "toml")]
     Toml,
+    #[clap(name = "prometheus")]
+    Prometheus,
+    #[clap(name = "cyclonedx")]
+    CycloneDx,
 }
 
 #[derive(Parser, Debug)]
@@ -26,11 +37,12 @@ pub enum OutputFormat {
     long_about = "A Rust-based CLI tool for analyzing Conda environment files, identifying dependencies, and providing optimization recommendations."
 )]
 pub struct Cli {
-    /// Path to the Conda environment file (environment.yml, environment.yaml, or conda-lock.yml)
+    /// Path to the Conda environment file (environment.yml, environment.yaml, conda-lock.yml, an @EXPLICIT .lock/.txt file, or a pip requirements.txt).
+    /// May also be an http(s):// URL or a gzip-compressed file (e.g. environment.yml.gz)
     #[clap(default_value = "environment.yml")]
     pub file: PathBuf,
 
-    /// Format for output data (text, json, yaml, csv, markdown, toml)
+    /// Format for output data (text, json, yaml, csv, markdown, toml, html, prometheus, or a cyclonedx SBOM)
     #[clap(short, long, value_enum, default_value = "text")]
     pub format: OutputFormat,
 
@@ -58,6 +70,169 @@ pub struct Cli {
     #[clap(short = 'r', long)]
     pub generate_recommendations: bool,
 
+    /// Check the environment for outdated packages, version conflicts (between dependency
+    /// requirements, and between the conda and pip sections) and known vulnerabilities, print a short summary, and exit with status 1 if any are found
+    /// (for pre-commit hooks and CI). Runs without a progress bar or info logs
+    #[clap(long, conflicts_with_all = ["format", "output", "generate_graph", "generate_recommendations"])]
+    pub check: bool,
+
+    /// Directory to write all artifacts to, named after the environment (e.g. <envname>.json, <envname>.dot).
+    /// Individual path flags such as --output and --graph-output take precedence
+    #[clap(short = 'D', long, global = true)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Overlay environment file merged on top of the base file (e.g. environment-gpu.yml).
+    /// Can be given multiple times; later overlays win, and overlay entries always win over the base
+    #[clap(long, global = true)]
+    pub overlay: Vec<PathBuf>,
+
+    /// Document to read from a multi-document YAML file (1-based). Without it, the one
+    /// document that looks like a conda environment is used
+    #[clap(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub document: Option<u32>,
+
+    /// Only keep packages matching an expression, e.g. 'outdated and size > 50MB and channel == "conda-forge"'.
+    /// Fields: name, version, channel, size, outdated, pinned, source; operators: == != < <= > >= ~ and/or/not
+    #[clap(long, global = true)]
+    pub select: Option<String>,
+
+    /// Only analyze packages whose name matches this glob (e.g. 'numpy*'). Repeatable; a pattern
+    /// with `::` matches channel::name, so 'pip::*' keeps just the pip packages
+    #[clap(long, global = true, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Leave out packages whose name matches this glob (e.g. 'lib*'), even if included. Repeatable
+    #[clap(long, global = true, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Target platforms the environment must work on (e.g. linux-64,osx-arm64,win-64).
+    /// Defaults to the file's `platforms:` list; packages missing on any target are flagged
+    #[clap(long, global = true, value_delimiter = ',')]
+    pub platforms: Vec<String>,
+
+    /// Platform the environment is analyzed for (e.g. linux-64, osx-arm64, win-64), instead of this machine's.
+    /// Use it to analyze a lock file made for another platform without a mismatch warning
+    #[clap(long, global = true, value_name = "SUBDIR")]
+    pub platform: Option<String>,
+
+    /// Always re-fetch package metadata, but still cache the results for later lookups
+    #[clap(long, global = true, conflicts_with = "no_cache")]
+    pub refresh: bool,
+
+    /// Disable the package metadata cache entirely (no cache reads or writes)
+    #[clap(long, global = true)]
+    pub no_cache: bool,
+
+    /// Download the vulnerability (Safety) database again instead of using the cached copy
+    #[clap(long, global = true)]
+    pub refresh_db: bool,
+
+    /// Hours a downloaded vulnerability database is reused before it is fetched again
+    #[clap(long, global = true, default_value_t = 24, value_name = "HOURS")]
+    pub db_ttl: u64,
+
+    /// Local vulnerability database (JSON or YAML list of name, affected (a version range such as
+    /// `>=2.0,<2.4`), description, cve and severity entries) merged with the bundled one. Defaults to vulnerabilities.json
+    /// (or .yml/.yaml) in the conda-env-inspect config directory, if present
+    #[clap(long, global = true, value_name = "PATH")]
+    pub vuln_db: Option<PathBuf>,
+
+    /// Accepted vulnerabilities from the config file's `ignore_vulns`
+    #[clap(skip)]
+    pub ignored_vulnerabilities: Vec<String>,
+
+    /// Order packages in the output by this field (name, size, version or status; status lists outdated packages first)
+    #[clap(long, value_enum, global = true, value_name = "FIELD")]
+    pub sort_by: Option<SortKey>,
+
+    /// Sort in descending order (e.g. largest packages first with --sort-by size)
+    #[clap(long, global = true, requires = "sort_by")]
+    pub descending: bool,
+
+    /// Use a 5 MB placeholder for packages whose size can't be looked up. Estimated sizes are
+    /// flagged in the output and the total is reported as partially estimated
+    #[clap(long, global = true)]
+    pub estimate_sizes: bool,
+
+    /// Treat the file as a `conda env export --from-history` spec (only explicitly requested
+    /// packages, loosely pinned) even if it isn't detected as one, so unpinned packages aren't flagged
+    #[clap(long, global = true)]
+    pub from_history: bool,
+
+    /// Also report the total size including all transitive dependencies, not just the listed packages
+    #[clap(long, global = true)]
+    pub include_transitive: bool,
+
+    /// Also report the download size (compressed archives) of the conda packages, looked up on
+    /// anaconda.org. Works without an installed environment, e.g. to review a spec file
+    #[clap(long, global = true)]
+    pub download_size: bool,
+
+    /// Check where each package is published (anaconda.org and PyPI) and recommend moving
+    /// conda packages that are only on PyPI to the pip block, and pip packages conda also has to conda
+    #[clap(long, global = true)]
+    pub check_availability: bool,
+
+    /// Expand transitive dependencies at most N levels deep in dependency graphs (1 keeps only
+    /// direct dependencies), to bound the time and size of large graphs. 0 means unlimited
+    #[clap(long, global = true, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// Layout direction of DOT dependency graphs (LR is usually more readable for large graphs)
+    #[clap(long, global = true, value_enum, default_value_t = RankDir::TopBottom)]
+    pub rankdir: RankDir,
+
+    /// Group the packages of DOT dependency graphs into one cluster per channel (pip packages together)
+    #[clap(long, global = true)]
+    pub cluster_by_channel: bool,
+
+    /// Omit the environment's install prefix from all output (it reveals the source machine's path)
+    #[clap(long, global = true)]
+    pub strip_prefix: bool,
+
+    /// Package manager used for metadata lookups (conda, mamba or micromamba).
+    /// Defaults to $CONDA_INSPECT_BACKEND, then the first one found in PATH
+    #[clap(long, value_enum, global = true)]
+    pub backend: Option<CondaBackend>,
+
+    /// Disable all network lookups; outdated checks report unknown and only the
+    /// local vulnerability database is consulted
+    #[clap(long, global = true)]
+    pub offline: bool,
+
+    /// Timeout in seconds for each network request
+    #[clap(long, global = true, default_value_t = 15, value_name = "SECONDS")]
+    pub timeout: u64,
+
+    /// Retries (with exponential backoff) after connection errors and timeouts
+    #[clap(long, global = true, default_value_t = 0)]
+    pub retries: u32,
+
+    /// Seconds a conda, mamba or micromamba subprocess may run before it is killed and the
+    /// next lookup method is tried (e.g. when conda hangs on a locked package cache). 0 waits indefinitely
+    #[clap(long, global = true, default_value_t = 60, value_name = "SECONDS")]
+    pub conda_timeout: u64,
+
+    /// Maximum number of packages looked up in parallel, which bounds simultaneous
+    /// requests to anaconda.org and PyPI (raise it for speed, lower it if you get rate-limited)
+    #[clap(long, global = true, default_value_t = 8, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub max_concurrency: u16,
+
+    /// Exit with status 1 (after writing the output) if the outdated check failed for any
+    /// package, e.g. because of network errors, so "couldn't check" never passes as "up to date"
+    #[clap(long, global = true)]
+    pub strict: bool,
+
+    /// Disable colors in terminal output, the log and the interactive UI (also disabled by
+    /// setting the NO_COLOR environment variable, and when stdout is not a terminal)
+    #[clap(long, global = true)]
+    pub no_color: bool,
+
+    /// Only log warnings and errors, and hide the progress bar (it is also hidden
+    /// automatically when stdout is not a terminal)
+    #[clap(short, long, global = true)]
+    pub quiet: bool,
+
     #[clap(subcommand)]
     pub command: Option<Commands>,
 }
@@ -66,9 +241,14 @@ pub struct Cli {
 pub enum Commands {
     /// Analyze conda environment file
     Analyze {
-        /// Path to the Conda environment file
+        /// Paths or glob patterns of the Conda environment files; several files are analyzed in one run,
+        /// each in its own section (or its own file with --output-dir)
         #[clap(default_value = "environment.yml")]
-        file: PathBuf,
+        files: Vec<PathBuf>,
+        
+        /// Union the analyses of all the files into a single report
+        #[clap(long)]
+        combined: bool,
 
         /// Check for outdated packages
         #[clap(short, long)]
@@ -97,6 +277,11 @@ pub enum Commands {
         /// Generate advanced dependency graph with conflict detection
         #[clap(short = 'a', long)]
         advanced_graph: bool,
+        
+        /// Write a full report bundle into this directory: analysis.json, analysis.md, analysis.html,
+        /// graph.dot and vulnerabilities.json (one subdirectory per environment when there are several)
+        #[clap(long, value_name = "DIR", conflicts_with = "interactive")]
+        report_dir: Option<PathBuf>,
     },
     
     /// Export environment analysis in various formats
@@ -114,15 +299,33 @@ pub enum Commands {
         output: Option<PathBuf>,
     },
     
+    /// Analyze the packages installed in an existing conda environment, by name
+    Inspect {
+        /// Name of the environment (as shown by `conda env list`)
+        env_name: String,
+        
+        /// Format for output data
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+        
+        /// Check for outdated packages
+        #[clap(short, long)]
+        check_outdated: bool,
+    },
+    
     /// Generate dependency graph
     Graph {
         /// Path to the Conda environment file
         #[clap(default_value = "environment.yml")]
         file: PathBuf,
         
-        /// Output path for the graph
-        #[clap(short = 'o', long, default_value = "dependency_graph.dot")]
-        output: PathBuf,
+        /// Output path for the graph (defaults to dependency_graph.dot, or <envname>.dot with --output-dir)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
         
         /// Use advanced graph generation with conflict detection
         #[clap(short = 'a', long)]
@@ -138,6 +341,18 @@ pub enum Commands {
         /// Check for outdated packages
         #[clap(short, long)]
         check_outdated: bool,
+        
+        /// Only show recommendations in these categories (comma-separated)
+        #[clap(long, value_enum, value_delimiter = ',', value_name = "CATEGORY")]
+        only: Vec<RecommendationCategory>,
+        
+        /// Format for output data (json and yaml emit the recommendations array with categories and priorities)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
     },
     
     /// Launch interactive TUI mode
@@ -160,5 +375,154 @@ pub enum Commands {
         /// Path to the Conda environment file
         #[clap(default_value = "environment.yml")]
         file: PathBuf,
+        
+        /// Format for output data (json and yaml include advisory IDs, severities and sources as separate fields)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+        
+        /// Exit with status 1 if any vulnerability at or above this severity is found.
+        /// Findings without a reported severity always count
+        #[clap(long, value_enum, value_name = "SEVERITY")]
+        fail_on: Option<Severity>,
+        
+        /// Accepted vulnerability to leave out of the results: an advisory ID (e.g. CVE-2021-44228)
+        /// or a package@version pair. Can be given multiple times; entries from a
+        /// .conda-inspect-ignore file next to the environment file (or in the current directory) are added
+        #[clap(long, value_name = "ID|PACKAGE@VERSION")]
+        ignore_vuln: Vec<String>,
+    },
+    
+    /// Audit the environment for outdated packages, version conflicts and vulnerabilities in one
+    /// pass, with an overall risk grade from A (no findings) to F (high or critical vulnerabilities)
+    Audit {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Format for output data (text, json, yaml, markdown)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+        
+        /// Exit with status 1 (after writing the report) if the grade is this one or worse
+        #[clap(long, value_enum, value_name = "GRADE")]
+        fail_on: Option<RiskGrade>,
+    },
+    
+    /// Group packages by license and flag copyleft (GPL/AGPL) licenses
+    Licenses {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Format for output data (text, json, yaml, csv, markdown)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Print a short summary of the environment (package counts, size, channels, conflicts)
+    /// without the package list. Conflicts are packages declared in both the conda and pip
+    /// sections with different versions
+    Stats {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Check for outdated packages
+        #[clap(short, long)]
+        check_outdated: bool,
+    },
+    
+    /// Explain why a package is in the environment by listing every chain of dependencies
+    /// that pulls it in (like `pipdeptree --reverse`)
+    Why {
+        /// Name of the package to explain
+        package: String,
+        
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+    },
+    
+    /// Write a minimal environment file that lists only the packages nothing else in the
+    /// environment depends on, keeping channels and the pip section. Pinned packages, pip, common
+    /// dev tools and packages without dependency information are always kept
+    Minimize {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Output file path (if not specified, the environment will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// List the largest packages with their share of the total size, to see what bloats an environment
+    Sizes {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Number of packages to list
+        #[clap(short = 'n', long, default_value_t = 10)]
+        top: usize,
+    },
+    
+    /// Compare package versions across many environment files (e.g. envs/*.yml)
+    Matrix {
+        /// Paths or glob patterns of the environment files to compare
+        #[clap(required = true)]
+        files: Vec<PathBuf>,
+        
+        /// Format for output data (text, json, csv, markdown)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Check that an environment file is well-formed without any lookups: parse errors,
+    /// invalid package specs, duplicate or conflicting pins, unknown channels and an absolute
+    /// prefix. Exits with status 1 if there are errors
+    Validate {
+        /// Path to the Conda environment file
+        #[clap(default_value = "environment.yml")]
+        file: PathBuf,
+        
+        /// Format for output data (text, json, yaml)
+        #[clap(short, long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Output file path (if not specified, output will be written to stdout)
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
     },
+    
+    /// Show or clear the on-disk cache (dependency lookups and the vulnerability database)
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+}
+
+/// What to do with the on-disk cache
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum CacheAction {
+    /// Show the cache location, the cached files with their entry counts, and the total size
+    Info,
+    /// Delete everything in the cache, e.g. when stale data gives wrong "outdated" reports
+    Clear,
 }
@@ -1,17 +1,296 @@
 use anyhow::{Context, Result};
 use log::{debug, info, warn, error};
-use reqwest::blocking::Client;
-use semver::Version;
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::fs;
-use std::process::Command;
+use std::process::{Command, Output, Stdio};
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use crate::models::Package;
+use crate::advanced_analysis::LocalVulnerability;
+use crate::performance::CachePolicy;
+use crate::models::{Channel, ComplexDependency, CondaEnvironment, Dependency, Package};
+use crate::version::{self, CondaVersion};
 
 const ANACONDA_API_URL: &str = "https://api.anaconda.org/package";
 
+/// Channels queried on anaconda.org when a package's own channel has no answer, in order
+const DEFAULT_API_CHANNELS: &[&str] = &["conda-forge", "main"];
+
+/// Channels to query on anaconda.org for a package: its declared channel first (by its
+/// anaconda.org name, see [`Channel::anaconda_name`]), then the default channels. Packages
+/// without a channel only get the defaults. Pip packages, and packages from a local channel or
+/// a channel URL outside anaconda.org, aren't looked up at all, since the same name on
+/// anaconda.org may well be a different package: that is an error, with the reason
+pub fn api_channels(package_name: &str, declared: Option<&str>) -> Result<Vec<String>> {
+    let declared = declared.map(Channel::parse);
+    let own = match &declared {
+        Some(channel) if channel.is_pip() => {
+            debug!("Skipping API lookup for {}: it is a pip package", package_name);
+            return Err(anyhow::anyhow!("{} is a pip package, which is looked up on PyPI", package_name));
+        },
+        Some(channel @ (Channel::Local(_) | Channel::Url(_))) if channel.anaconda_name().is_none() => {
+            info!("Skipping API lookup for {}: channel {} is not hosted on anaconda.org", package_name, channel);
+            return Err(anyhow::anyhow!(
+                "{} comes from channel {}, which is not hosted on anaconda.org", package_name, channel
+            ));
+        },
+        Some(channel) => channel.anaconda_name(),
+        None => None,
+    };
+    
+    Ok(own.into_iter()
+        .chain(DEFAULT_API_CHANNELS.iter().copied().filter(|channel| Some(*channel) != own))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Environment variable used to pick the package manager binary when `--backend` is not given
+pub const BACKEND_ENV_VAR: &str = "CONDA_INSPECT_BACKEND";
+
+/// Backend found by [`detect_backend`], shared by the lookups that don't choose one. Detection
+/// runs each binary, so it happens once per process, on the first command that needs it
+static DETECTED_BACKEND: OnceLock<CondaBackend> = OnceLock::new();
+
+/// How packages are looked up in the registries and the package manager. Carried by
+/// [`AnalysisOptions`](crate::utils::AnalysisOptions) and passed to every lookup, so
+/// analyses run with different settings don't affect each other
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    /// Skip every network lookup, using only cached and local data (`--offline`)
+    pub offline: bool,
+    /// Timeout and retries of HTTP requests (`--timeout`, `--retries`)
+    pub network: NetworkConfig,
+    /// How long a conda, mamba or micromamba subprocess may run before it is killed, in
+    /// seconds (`--conda-timeout`); 0 waits indefinitely
+    pub command_timeout_secs: u64,
+    /// Package manager to run (`--backend`); `None` detects it on first use, see [`detect_backend`]
+    pub backend: Option<CondaBackend>,
+    /// How lookups use the package metadata cache (`--no-cache`, `--refresh`)
+    pub cache: CachePolicy,
+    /// How long a downloaded Safety DB is reused before it is fetched again (`--db-ttl`)
+    pub safety_db_ttl: Duration,
+    /// Ignore the downloaded Safety DB and fetch it again (`--refresh-db`)
+    pub refresh_safety_db: bool,
+    /// A user's entries for the local vulnerability database, merged into the bundled ones
+    /// (`--vuln-db`, see [`load_user_vulnerability_db`](crate::advanced_analysis::load_user_vulnerability_db))
+    pub local_vulnerabilities: Vec<LocalVulnerability>,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        LookupOptions {
+            offline: false,
+            network: NetworkConfig::default(),
+            command_timeout_secs: 60,
+            backend: None,
+            cache: CachePolicy::DEFAULT,
+            safety_db_ttl: Duration::from_secs(24 * 60 * 60),
+            refresh_safety_db: false,
+            local_vulnerabilities: Vec::new(),
+        }
+    }
+}
+
+impl LookupOptions {
+    /// Fails fast in offline mode instead of attempting a network lookup
+    pub fn ensure_online(&self, what: &str) -> Result<()> {
+        if self.offline {
+            debug!("Offline mode: skipping {}", what);
+            return Err(anyhow::anyhow!("Offline mode: skipped {}", what));
+        }
+        Ok(())
+    }
+
+    /// The package manager to run: the chosen backend, or the detected one
+    pub fn backend(&self) -> CondaBackend {
+        self.backend.unwrap_or_else(|| *DETECTED_BACKEND.get_or_init(|| detect_backend(self.command_timeout_secs)))
+    }
+
+    /// Creates a command invoking the backend's binary
+    pub fn backend_command(&self) -> Command {
+        Command::new(self.backend().binary())
+    }
+}
+
+/// Timeout and retry settings shared by all HTTP requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkConfig {
+    /// Per-request timeout in seconds
+    pub timeout_secs: u64,
+    /// Number of times a request is retried after a connection error or timeout
+    pub retries: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig { timeout_secs: 15, retries: 0 }
+    }
+}
+
+/// Build an HTTP client honoring the configured timeout
+pub fn build_http_client(config: &NetworkConfig) -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Send a request, retrying up to `config.retries` times with exponential backoff (0.5s, 1s,
+/// 2s, ...) on connection errors and timeouts. HTTP error statuses are returned as-is and never retried
+pub fn send_with_retry(request: RequestBuilder, config: &NetworkConfig) -> reqwest::Result<Response> {
+    let retries = config.retries;
+    let mut attempt = 0;
+    
+    loop {
+        // Requests with streaming bodies cannot be cloned, so they are only sent once
+        let Some(current) = request.try_clone() else {
+            return request.send();
+        };
+        
+        match current.send() {
+            Err(e) if attempt < retries && (e.is_connect() || e.is_timeout()) => {
+                let backoff = Duration::from_millis(500 * 2u64.pow(attempt.min(6)));
+                warn!("Request failed ({}), retrying in {:?} ({}/{})", e, backoff, attempt + 1, retries);
+                std::thread::sleep(backoff);
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Download a file over HTTP(S), e.g. an environment file given as a URL
+pub fn fetch_url(url: &str, lookup: &LookupOptions) -> Result<Vec<u8>> {
+    lookup.ensure_online(&format!("download of {}", url))?;
+    info!("Downloading {}", url);
+    
+    let client = build_http_client(&lookup.network);
+    let response = send_with_retry(client.get(url), &lookup.network)
+        .with_context(|| format!("Failed to download {}", url))?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Failed to download {}: HTTP {}", url, response.status()));
+    }
+    
+    let bytes = response.bytes()
+        .with_context(|| format!("Failed to read response from {}", url))?;
+    Ok(bytes.to_vec())
+}
+
+/// Package manager binary used for `search`, `info` and `env list` calls
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum CondaBackend {
+    Conda,
+    Mamba,
+    Micromamba,
+}
+
+impl CondaBackend {
+    /// All backends, in detection order
+    pub const ALL: [CondaBackend; 3] = [CondaBackend::Conda, CondaBackend::Mamba, CondaBackend::Micromamba];
+
+    /// Name of the executable for this backend
+    pub fn binary(&self) -> &'static str {
+        match self {
+            CondaBackend::Conda => "conda",
+            CondaBackend::Mamba => "mamba",
+            CondaBackend::Micromamba => "micromamba",
+        }
+    }
+
+    /// Parses a backend name such as "mamba" (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|backend| backend.binary().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Runs `<binary> --version`, returning the reported version when the binary works
+    pub fn probe(&self, timeout_secs: u64) -> Option<String> {
+        let output = run_command(Command::new(self.binary()).arg("--version"), timeout_secs).ok()?;
+        if !output.status.success() {
+            debug!("{} --version failed: {}", self.binary(), String::from_utf8_lossy(&output.stderr));
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Detects the backend when none is chosen: `CONDA_INSPECT_BACKEND`, then the first of conda,
+/// mamba and micromamba that responds to `--version` within `timeout_secs`.
+/// Falls back to conda when none is available, so callers keep their API fallbacks
+pub fn detect_backend(timeout_secs: u64) -> CondaBackend {
+    let from_env = std::env::var(BACKEND_ENV_VAR).ok().and_then(|name| {
+        let backend = CondaBackend::from_name(&name);
+        if backend.is_none() {
+            warn!("Ignoring unknown {}={:?}; expected conda, mamba or micromamba", BACKEND_ENV_VAR, name);
+        }
+        backend
+    });
+    if let Some(backend) = from_env {
+        info!("Using {} backend from {}", backend.binary(), BACKEND_ENV_VAR);
+        return backend;
+    }
+
+    match CondaBackend::ALL.into_iter().find_map(|b| b.probe(timeout_secs).map(|v| (b, v))) {
+        Some((backend, version)) => {
+            info!("Using {} backend: {}", backend.binary(), version);
+            backend
+        },
+        None => {
+            warn!("No conda, mamba or micromamba found in the system PATH. Some features will use fallback mechanisms.");
+            warn!("For complete functionality, please install conda and ensure it's in your PATH.");
+            CondaBackend::Conda
+        }
+    }
+}
+
+/// Runs a command to completion like [`Command::output`], but kills it once it has run longer
+/// than `timeout` seconds (e.g. conda waiting on a locked package cache; 0 waits indefinitely)
+/// and returns an error instead, so callers fall through to their next lookup method
+pub fn run_command(command: &mut Command, timeout: u64) -> Result<Output> {
+    use std::io::Read;
+    
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    
+    // Drain the pipes while waiting, so a chatty child can't block on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    });
+    let stdout = drain(child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+    let stderr = drain(child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+    
+    let deadline = (timeout > 0).then(|| std::time::Instant::now() + Duration::from_secs(timeout));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            let _ = child.kill();
+            let _ = child.wait();
+            let program = command.get_program().to_string_lossy().to_string();
+            let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().to_string()).collect();
+            warn!("{} {} did not finish within {}s and was killed", program, args.join(" "), timeout);
+            return Err(anyhow::anyhow!("{} {} timed out after {}s", program, args.join(" "), timeout));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
 /// Package information structure returned by API calls
 #[derive(Debug, Clone)]
 pub struct PackageInfo {
@@ -23,22 +302,42 @@ pub struct PackageInfo {
     pub size: Option<u64>,
     /// Available versions of the package
     pub versions: Vec<String>,
+    /// License of the package, if the channel reports one
+    pub license: Option<String>,
+}
+
+/// Get information about a package from the Conda API. The package's declared `channel`
+/// is queried first, and the default channels only if it has no answer
+pub fn get_package_info(package_name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Result<PackageInfo> {
+    lookup.ensure_online(&format!("Anaconda API lookup for {}", package_name))?;
+    
+    // Use a timeout to avoid hanging on slow connections
+    let client = build_http_client(&lookup.network);
+    
+    let mut last_error = None;
+    for channel in api_channels(package_name, channel)? {
+        match get_package_info_from_channel(&client, &lookup.network, package_name, &channel) {
+            Ok(info) => {
+                info!("Package info for {} found on channel {}", package_name, channel);
+                return Ok(info);
+            },
+            Err(e) => {
+                debug!("No package info for {} on channel {}: {}", package_name, channel, e);
+                last_error = Some(e);
+            },
+        }
+    }
+    
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No channel to query for {}", package_name)))
 }
 
-/// Get information about a package from the Conda API
-pub fn get_package_info(package_name: &str, channel: Option<&str>) -> Result<PackageInfo> {
-    let channel = channel.unwrap_or("conda-forge");
+/// Get information about a package on one anaconda.org channel
+fn get_package_info_from_channel(client: &Client, network: &NetworkConfig, package_name: &str, channel: &str) -> Result<PackageInfo> {
     let url = format!("{}/{}/{}", ANACONDA_API_URL, channel, package_name);
     
     debug!("Querying Anaconda API: {}", url);
     
-    // Use a timeout to avoid hanging on slow connections
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()
-        .unwrap_or_default();
-    
-    let response = match client.get(&url).send() {
+    let response = match send_with_retry(client.get(&url), network) {
         Ok(resp) => resp,
         Err(e) => {
             warn!("Network error querying API: {}", e);
@@ -47,7 +346,7 @@ pub fn get_package_info(package_name: &str, channel: Option<&str>) -> Result<Pac
     };
     
     if !response.status().is_success() {
-        error!("API request failed with status: {}", response.status());
+        debug!("API request failed with status: {}", response.status());
         return Err(anyhow::anyhow!("Failed to get package info: HTTP status {}", response.status()));
     }
     
@@ -93,81 +392,106 @@ pub fn get_package_info(package_name: &str, channel: Option<&str>) -> Result<Pac
         None
     };
     
+    let license = json["license"].as_str()
+        .map(str::trim)
+        .filter(|license| !license.is_empty())
+        .map(str::to_string);
+    
     Ok(PackageInfo {
         name: package_name.to_string(),
         latest_version,
         size,
         versions,
+        license,
     })
 }
 
-/// Check if a package is outdated using semantic versioning
-pub fn is_outdated(package: &Package, info: &PackageInfo) -> bool {
-    if let Some(version) = &package.version {
-        // Use semver for proper version comparison
-        match (parse_conda_version(version), parse_conda_version(&info.latest_version)) {
-            (Some(current_version), Some(latest_version)) => {
-                debug!("Comparing versions for {}: current={}, latest={}", 
-                       package.name, current_version, latest_version);
-                current_version < latest_version
-            },
-            _ => {
-                // Fallback to string comparison if parsing fails
-                warn!("Failed to parse version for {}, falling back to string comparison", package.name);
-                version != &info.latest_version
-            }
-        }
-    } else {
-        false
+/// Whether a URL answers with a success status (`true`) or 404 Not Found (`false`).
+/// Other statuses and network errors are errors, so "couldn't check" never reads as "missing"
+fn url_exists(client: &Client, network: &NetworkConfig, url: &str) -> Result<bool> {
+    let response = send_with_retry(client.get(url), network)
+        .with_context(|| format!("Request to {} failed", url))?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::NOT_FOUND => Ok(false),
+        status => Err(anyhow::anyhow!("{} returned HTTP {}", url, status)),
     }
 }
 
-/// Parse a conda version string into a semver Version
-fn parse_conda_version(version_str: &str) -> Option<Version> {
-    // Normalize conda version for semver parsing
-    let normalized = normalize_conda_version(version_str);
-    match Version::parse(&normalized) {
-        Ok(version) => Some(version),
-        Err(e) => {
-            warn!("Failed to parse version '{}': {}", version_str, e);
-            None
+/// The first anaconda.org channel publishing a package, trying its declared `channel` before
+/// the default channels, or `None` if none of them has it
+pub fn find_conda_channel(package_name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Result<Option<String>> {
+    lookup.ensure_online(&format!("Anaconda API lookup for {}", package_name))?;
+    
+    let client = build_http_client(&lookup.network);
+    for channel in api_channels(package_name, channel)? {
+        let url = format!("{}/{}/{}", ANACONDA_API_URL, channel, package_name);
+        if url_exists(&client, &lookup.network, &url)? {
+            return Ok(Some(channel));
         }
     }
+    Ok(None)
 }
 
-/// Normalize conda version string to semver compatibility
-fn normalize_conda_version(version: &str) -> String {
-    // Handle conda specific version formats
-    let version_without_build;
+/// Whether a project of this name is published on PyPI
+pub fn is_on_pypi(package_name: &str, lookup: &LookupOptions) -> Result<bool> {
+    lookup.ensure_online(&format!("PyPI lookup for {}", package_name))?;
     
-    // Remove build string if present
-    if let Some(idx) = version.find('+') {
-        version_without_build = &version[0..idx];
-    } else if let Some(idx) = version.find('-') {
-        if !version.starts_with("0-") {
-            version_without_build = &version[0..idx];
-        } else {
-            version_without_build = version;
-        }
-    } else {
-        version_without_build = version;
+    let client = build_http_client(&lookup.network);
+    url_exists(&client, &lookup.network, &format!("https://pypi.org/pypi/{}/json", package_name))
+}
+
+/// Get the license of a PyPI package, preferring its SPDX license expression, then a short
+/// `license` field, then its "License ::" trove classifier
+pub fn get_pypi_license(package_name: &str, lookup: &LookupOptions) -> Result<Option<String>> {
+    lookup.ensure_online(&format!("PyPI license lookup for {}", package_name))?;
+    
+    let client = build_http_client(&lookup.network);
+    let url = format!("https://pypi.org/pypi/{}/json", package_name);
+    let response = send_with_retry(client.get(&url), &lookup.network)
+        .with_context(|| format!("PyPI API request failed for {}", package_name))?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("PyPI API returned HTTP {} for {}", response.status(), package_name));
     }
+    let json: serde_json::Value = response.json()
+        .with_context(|| format!("Failed to parse PyPI API response for {}", package_name))?;
+    
+    let info = &json["info"];
+    let non_empty = |value: &serde_json::Value| value.as_str()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
     
-    // Ensure there are at least major.minor.patch components
-    let parts: Vec<&str> = version_without_build.split('.').collect();
-    match parts.len() {
-        1 => format!("{}.0.0", parts[0]),
-        2 => format!("{}.{}.0", parts[0], parts[1]),
-        _ => version_without_build.to_string(),
+    // The `license` field sometimes holds the full license text, which is useless as a label
+    let license = non_empty(&info["license_expression"])
+        .or_else(|| non_empty(&info["license"]).filter(|l| l.len() <= 64 && !l.contains('\n')))
+        .or_else(|| {
+            info["classifiers"].as_array()?.iter()
+                .filter_map(|c| c.as_str())
+                .find_map(|c| c.strip_prefix("License :: "))
+                .map(|c| c.rsplit(" :: ").next().unwrap_or(c).to_string())
+        });
+    
+    Ok(license)
+}
+
+/// Check if a package is outdated using conda's version ordering
+pub fn is_outdated(package: &Package, info: &PackageInfo) -> bool {
+    if let Some(version) = &package.version {
+        debug!("Comparing versions for {}: current={}, latest={}", 
+               package.name, version, info.latest_version);
+        version::is_newer(version, &info.latest_version)
+    } else {
+        false
     }
 }
 
 /// Get the total size of an environment by querying conda and inspecting the file system
-pub fn get_environment_size(env_name: &str) -> Result<Option<u64>> {
+pub fn get_environment_size(env_name: &str, lookup: &LookupOptions) -> Result<Option<u64>> {
     info!("Calculating size for environment: {}", env_name);
     
     // Get the environment path
-    let env_path = get_env_path(env_name)?;
+    let env_path = get_env_path(env_name, lookup)?;
     
     // If we have a valid path, calculate the total size
     if let Some(path) = env_path {
@@ -182,13 +506,12 @@ pub fn get_environment_size(env_name: &str) -> Result<Option<u64>> {
 }
 
 /// Get the file system path for a conda environment
-fn get_env_path(env_name: &str) -> Result<Option<String>> {
+fn get_env_path(env_name: &str, lookup: &LookupOptions) -> Result<Option<String>> {
     debug!("Looking up environment path for: {}", env_name);
     
-    let output = std::process::Command::new("conda")
-        .args(["env", "list", "--json"])
-        .output()
-        .with_context(|| "Failed to execute conda env list command")?;
+    let output = run_command(lookup.backend_command()
+        .args(["env", "list", "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} env list command", lookup.backend().binary()))?;
 
     if !output.status.success() {
         error!("conda env list command failed: {}", 
@@ -199,6 +522,16 @@ fn get_env_path(env_name: &str) -> Result<Option<String>> {
     let json: serde_json::Value = serde_json::from_slice(&output.stdout)
         .with_context(|| "Failed to parse conda env list JSON output")?;
 
+    // Newer conda versions report names directly, which also covers the base environment
+    if let Some(details) = json["envs_details"].as_object() {
+        for (path_str, detail) in details {
+            if detail["name"].as_str() == Some(env_name) || (env_name == "base" && detail["base"].as_bool() == Some(true)) {
+                debug!("Found environment path: {}", path_str);
+                return Ok(Some(path_str.clone()));
+            }
+        }
+    }
+
     if let Some(envs) = json["envs"].as_array() {
         for env_path in envs {
             if let Some(path_str) = env_path.as_str() {
@@ -220,6 +553,92 @@ fn get_env_path(env_name: &str) -> Result<Option<String>> {
     Ok(None)
 }
 
+/// Reads the packages installed in a named environment (`conda list --name NAME --json`)
+/// into an environment whose dependencies carry the exact installed version, build,
+/// channel and, where the conda-meta records are readable, size
+pub fn read_installed_environment(env_name: &str, lookup: &LookupOptions) -> Result<CondaEnvironment> {
+    info!("Reading installed packages of environment: {}", env_name);
+    
+    let output = run_command(lookup.backend_command()
+        .args(["list", "--name", env_name, "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} list for environment {}", lookup.backend().binary(), env_name))?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{} list failed for environment {}: {}",
+            lookup.backend().binary(), env_name, String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Failed to parse JSON output from {} list", lookup.backend().binary()))?;
+    let installed = json.as_array()
+        .ok_or_else(|| anyhow::anyhow!("Unexpected {} list output for environment {}", lookup.backend().binary(), env_name))?;
+    
+    let env_path = get_env_path(env_name, lookup).unwrap_or_else(|e| {
+        warn!("Could not determine path of environment {}: {}", env_name, e);
+        None
+    });
+    
+    let mut env = CondaEnvironment {
+        name: Some(env_name.to_string()),
+        channels: Vec::new(),
+        dependencies: Vec::new(),
+        prefix: env_path.clone(),
+        variables: None,
+        extra: HashMap::new(),
+    };
+    
+    for record in installed {
+        let Some(name) = record["name"].as_str() else { continue };
+        
+        // conda reports pip-installed packages under the "pypi" channel
+        let channel = match record["channel"].as_str() {
+            Some("pypi") => "pip",
+            Some(channel) => channel,
+            None => "unknown",
+        };
+        if channel != "pip" && !env.channels.iter().any(|c| c == channel) {
+            env.channels.push(channel.to_string());
+        }
+        
+        let mut extra = HashMap::new();
+        for (key, field) in [("version", "version"), ("build", "build_string")] {
+            if let Some(value) = record[field].as_str() {
+                extra.insert(key.to_string(), serde_yaml::Value::String(value.to_string()));
+            }
+        }
+        extra.insert("channel".to_string(), serde_yaml::Value::String(channel.to_string()));
+        
+        let meta = env_path.as_deref()
+            .zip(record["dist_name"].as_str())
+            .and_then(|(path, dist_name)| installed_package_meta(path, dist_name));
+        if let Some(meta) = meta {
+            if let Some(size) = meta["size"].as_u64() {
+                extra.insert("size".to_string(), serde_yaml::Value::Number(size.into()));
+            }
+            if let Some(license) = meta["license"].as_str() {
+                extra.insert("license".to_string(), serde_yaml::Value::String(license.to_string()));
+            }
+        }
+        
+        env.dependencies.push(Dependency::Complex(ComplexDependency {
+            name: Some(name.to_string()),
+            pip: None,
+            extra,
+        }));
+    }
+    
+    info!("Found {} installed packages in environment {}", env.dependencies.len(), env_name);
+    Ok(env)
+}
+
+/// Read a package's conda-meta record (size, license, ...) in an environment
+fn installed_package_meta(env_path: &str, dist_name: &str) -> Option<serde_json::Value> {
+    let record = fs::read_to_string(Path::new(env_path).join("conda-meta").join(format!("{}.json", dist_name))).ok()?;
+    serde_json::from_str(&record).ok()
+}
+
 /// Calculate the total size of a directory recursively
 fn calculate_directory_size(dir_path: &str) -> Result<u64> {
     debug!("Calculating directory size for: {}", dir_path);
@@ -250,12 +669,12 @@ fn calculate_directory_size(dir_path: &str) -> Result<u64> {
 }
 
 /// Enriches package information with data from Conda API
-pub fn enrich_packages(packages: &mut Vec<Package>) -> Result<()> {
+pub fn enrich_packages(packages: &mut Vec<Package>, lookup: &LookupOptions) -> Result<()> {
     info!("Enriching package information for {} packages", packages.len());
     
     for package in packages {
         // Skip packages without a name or pip packages
-        if package.name.is_empty() || package.name.contains('>') {
+        if package.name.is_empty() || package.name.contains('>') || package.channel.as_deref() == Some("pip") {
             debug!("Skipping package: {}", package.name);
             continue;
         }
@@ -263,7 +682,7 @@ pub fn enrich_packages(packages: &mut Vec<Package>) -> Result<()> {
         debug!("Enriching package: {}", package.name);
         
         // Try to get package info from API
-        match get_package_info(&package.name, package.channel.as_deref()) {
+        match get_package_info(&package.name, package.channel.as_deref(), lookup) {
             Ok(info) => {
                 // Check if outdated
                 package.is_outdated = is_outdated(package, &info);
@@ -274,6 +693,10 @@ pub fn enrich_packages(packages: &mut Vec<Package>) -> Result<()> {
                 // Set package size
                 package.size = info.size;
                 
+                if info.license.is_some() {
+                    package.license = info.license.clone();
+                }
+                
                 debug!("Enriched {}: outdated={}, latest={}, size={:?}", 
                        package.name, package.is_outdated, 
                        info.latest_version, package.size);
@@ -289,25 +712,38 @@ pub fn enrich_packages(packages: &mut Vec<Package>) -> Result<()> {
 }
 
 /// Get the latest version of a package from conda-forge
-pub fn get_latest_version(package_name: &str) -> Result<String> {
+pub fn get_latest_version(package_name: &str, lookup: &LookupOptions) -> Result<String> {
+    get_latest_version_with_source(package_name, None, lookup).map(|(version, _)| version)
+}
+
+/// Get the latest version of a package along with the name of the source that provided it
+/// ("conda", "anaconda-api" or "pypi"). On anaconda.org the package's declared `channel`
+/// is queried before the default channels; pip packages are only looked up on PyPI
+pub fn get_latest_version_with_source(package_name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Result<(String, &'static str)> {
+    // conda search queries the remote channels, so it is skipped offline as well
+    lookup.ensure_online(&format!("latest version lookup for {}", package_name))?;
+    
+    if channel.map(Channel::parse).is_some_and(|channel| channel.is_pip()) {
+        return get_latest_version_pypi(&build_http_client(&lookup.network), package_name, lookup);
+    }
+    
     // First try using conda directly
-    match get_latest_version_conda(package_name) {
-        Ok(version) => return Ok(version),
+    match get_latest_version_conda(package_name, lookup) {
+        Ok(version) => return Ok((version, "conda")),
         Err(e) => debug!("Failed to get latest version via conda: {}", e),
     }
     
     // Fall back to Anaconda API
-    get_latest_version_api(package_name)
+    get_latest_version_api(package_name, channel, lookup)
 }
 
 /// Get the latest version using conda command
-fn get_latest_version_conda(package_name: &str) -> Result<String> {
+fn get_latest_version_conda(package_name: &str, lookup: &LookupOptions) -> Result<String> {
     info!("Getting latest version for {} via conda", package_name);
     
-    let output = Command::new("conda")
-        .args(["search", package_name, "--json"])
-        .output()
-        .with_context(|| format!("Failed to execute conda search for {}", package_name))?;
+    let output = run_command(lookup.backend_command()
+        .args(["search", package_name, "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} search for {}", lookup.backend().binary(), package_name))?;
         
     if !output.status.success() {
         return Err(anyhow::anyhow!("conda search command failed with status: {}", output.status));
@@ -328,9 +764,9 @@ fn get_latest_version_conda(package_name: &str) -> Result<String> {
         
         // Sort versions and get latest (last in sorted array)
         versions.sort_by(|a, b| {
-            // Try to use semver for comparison if possible
-            match (Version::parse(a), Version::parse(b)) {
-                (Ok(ver_a), Ok(ver_b)) => ver_a.cmp(&ver_b),
+            // Use conda version ordering if possible
+            match (CondaVersion::parse(a), CondaVersion::parse(b)) {
+                (Some(ver_a), Some(ver_b)) => ver_a.cmp(&ver_b),
                 _ => a.cmp(b) // Fallback to lexicographic ordering
             }
         });
@@ -343,26 +779,25 @@ fn get_latest_version_conda(package_name: &str) -> Result<String> {
     Err(anyhow::anyhow!("Failed to find latest version for {}", package_name))
 }
 
-/// Get the latest version using Anaconda API
-fn get_latest_version_api(package_name: &str) -> Result<String> {
+/// Get the latest version using Anaconda API, trying the declared channel before the defaults
+fn get_latest_version_api(package_name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Result<(String, &'static str)> {
+    lookup.ensure_online(&format!("Anaconda API lookup for {}", package_name))?;
     info!("Getting latest version for {} via API", package_name);
     
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+    let client = build_http_client(&lookup.network);
     
-    // Try conda-forge first, then default channels
-    for channel in &["conda-forge", "main"] {
-        let url = format!("https://api.anaconda.org/package/{}/{}", channel, package_name);
+    for channel in api_channels(package_name, channel)? {
+        let url = format!("{}/{}/{}", ANACONDA_API_URL, channel, package_name);
         
-        match client.get(&url).send() {
+        match send_with_retry(client.get(&url), &lookup.network) {
             Ok(response) => {
                 if response.status().is_success() {
                     let json: serde_json::Value = response.json()
                         .with_context(|| format!("Failed to parse API response for {}", package_name))?;
                     
                     if let Some(latest) = json["latest_version"].as_str() {
-                        return Ok(latest.to_string());
+                        info!("Latest version of {} is {} (channel {})", package_name, latest, channel);
+                        return Ok((latest.to_string(), "anaconda-api"));
                     }
                 }
             },
@@ -371,15 +806,20 @@ fn get_latest_version_api(package_name: &str) -> Result<String> {
     }
     
     // Try PyPI for Python packages
+    get_latest_version_pypi(&client, package_name, lookup)
+}
+
+/// Get the latest version of a package on PyPI
+fn get_latest_version_pypi(client: &Client, package_name: &str, lookup: &LookupOptions) -> Result<(String, &'static str)> {
     let pypi_url = format!("https://pypi.org/pypi/{}/json", package_name);
-    match client.get(&pypi_url).send() {
+    match send_with_retry(client.get(&pypi_url), &lookup.network) {
         Ok(response) => {
             if response.status().is_success() {
                 let json: serde_json::Value = response.json()
                     .with_context(|| format!("Failed to parse PyPI API response for {}", package_name))?;
                 
                 if let Some(version) = json["info"]["version"].as_str() {
-                    return Ok(version.to_string());
+                    return Ok((version.to_string(), "pypi"));
                 }
             }
         },
@@ -390,25 +830,80 @@ fn get_latest_version_api(package_name: &str) -> Result<String> {
 }
 
 /// Get the size of a package in bytes
-pub fn get_package_size(package_name: &str) -> Result<u64> {
+pub fn get_package_size(package_name: &str, lookup: &LookupOptions) -> Result<u64> {
+    get_package_size_with_source(package_name, lookup).map(|(size, _)| size)
+}
+
+/// Get the size of a package in bytes along with the name of the source that provided it
+/// ("conda" or "anaconda-api")
+pub fn get_package_size_with_source(package_name: &str, lookup: &LookupOptions) -> Result<(u64, &'static str)> {
+    lookup.ensure_online(&format!("package size lookup for {}", package_name))?;
+    
     // First try using conda directly
-    match get_package_size_conda(package_name) {
-        Ok(size) => return Ok(size),
+    match get_package_size_conda(package_name, lookup) {
+        Ok(size) => return Ok((size, "conda")),
         Err(e) => debug!("Failed to get package size via conda: {}", e),
     }
     
     // Fall back to Anaconda API
-    get_package_size_api(package_name)
+    get_package_size_api(package_name, lookup).map(|size| (size, "anaconda-api"))
+}
+
+/// Get the download size in bytes of a package's archive on anaconda.org: the file built for
+/// `platform` (or noarch), taking the newest version that satisfies the `version` spec.
+/// The package's own channel is tried first, then conda-forge and main
+pub fn get_package_download_size(
+    package_name: &str,
+    version: Option<&str>,
+    channel: Option<&str>,
+    platform: Option<&str>,
+    lookup: &LookupOptions,
+) -> Result<u64> {
+    lookup.ensure_online(&format!("download size lookup for {}", package_name))?;
+    info!("Getting download size for {} via API", package_name);
+    
+    let client = build_http_client(&lookup.network);
+    
+    for channel in api_channels(package_name, channel)? {
+        let url = format!("{}/{}/{}", ANACONDA_API_URL, channel, package_name);
+        
+        match send_with_retry(client.get(&url), &lookup.network) {
+            Ok(response) if response.status().is_success() => {
+                let json: serde_json::Value = response.json()
+                    .with_context(|| format!("Failed to parse API response for {}", package_name))?;
+                let files = json["files"].as_array().map(Vec::as_slice).unwrap_or_default();
+                
+                let candidates = files.iter().filter(|file| {
+                    let subdir = file["attrs"]["subdir"].as_str().unwrap_or("noarch");
+                    let for_platform = subdir == "noarch" || platform.is_none_or(|platform| subdir == platform);
+                    let for_version = version.is_none_or(|spec| {
+                        file["version"].as_str().is_some_and(|v| crate::version::matches_spec(v, spec))
+                    });
+                    for_platform && for_version
+                });
+                let newest = candidates.max_by_key(|file| {
+                    file["version"].as_str().and_then(crate::version::CondaVersion::parse)
+                });
+                
+                if let Some(size) = newest.and_then(|file| file["size"].as_u64()) {
+                    return Ok(size);
+                }
+            },
+            Ok(response) => debug!("API request to {} returned {}", url, response.status()),
+            Err(e) => debug!("API request to {} failed: {}", url, e),
+        }
+    }
+    
+    Err(anyhow::anyhow!("Could not find a package archive for {}", package_name))
 }
 
 /// Get package size using conda command
-fn get_package_size_conda(package_name: &str) -> Result<u64> {
+fn get_package_size_conda(package_name: &str, lookup: &LookupOptions) -> Result<u64> {
     info!("Getting package size for {} via conda", package_name);
     
-    let output = Command::new("conda")
-        .args(["search", package_name, "--info", "--json"])
-        .output()
-        .with_context(|| format!("Failed to execute conda search --info for {}", package_name))?;
+    let output = run_command(lookup.backend_command()
+        .args(["search", package_name, "--info", "--json"]), lookup.command_timeout_secs)
+        .with_context(|| format!("Failed to execute {} search --info for {}", lookup.backend().binary(), package_name))?;
         
     if !output.status.success() {
         return Err(anyhow::anyhow!("conda search command failed with status: {}", output.status));
@@ -430,18 +925,17 @@ fn get_package_size_conda(package_name: &str) -> Result<u64> {
 }
 
 /// Get package size using Anaconda API
-fn get_package_size_api(package_name: &str) -> Result<u64> {
+fn get_package_size_api(package_name: &str, lookup: &LookupOptions) -> Result<u64> {
+    lookup.ensure_online(&format!("Anaconda API lookup for {}", package_name))?;
     info!("Getting package size for {} via API", package_name);
     
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+    let client = build_http_client(&lookup.network);
     
     // Try conda-forge first, then default channels
     for channel in &["conda-forge", "main"] {
         let url = format!("https://api.anaconda.org/package/{}/{}", channel, package_name);
         
-        match client.get(&url).send() {
+        match send_with_retry(client.get(&url), &lookup.network) {
             Ok(response) => {
                 if response.status().is_success() {
                     let json: serde_json::Value = response.json()
@@ -461,4 +955,22 @@ fn get_package_size_api(package_name: &str) -> Result<u64> {
     }
     
     Err(anyhow::anyhow!("Could not determine package size for {}", package_name))
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_channels_try_the_declared_channel_first() {
+        assert_eq!(api_channels("numpy", Some("bioconda")).unwrap()[0], "bioconda");
+        assert_eq!(api_channels("numpy", Some("conda-forge")).unwrap(), DEFAULT_API_CHANNELS);
+        assert_eq!(api_channels("numpy", None).unwrap(), DEFAULT_API_CHANNELS);
+    }
+
+    #[test]
+    fn pip_and_local_packages_are_not_looked_up_on_anaconda() {
+        assert!(api_channels("requests", Some("pip")).is_err());
+        assert!(api_channels("requests", Some("pypi")).is_err());
+        assert!(api_channels("mypkg", Some("file:///opt/channel")).is_err());
+    }
+}
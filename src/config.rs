@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Cli, Commands, OutputFormat};
+use crate::conda_api::CondaBackend;
+
+/// Name of the config file, looked up in the current directory and then in the
+/// conda-env-inspect config directory (e.g. ~/.config/conda-env-inspect/conda-inspect.toml)
+pub const CONFIG_FILE: &str = "conda-inspect.toml";
+
+/// Default flags read from a `conda-inspect.toml` file. Every key is optional; flags
+/// given on the command line take precedence over the file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Output format, by the same names as `--format`
+    pub format: Option<String>,
+    /// Output file path for the analysis report
+    pub output: Option<PathBuf>,
+    /// Check for outdated packages
+    pub check_outdated: Option<bool>,
+    /// Flag pinned packages in the output
+    pub flag_pinned: Option<bool>,
+    /// Timeout in seconds for each network request
+    pub timeout: Option<u64>,
+    /// Package manager used for metadata lookups (conda, mamba or micromamba)
+    pub backend: Option<String>,
+    /// Globs of packages to leave out of every analysis, added to `--exclude`
+    pub exclude: Vec<String>,
+    /// Accepted vulnerabilities (advisory IDs or package@version pairs), added to the
+    /// `.conda-inspect-ignore` entries
+    pub ignore_vulns: Vec<String>,
+}
+
+impl Config {
+    /// Read a config file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = crate::parsers::read_text_file(&path)
+            .with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {:?}", path.as_ref()))
+    }
+
+    /// Find the config file in the current directory, or else in the config directory
+    pub fn find() -> Option<PathBuf> {
+        let local = PathBuf::from(CONFIG_FILE);
+        if local.is_file() {
+            return Some(local);
+        }
+        dirs::config_dir()
+            .map(|dir| dir.join("conda-env-inspect").join(CONFIG_FILE))
+            .filter(|path| path.is_file())
+    }
+
+    /// Fill in the options of `cli` that weren't given on the command line. `matches` are the
+    /// matches `cli` was parsed from, to tell explicit flags from clap's defaults. `format`
+    /// also applies to subcommands with their own `--format`, and `check_outdated` and
+    /// `flag_pinned` to subcommands with those flags
+    pub fn apply(&self, cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+        let given = |matches: &ArgMatches, id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let subcommand_matches = matches.subcommand().map(|(_, matches)| matches);
+
+        if let Some(format) = &self.format {
+            let format = parse_value::<OutputFormat>("format", format)?;
+            if !given(matches, "format") {
+                cli.format = format;
+            }
+            let subcommand_format = match &mut cli.command {
+                Some(Commands::Export { format, .. })
+                | Some(Commands::Inspect { format, .. })
+                | Some(Commands::Recommend { format, .. })
+                | Some(Commands::Vulnerabilities { format, .. })
+                | Some(Commands::Audit { format, .. })
+                | Some(Commands::Licenses { format, .. })
+                | Some(Commands::Matrix { format, .. })
+                | Some(Commands::Validate { format, .. }) => Some(format),
+                _ => None,
+            };
+            if let (Some(subcommand_format), Some(subcommand_matches)) = (subcommand_format, subcommand_matches) {
+                if !given(subcommand_matches, "format") {
+                    *subcommand_format = format;
+                }
+            }
+        }
+
+        if cli.output.is_none() {
+            cli.output = self.output.clone();
+        }
+
+        if self.check_outdated == Some(true) {
+            cli.check_outdated = true;
+            match &mut cli.command {
+                Some(Commands::Analyze { check_outdated, .. })
+                | Some(Commands::Inspect { check_outdated, .. })
+                | Some(Commands::Recommend { check_outdated, .. })
+                | Some(Commands::Interactive { check_outdated, .. })
+                | Some(Commands::Stats { check_outdated, .. }) => *check_outdated = true,
+                _ => {},
+            }
+        }
+
+        if self.flag_pinned == Some(true) {
+            cli.flag_pinned = true;
+            if let Some(Commands::Analyze { flag_pinned, .. }) = &mut cli.command {
+                *flag_pinned = true;
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            if !given(matches, "timeout") {
+                cli.timeout = timeout;
+            }
+        }
+
+        // $CONDA_INSPECT_BACKEND still wins over the file, like over the automatic choice
+        if let Some(backend) = &self.backend {
+            let backend = parse_value::<CondaBackend>("backend", backend)?;
+            if cli.backend.is_none() && std::env::var_os("CONDA_INSPECT_BACKEND").is_none() {
+                cli.backend = Some(backend);
+            }
+        }
+
+        cli.exclude.extend(self.exclude.iter().cloned());
+        cli.ignored_vulnerabilities.extend(self.ignore_vulns.iter().cloned());
+
+        Ok(())
+    }
+}
+
+/// Parse a config value by the names its command-line flag accepts
+fn parse_value<T: ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let names: Vec<String> = T::value_variants().iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        anyhow::anyhow!("Invalid `{}` in {}: {} (expected one of {})", key, CONFIG_FILE, value, names.join(", "))
+    })
+}
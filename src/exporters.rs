@@ -1,10 +1,18 @@
 use anyhow::{Context, Result};
+use crossterm::style::{StyledContent, Stylize};
 use prettytable::{Cell, Row, Table};
+use petgraph::algo::{condensation, toposort};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
-use crate::models::EnvironmentAnalysis;
+use crate::advanced_analysis::{self, AdvancedDependencyGraph, EdgeKind};
+use crate::conda_api::LookupOptions;
+use crate::models::{group_recommendations, EnvironmentAnalysis, Package, PinStatus, Recommendation, Severity, Vulnerability, VulnerabilityReport};
 use crate::utils;
 
 /// Export formats supported by the tool
@@ -14,12 +22,20 @@ pub enum ExportFormat {
     Text,
     /// JSON format
     Json,
+    /// YAML format
+    Yaml,
+    /// TOML format
+    Toml,
     /// Markdown format
     Markdown,
     /// HTML format
     Html,
     /// CSV format
     Csv,
+    /// Prometheus text exposition format
+    Prometheus,
+    /// CycloneDX JSON software bill of materials
+    CycloneDx,
 }
 
 impl ExportFormat {
@@ -28,28 +44,235 @@ impl ExportFormat {
         match s.to_lowercase().as_str() {
             "text" | "txt" => Some(ExportFormat::Text),
             "json" => Some(ExportFormat::Json),
+            "yaml" | "yml" => Some(ExportFormat::Yaml),
+            "toml" => Some(ExportFormat::Toml),
             "markdown" | "md" => Some(ExportFormat::Markdown),
             "html" => Some(ExportFormat::Html),
             "csv" => Some(ExportFormat::Csv),
+            "prometheus" | "prom" => Some(ExportFormat::Prometheus),
+            "cyclonedx" | "cdx" | "sbom" => Some(ExportFormat::CycloneDx),
             _ => None,
         }
     }
+
+    /// File extension used when writing this format into an output directory
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Yaml => "yaml",
+            ExportFormat::Toml => "toml",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Prometheus => "prom",
+            ExportFormat::CycloneDx => "cdx.json",
+        }
+    }
 }
 
-/// Export analysis data in the specified format
+/// Export analysis data in the specified format. `lookup` is used by the formats that look
+/// up more data, like the vulnerabilities of a CycloneDX SBOM
 pub fn export_analysis<P: AsRef<Path>>(
     analysis: &EnvironmentAnalysis,
     format: ExportFormat,
     output_path: Option<P>,
+    lookup: &LookupOptions,
+) -> Result<()> {
+    export_analysis_with_graph(analysis, None, format, output_path, lookup)
+}
+
+/// Export analysis data like [`export_analysis`]. When an advanced dependency graph is
+/// given, the Markdown output also renders it as a Mermaid diagram and the HTML output
+/// embeds it as an interactive SVG
+pub fn export_analysis_with_graph<P: AsRef<Path>>(
+    analysis: &EnvironmentAnalysis,
+    graph: Option<&AdvancedDependencyGraph>,
+    format: ExportFormat,
+    output_path: Option<P>,
+    lookup: &LookupOptions,
 ) -> Result<()> {
+    let content = format_analysis_with_graph(analysis, graph, format, lookup)?;
+    write_output(&content, output_path)
+}
+
+/// Render analysis data in the specified format, like [`export_analysis_with_graph`]
+/// without writing it anywhere
+pub fn format_analysis_with_graph(
+    analysis: &EnvironmentAnalysis,
+    graph: Option<&AdvancedDependencyGraph>,
+    format: ExportFormat,
+    lookup: &LookupOptions,
+) -> Result<String> {
     let content = match format {
         ExportFormat::Text => format_as_text(analysis),
         ExportFormat::Json => format_as_json(analysis)?,
-        ExportFormat::Markdown => format_as_markdown(analysis),
-        ExportFormat::Html => format_as_html(analysis),
+        ExportFormat::Yaml => format_as_yaml(analysis)?,
+        ExportFormat::Toml => format_as_toml(analysis),
+        ExportFormat::Markdown => format_as_markdown(analysis, graph),
+        ExportFormat::Html => format_as_html(analysis, graph),
         ExportFormat::Csv => format_as_csv(analysis),
+        ExportFormat::Prometheus => format_as_prometheus(analysis),
+        ExportFormat::CycloneDx => {
+            let vulnerabilities = advanced_analysis::find_vulnerabilities(&analysis.packages, lookup);
+            format_as_cyclonedx(analysis, &vulnerabilities)?
+        }
     };
     
+    Ok(content)
+}
+
+/// Render the analyses of several environment files into one report. JSON and YAML
+/// emit an array of analyses, each with a `file` field; the other formats emit one
+/// section per file (headed by the file name in text output)
+pub fn format_analyses(
+    analyses: &[(&Path, &EnvironmentAnalysis, Option<&AdvancedDependencyGraph>)],
+    format: ExportFormat,
+    lookup: &LookupOptions,
+) -> Result<String> {
+    match format {
+        ExportFormat::Json | ExportFormat::Yaml => {
+            let mut entries = Vec::new();
+            for (file, analysis, _) in analyses {
+                let mut entry = serde_json::to_value(analysis)
+                    .with_context(|| "Failed to serialize analysis")?;
+                entry["file"] = file.display().to_string().into();
+                entries.push(entry);
+            }
+            if format == ExportFormat::Json {
+                serde_json::to_string_pretty(&entries).with_context(|| "Failed to serialize analyses to JSON")
+            } else {
+                serde_yaml::to_string(&entries).with_context(|| "Failed to serialize analyses to YAML")
+            }
+        },
+        _ => {
+            let mut sections = Vec::new();
+            for (file, analysis, graph) in analyses {
+                let content = format_analysis_with_graph(analysis, *graph, format, lookup)?;
+                if format == ExportFormat::Text {
+                    sections.push(format!("==> {} <==\n{}", file.display(), content));
+                } else {
+                    sections.push(content);
+                }
+            }
+            Ok(sections.join("\n"))
+        }
+    }
+}
+
+/// Format vulnerability scan results; JSON and YAML follow the `VulnerabilityReport` schema
+pub fn format_vulnerabilities(report: &VulnerabilityReport, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(report)
+            .with_context(|| "Failed to serialize vulnerabilities to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(report)
+            .with_context(|| "Failed to serialize vulnerabilities to YAML"),
+        ExportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(Vec::new());
+            wtr.write_record(["Package", "Version", "ID", "Severity", "Source", "Description"])?;
+            for v in &report.vulnerabilities {
+                wtr.write_record([
+                    v.package.as_str(),
+                    v.version.as_str(),
+                    v.id.as_deref().unwrap_or(""),
+                    v.severity.as_deref().unwrap_or(""),
+                    v.source.as_str(),
+                    v.description.as_str(),
+                ])?;
+            }
+            Ok(String::from_utf8(wtr.into_inner()?)?)
+        },
+        ExportFormat::Markdown => {
+            let mut output = String::from("# Vulnerabilities\n\n");
+            output.push_str("| Package | Version | ID | Severity | Source | Description |\n");
+            output.push_str("|---------|---------|----|----------|--------|-------------|\n");
+            for v in &report.vulnerabilities {
+                output.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} |\n",
+                    v.package, v.version, v.id.as_deref().unwrap_or(""),
+                    v.severity.as_deref().unwrap_or(""), v.source, v.description.replace('|', "\\|")
+                ));
+            }
+            if report.suppressed > 0 {
+                output.push_str(&format!("\n_{} accepted vulnerabilities suppressed by the ignore list._\n", report.suppressed));
+            }
+            Ok(output)
+        },
+        _ => {
+            let mut output = if report.vulnerabilities.is_empty() {
+                "No known vulnerabilities found in the environment.".to_string()
+            } else {
+                let mut output = format!("Found {} potential security vulnerabilities:\n", report.vulnerabilities.len());
+                for (i, v) in report.vulnerabilities.iter().enumerate() {
+                    output.push_str(&format!("{}. {}\n", i + 1, v));
+                }
+                output
+            };
+            if report.suppressed > 0 {
+                if report.vulnerabilities.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(&format!("{} accepted vulnerabilities suppressed by the ignore list\n", report.suppressed));
+            }
+            Ok(output)
+        }
+    }
+}
+
+/// Format recommendations; JSON and YAML emit the `Recommendation` array as is, the
+/// other formats group them by category with the highest priority first
+pub fn format_recommendations(recommendations: &[Recommendation], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(recommendations)
+            .with_context(|| "Failed to serialize recommendations to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(recommendations)
+            .with_context(|| "Failed to serialize recommendations to YAML"),
+        ExportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(Vec::new());
+            wtr.write_record(["Category", "Priority", "Description", "Value", "Details"])?;
+            for (category, recs) in group_recommendations(recommendations) {
+                for rec in recs {
+                    wtr.write_record([
+                        category.to_string().as_str(),
+                        rec.priority.to_string().as_str(),
+                        rec.description.as_str(),
+                        rec.value.as_str(),
+                        rec.details.as_deref().unwrap_or(""),
+                    ])?;
+                }
+            }
+            Ok(String::from_utf8(wtr.into_inner()?)?)
+        },
+        ExportFormat::Markdown => {
+            let mut output = String::from("# Recommendations\n");
+            for (category, recs) in group_recommendations(recommendations) {
+                output.push_str(&format!("\n## {}\n\n", category.title()));
+                for rec in recs {
+                    output.push_str(&format!("- **{}**: {}\n", rec.priority, rec));
+                }
+            }
+            Ok(output)
+        },
+        _ => {
+            if recommendations.is_empty() {
+                return Ok("No recommendations available for this environment.".to_string());
+            }
+            let mut output = String::new();
+            let mut i = 0;
+            for (category, recs) in group_recommendations(recommendations) {
+                output.push_str(&format!("\n{}:\n", category.title()));
+                for rec in recs {
+                    i += 1;
+                    output.push_str(&format!("{}. [{}] {}\n", i, rec.priority, rec));
+                }
+            }
+            Ok(output.trim_end().to_string())
+        }
+    }
+}
+
+/// Write rendered content to a file, or to stdout when no path is given
+pub fn write_output<P: AsRef<Path>>(content: &str, output_path: Option<P>) -> Result<()> {
     if let Some(path) = output_path {
         let mut file = File::create(path)
             .with_context(|| "Failed to create output file")?;
@@ -82,11 +305,11 @@ fn export_terminal<P: AsRef<Path>>(
     // Add data rows
     for package in &analysis.packages {
         table.add_row(Row::new(vec![
-            Cell::new(&package.name),
+            Cell::new(&package.display_name()),
             Cell::new(package.version.as_deref().unwrap_or("N/A")),
             Cell::new(package.build.as_deref().unwrap_or("N/A")),
             Cell::new(package.channel.as_deref().unwrap_or("default")),
-            Cell::new(if package.is_pinned { "Yes" } else { "No" }),
+            Cell::new(if package.is_pinned() { "Yes" } else { "No" }),
             Cell::new(if package.is_outdated { "Yes" } else { "No" }),
         ]));
     }
@@ -104,6 +327,16 @@ fn export_terminal<P: AsRef<Path>>(
     if let Some(size) = analysis.total_size {
         table.add_row(Row::new(vec![
             Cell::new("Size"),
+            Cell::new(&utils::format_total_size(analysis, size)),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+        ]));
+    }
+    if let Some(size) = analysis.total_size_with_dependencies {
+        table.add_row(Row::new(vec![
+            Cell::new("Size (incl. dependencies)"),
             Cell::new(&utils::format_size(size)),
             Cell::new(""),
             Cell::new(""),
@@ -111,6 +344,16 @@ fn export_terminal<P: AsRef<Path>>(
             Cell::new(""),
         ]));
     }
+    if let Some(download) = &analysis.download_size {
+        table.add_row(Row::new(vec![
+            Cell::new("Download size"),
+            Cell::new(&utils::format_download_size(download)),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+            Cell::new(""),
+        ]));
+    }
     
     // Print the table
     let mut output = Vec::new();
@@ -134,26 +377,99 @@ fn export_terminal<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Few-line summary of an analysis for the `stats` command. `channel_count` is the number of
+/// channels the environment declares; `color` adds ANSI colors for terminal output
+pub fn format_stats(analysis: &EnvironmentAnalysis, channel_count: usize, color: bool) -> String {
+    let paint = |text: String, style: fn(String) -> StyledContent<String>| {
+        if color { style(text).to_string() } else { text }
+    };
+    let count_style = |count: usize, warning: fn(String) -> StyledContent<String>| {
+        if count == 0 { Stylize::green } else { warning }
+    };
+    let label = |text: &str| paint(format!("{:<14}", text), Stylize::bold);
+    
+    let total = analysis.packages.len();
+    let pip = analysis.packages.iter().filter(|p| p.channel.as_deref() == Some("pip")).count();
+    let pinned_percent = if total == 0 { 0.0 } else { analysis.pinned_count as f64 * 100.0 / total as f64 };
+    let conflicts = analysis.packages.iter().filter(|p| p.has_conflicting_pip_version()).count();
+    
+    let mut output = String::new();
+    output.push_str(&format!("{}{}\n", label("Environment:"), analysis.name.as_deref().unwrap_or("unknown")));
+    output.push_str(&format!("{}{} ({} conda, {} pip)\n", label("Packages:"), total, total - pip, pip));
+    output.push_str(&format!("{}{} ({:.1}%)\n", label("Pinned:"), analysis.pinned_count, pinned_percent));
+    let outdated = if !analysis.check_failures.is_empty() {
+        format!(
+            "{} ({} could not be checked)",
+            paint(analysis.outdated_count.to_string(), count_style(analysis.outdated_count, Stylize::yellow)),
+            paint(analysis.check_failures.len().to_string(), Stylize::red)
+        )
+    } else if analysis.enrichment.is_some() {
+        paint(analysis.outdated_count.to_string(), count_style(analysis.outdated_count, Stylize::yellow))
+    } else {
+        "not checked (use --check-outdated)".to_string()
+    };
+    output.push_str(&format!("{}{}\n", label("Outdated:"), outdated));
+    let size = analysis.total_size
+        .map(|size| utils::format_total_size(analysis, size))
+        .unwrap_or_else(|| "unknown".to_string());
+    output.push_str(&format!("{}{}\n", label("Total size:"), size));
+    output.push_str(&format!("{}{}\n", label("Channels:"), channel_count));
+    output.push_str(&format!("{}{}\n", label("Conflicts:"), paint(conflicts.to_string(), count_style(conflicts, Stylize::red))));
+    
+    output
+}
+
 /// Format analysis as plain text
 fn format_as_text(analysis: &EnvironmentAnalysis) -> String {
     let mut output = String::new();
     
     // Environment info
     output.push_str(&format!("Environment: {}\n", analysis.name.as_deref().unwrap_or("unknown")));
+    if let Some(prefix) = &analysis.prefix {
+        output.push_str(&format!("Prefix: {}\n", prefix));
+    }
+    if let Some(platform) = &analysis.platform {
+        output.push_str(&format!("Platform: {}\n", platform));
+    }
     output.push_str(&format!("Packages: {}\n", analysis.packages.len()));
+    if analysis.is_history_export {
+        output.push_str("Spec: --from-history export (explicitly requested packages only)\n");
+    }
+    if analysis.variable_count > 0 {
+        output.push_str(&format!("Environment variables: {}\n", analysis.variable_count));
+    }
     
     if let Some(size) = analysis.total_size {
-        output.push_str(&format!("Total size: {}\n", utils::format_size(size)));
+        output.push_str(&format!("Total size: {}\n", utils::format_total_size(analysis, size)));
+    }
+    if let Some(size) = analysis.total_size_with_dependencies {
+        output.push_str(&format!("Total size (incl. dependencies): {}\n", utils::format_size(size)));
+    }
+    if let Some(download) = &analysis.download_size {
+        output.push_str(&format!("Download size: {}\n", utils::format_download_size(download)));
     }
     
     output.push_str(&format!("Pinned packages: {}\n", analysis.pinned_count));
     output.push_str(&format!("Outdated packages: {}\n", analysis.outdated_count));
+    if let Some(note) = analysis.check_failure_note() {
+        output.push_str(&format!("Note: {}\n", note));
+    }
+    
+    if let Some(enrichment) = &analysis.enrichment {
+        output.push_str(&format!("Enrichment: {}\n", enrichment));
+    }
+    if let Some(metrics) = &analysis.graph_metrics {
+        output.push_str(&format!("Graph metrics: {}\n", metrics));
+    }
     
     // Recommendations
     if !analysis.recommendations.is_empty() {
         output.push_str("\nRecommendations:\n");
-        for rec in &analysis.recommendations {
-            output.push_str(&format!("- {}\n", rec));
+        for (category, recs) in group_recommendations(&analysis.recommendations) {
+            output.push_str(&format!("{}:\n", category.title()));
+            for rec in recs {
+                output.push_str(&format!("- [{}] {}\n", rec.priority, rec));
+            }
         }
     }
     
@@ -167,13 +483,13 @@ fn format_as_text(analysis: &EnvironmentAnalysis) -> String {
             } else {
                 "[outdated]".to_string()
             }
-        } else if package.is_pinned {
+        } else if package.is_pinned() {
             "[pinned]".to_string()
         } else {
             "".to_string()
         };
         
-        output.push_str(&format!("- {} {} {}\n", package.name, version, status));
+        output.push_str(&format!("- {} {} {}\n", package.display_name(), version, status));
     }
     
     output
@@ -185,29 +501,156 @@ fn format_as_json(analysis: &EnvironmentAnalysis) -> Result<String> {
         .with_context(|| "Failed to serialize analysis to JSON")
 }
 
+/// Format analysis as YAML
+fn format_as_yaml(analysis: &EnvironmentAnalysis) -> Result<String> {
+    serde_yaml::to_string(analysis)
+        .with_context(|| "Failed to serialize analysis to YAML")
+}
+
+/// Format analysis as TOML, with one `[[packages]]` table per package
+fn format_as_toml(analysis: &EnvironmentAnalysis) -> String {
+    let mut toml_string = String::new();
+    
+    if let Some(name) = &analysis.name {
+        toml_string.push_str(&format!("name = {}\n", toml_quote(name)));
+    }
+    if let Some(prefix) = &analysis.prefix {
+        toml_string.push_str(&format!("prefix = {}\n", toml_quote(prefix)));
+    }
+    if let Some(platform) = &analysis.platform {
+        toml_string.push_str(&format!("platform = {}\n", toml_quote(platform)));
+    }
+    
+    toml_string.push_str(&format!("total_size = {}\n", analysis.total_size.unwrap_or(0)));
+    if let Some(size) = analysis.total_size_with_dependencies {
+        toml_string.push_str(&format!("total_size_with_dependencies = {}\n", size));
+    }
+    if let Some(download) = &analysis.download_size {
+        toml_string.push_str(&format!("download_size = {}\n", download.bytes));
+    }
+    toml_string.push_str(&format!("pinned_count = {}\n", analysis.pinned_count));
+    toml_string.push_str(&format!("outdated_count = {}\n", analysis.outdated_count));
+    toml_string.push_str(&format!("is_history_export = {}\n", analysis.is_history_export));
+    if analysis.variable_count > 0 {
+        toml_string.push_str(&format!("variable_count = {}\n", analysis.variable_count));
+    }
+    
+    for package in &analysis.packages {
+        toml_string.push_str("\n[[packages]]\n");
+        toml_string.push_str(&format!("name = {}\n", toml_quote(&package.name)));
+        if let Some(version) = &package.version {
+            toml_string.push_str(&format!("version = {}\n", toml_quote(version)));
+        }
+        if let Some(channel) = &package.channel {
+            toml_string.push_str(&format!("channel = {}\n", toml_quote(channel)));
+        }
+        if !package.extras.is_empty() {
+            let extras: Vec<String> = package.extras.iter().map(|extra| toml_quote(extra)).collect();
+            toml_string.push_str(&format!("extras = [{}]\n", extras.join(", ")));
+        }
+        if let Some(build) = &package.build {
+            toml_string.push_str(&format!("build = {}\n", toml_quote(build)));
+        }
+        if let Some(size) = package.size {
+            toml_string.push_str(&format!("size = {}\n", size));
+        }
+        if package.size_estimated {
+            toml_string.push_str("size_estimated = true\n");
+        }
+        if let Some(latest) = &package.latest_version {
+            toml_string.push_str(&format!("latest_version = {}\n", toml_quote(latest)));
+        }
+        toml_string.push_str(&format!("is_pinned = {}\n", package.is_pinned()));
+        toml_string.push_str(&format!("pin_status = {}\n", toml_quote(&package.pin_status.to_string())));
+        toml_string.push_str(&format!("is_outdated = {}\n", package.is_outdated));
+    }
+    
+    toml_string
+}
+
+/// Quotes a value as a TOML basic string, escaping backslashes, quotes and control characters
+fn toml_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
 /// Format analysis as Markdown
-fn format_as_markdown(analysis: &EnvironmentAnalysis) -> String {
+fn format_as_markdown(analysis: &EnvironmentAnalysis, graph: Option<&AdvancedDependencyGraph>) -> String {
     let mut output = String::new();
     
     // Environment info
     output.push_str(&format!("# Environment Analysis: {}\n\n", analysis.name.as_deref().unwrap_or("unknown")));
+    if let Some(prefix) = &analysis.prefix {
+        output.push_str(&format!("- **Prefix**: `{}`\n", prefix));
+    }
+    if let Some(platform) = &analysis.platform {
+        output.push_str(&format!("- **Platform**: {}\n", platform));
+    }
     output.push_str(&format!("- **Packages**: {}\n", analysis.packages.len()));
+    if analysis.is_history_export {
+        output.push_str("- **Spec**: `--from-history` export (explicitly requested packages only)\n");
+    }
+    if analysis.variable_count > 0 {
+        output.push_str(&format!("- **Environment variables**: {}\n", analysis.variable_count));
+    }
     
     if let Some(size) = analysis.total_size {
-        output.push_str(&format!("- **Total size**: {}\n", utils::format_size(size)));
+        output.push_str(&format!("- **Total size**: {}\n", utils::format_total_size(analysis, size)));
+    }
+    if let Some(size) = analysis.total_size_with_dependencies {
+        output.push_str(&format!("- **Total size (incl. dependencies)**: {}\n", utils::format_size(size)));
+    }
+    if let Some(download) = &analysis.download_size {
+        output.push_str(&format!("- **Download size**: {}\n", utils::format_download_size(download)));
     }
     
     output.push_str(&format!("- **Pinned packages**: {}\n", analysis.pinned_count));
     output.push_str(&format!("- **Outdated packages**: {}\n", analysis.outdated_count));
+    if let Some(note) = analysis.check_failure_note() {
+        output.push_str(&format!("- **Note**: {}\n", note));
+    }
+    
+    if let Some(enrichment) = &analysis.enrichment {
+        output.push_str(&format!("- **Enrichment**: {}\n", enrichment));
+    }
     
     // Recommendations
     if !analysis.recommendations.is_empty() {
-        output.push_str("\n## Recommendations\n\n");
-        for rec in &analysis.recommendations {
-            output.push_str(&format!("- {}\n", rec));
+        output.push_str("\n## Recommendations\n");
+        for (category, recs) in group_recommendations(&analysis.recommendations) {
+            output.push_str(&format!("\n### {}\n\n", category.title()));
+            for rec in recs {
+                output.push_str(&format!("- **{}**: {}\n", rec.priority, rec));
+            }
         }
     }
     
+    // Dependency graph metrics
+    if let Some(metrics) = &analysis.graph_metrics {
+        output.push_str("\n## Dependency Graph Metrics\n\n");
+        output.push_str(&format!("- **Max dependency depth**: {}\n", metrics.max_depth));
+        if let Some(name) = &metrics.most_depended_upon {
+            output.push_str(&format!("- **Most depended upon**: {} ({} dependents)\n", name, metrics.max_fan_in));
+        }
+        if let Some(name) = &metrics.most_dependencies {
+            output.push_str(&format!("- **Most dependencies**: {} ({} dependencies)\n", name, metrics.max_fan_out));
+        }
+        output.push_str(&format!("- **Average degree**: {:.2}\n", metrics.average_degree));
+    }
+    
     // Packages
     output.push_str("\n## Package list\n\n");
     output.push_str("| Package | Version | Status |\n");
@@ -220,20 +663,242 @@ fn format_as_markdown(analysis: &EnvironmentAnalysis) -> String {
             } else {
                 "⚠️ Outdated".to_string()
             }
-        } else if package.is_pinned {
+        } else if package.is_pinned() {
             "📌 Pinned".to_string()
         } else {
             "✅ Up-to-date".to_string()
         };
         
-        output.push_str(&format!("| {} | {} | {} |\n", package.name, version, status));
+        output.push_str(&format!("| {} | {} | {} |\n", package.display_name(), version, status));
+    }
+    
+    if let Some(graph) = graph {
+        output.push_str("\n## Dependency Graph\n\n");
+        output.push_str(&format_as_mermaid(graph));
+    }
+    
+    output
+}
+
+/// Render the direct dependency edges of a graph as a Mermaid `graph TD` block
+/// (GitHub renders these inline). Top-level packages are highlighted
+fn format_as_mermaid(graph: &AdvancedDependencyGraph) -> String {
+    let mut nodes: Vec<_> = graph.graph.node_indices().collect();
+    nodes.sort_by(|a, b| graph.graph[*a].cmp(&graph.graph[*b]));
+    
+    // Package names may contain characters Mermaid doesn't allow in IDs, so use
+    // positional IDs and put the name in the label
+    let ids: HashMap<_, String> = nodes.iter()
+        .enumerate()
+        .map(|(i, node)| (*node, format!("n{}", i)))
+        .collect();
+    
+    let mut output = String::from("```mermaid\ngraph TD\n");
+    for node in &nodes {
+        output.push_str(&format!("    {}[\"{}\"]\n", ids[node], graph.graph[*node].replace('"', "#quot;")));
+    }
+    
+    let mut edges: Vec<(&str, &str, &String, &String)> = graph.graph.edge_references()
+        .filter(|edge| *edge.weight() == EdgeKind::Direct)
+        .map(|edge| (
+            graph.graph[edge.source()].as_str(),
+            graph.graph[edge.target()].as_str(),
+            &ids[&edge.source()],
+            &ids[&edge.target()],
+        ))
+        .collect();
+    edges.sort();
+    for (_, _, from, to) in edges {
+        output.push_str(&format!("    {} --> {}\n", from, to));
+    }
+    
+    let roots: Vec<&str> = nodes.iter()
+        .filter(|&&node| {
+            !graph.graph.edges_directed(node, Direction::Incoming)
+                .any(|edge| *edge.weight() == EdgeKind::Direct)
+        })
+        .map(|node| ids[node].as_str())
+        .collect();
+    if !roots.is_empty() {
+        output.push_str("    classDef root fill:#d4edda,stroke:#2ecc71\n");
+        output.push_str(&format!("    class {} root\n", roots.join(",")));
+    }
+    
+    output.push_str("```\n");
+    output
+}
+
+/// Size of a package box in the HTML dependency graph, and the spacing between boxes, in pixels
+const SVG_NODE_SIZE: (f64, f64) = (140.0, 30.0);
+const SVG_NODE_SPACING: (f64, f64) = (160.0, 90.0);
+
+/// Escape text for use in HTML and SVG content and attributes
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render the dependency graph as an inline SVG, layered top-down by dependency depth.
+/// Direct edges are solid, transitive edges dashed (hidden until toggled on), and packages
+/// in a version conflict are red and joined by a red line. Hovering shows details, and
+/// clicking a package highlights its edges
+fn format_as_svg(graph: &AdvancedDependencyGraph) -> String {
+    // Layer each package below everything that depends on it, treating each cycle as one package
+    let direct = graph.graph.filter_map(
+        |node, _| Some(node),
+        |_, kind| (*kind == EdgeKind::Direct).then_some(()),
+    );
+    let condensed = condensation(direct, true);
+    let mut layers: BTreeMap<usize, Vec<NodeIndex>> = BTreeMap::new();
+    if let Ok(order) = toposort(&condensed, None) {
+        let mut level = vec![0usize; condensed.node_count()];
+        for component in order {
+            level[component.index()] = condensed.neighbors_directed(component, Direction::Incoming)
+                .map(|parent| level[parent.index()] + 1)
+                .max()
+                .unwrap_or(0);
+            layers.entry(level[component.index()]).or_default().extend(condensed[component].iter().copied());
+        }
+    }
+    
+    let (node_width, node_height) = SVG_NODE_SIZE;
+    let (spacing_x, spacing_y) = SVG_NODE_SPACING;
+    let widest = layers.values().map(Vec::len).max().unwrap_or(0);
+    let width = widest as f64 * spacing_x + 20.0;
+    let height = layers.len() as f64 * spacing_y + 20.0;
+    
+    // Center each layer, with packages in alphabetical order
+    let mut positions: HashMap<NodeIndex, (f64, f64)> = HashMap::new();
+    for (row, nodes) in layers.values_mut().enumerate() {
+        nodes.sort_by(|a, b| graph.graph[*a].cmp(&graph.graph[*b]));
+        let offset = (width - nodes.len() as f64 * spacing_x) / 2.0;
+        for (column, node) in nodes.iter().enumerate() {
+            let x = offset + column as f64 * spacing_x + spacing_x / 2.0;
+            let y = 10.0 + row as f64 * spacing_y + spacing_y / 2.0;
+            positions.insert(*node, (x, y));
+        }
+    }
+    
+    let conflicted: HashSet<&str> = graph.conflicts.iter()
+        .flat_map(|(a, b, _)| [a.as_str(), b.as_str()])
+        .collect();
+    let id = |node: NodeIndex| format!("pkg-{}", node.index());
+    
+    let mut output = String::new();
+    output.push_str(&format!(
+        "    <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\">\n",
+        width, height, width, height
+    ));
+    output.push_str("      <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\"><path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#555\"/></marker></defs>\n");
+    
+    let mut edges: Vec<_> = graph.graph.edge_references().collect();
+    edges.sort_by_key(|edge| (*edge.weight() == EdgeKind::Direct, edge.source().index(), edge.target().index()));
+    for edge in edges {
+        let (Some(&(x1, y1)), Some(&(x2, y2))) = (positions.get(&edge.source()), positions.get(&edge.target())) else {
+            continue;
+        };
+        let (class, kind) = match edge.weight() {
+            EdgeKind::Direct => ("direct", "depends on"),
+            EdgeKind::Transitive => ("transitive", "transitively depends on"),
+        };
+        output.push_str(&format!(
+            "      <line class=\"edge {}\" data-from=\"{}\" data-to=\"{}\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" marker-end=\"url(#arrow)\"><title>{} {} {}</title></line>\n",
+            class, id(edge.source()), id(edge.target()),
+            x1, y1 + node_height / 2.0, x2, y2 - node_height / 2.0,
+            escape_html(&graph.graph[edge.source()]), kind, escape_html(&graph.graph[edge.target()])
+        ));
+    }
+    
+    for (first, second, description) in &graph.conflicts {
+        let (Some(&a), Some(&b)) = (graph.node_map.get(first), graph.node_map.get(second)) else {
+            continue;
+        };
+        let (Some(&(x1, y1)), Some(&(x2, y2))) = (positions.get(&a), positions.get(&b)) else {
+            continue;
+        };
+        output.push_str(&format!(
+            "      <line class=\"edge conflict\" data-from=\"{}\" data-to=\"{}\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"><title>Conflict between {} and {}: {}</title></line>\n",
+            id(a), id(b), x1, y1, x2, y2,
+            escape_html(first), escape_html(second), escape_html(description)
+        ));
+    }
+    
+    let mut nodes: Vec<_> = positions.iter().collect();
+    nodes.sort_by_key(|(node, _)| node.index());
+    for (&node, &(x, y)) in nodes {
+        let name = &graph.graph[node];
+        let is_root = !graph.graph.edges_directed(node, Direction::Incoming)
+            .any(|edge| *edge.weight() == EdgeKind::Direct);
+        let class = if conflicted.contains(name.as_str()) {
+            "node conflict"
+        } else if is_root {
+            "node root"
+        } else {
+            "node"
+        };
+        let label = if name.chars().count() > 18 {
+            format!("{}…", name.chars().take(17).collect::<String>())
+        } else {
+            name.clone()
+        };
+        let dependencies = graph.graph.edges_directed(node, Direction::Outgoing)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct)
+            .count();
+        let dependents = graph.graph.edges_directed(node, Direction::Incoming)
+            .filter(|edge| *edge.weight() == EdgeKind::Direct)
+            .count();
+        output.push_str(&format!(
+            "      <g class=\"{}\" id=\"{}\"><title>{}: {} dependencies, {} dependents</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.0}\" height=\"{:.0}\" rx=\"5\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text></g>\n",
+            class, id(node), escape_html(name), dependencies, dependents,
+            x - node_width / 2.0, y - node_height / 2.0, node_width, node_height,
+            x, y + 4.0, escape_html(&label)
+        ));
     }
     
+    output.push_str("    </svg>\n");
     output
 }
 
-/// Format analysis as HTML
-fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
+/// Styles and script for the interactive graph in the HTML report
+const HTML_GRAPH_STYLE: &str = r#"    .graph { overflow: auto; border: 1px solid #ddd; }
+    .graph .node rect { fill: #fff; stroke: #555; }
+    .graph .node.root rect { fill: #d4edda; stroke: #2ecc71; }
+    .graph .node.conflict rect { fill: #fdecea; stroke: #e74c3c; }
+    .graph .node.selected rect { stroke-width: 3; }
+    .graph .node { cursor: pointer; }
+    .graph text { font-size: 12px; text-anchor: middle; pointer-events: none; }
+    .graph .edge { stroke: #555; stroke-width: 1.2; }
+    .graph .edge.transitive { stroke: #aaa; stroke-dasharray: 4 3; display: none; }
+    .graph.show-transitive .edge.transitive { display: inline; }
+    .graph .edge.conflict { stroke: #e74c3c; stroke-width: 2; stroke-dasharray: 6 3; }
+    .graph .edge.highlight { stroke: #3498db; stroke-width: 2.5; display: inline; }
+"#;
+
+const HTML_GRAPH_SCRIPT: &str = r#"  <script>
+    (function () {
+      var graph = document.getElementById('dependency-graph');
+      graph.querySelectorAll('.node').forEach(function (node) {
+        node.addEventListener('click', function () {
+          var active = !node.classList.contains('selected');
+          graph.querySelectorAll('.node.selected').forEach(function (other) { other.classList.remove('selected'); });
+          graph.querySelectorAll('.edge').forEach(function (edge) {
+            var touches = edge.getAttribute('data-from') === node.id || edge.getAttribute('data-to') === node.id;
+            edge.classList.toggle('highlight', active && touches);
+          });
+          node.classList.toggle('selected', active);
+        });
+      });
+      document.getElementById('show-transitive').addEventListener('change', function (event) {
+        graph.classList.toggle('show-transitive', event.target.checked);
+      });
+    })();
+  </script>
+"#;
+
+/// Format analysis as HTML. With an advanced dependency graph, an interactive SVG of it is embedded
+fn format_as_html(analysis: &EnvironmentAnalysis, graph: Option<&AdvancedDependencyGraph>) -> String {
     let mut output = String::new();
     
     // HTML header
@@ -243,6 +908,10 @@ fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
     output.push_str("  <meta charset=\"UTF-8\">\n");
     output.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
     output.push_str("  <title>Conda Environment Analysis</title>\n");
+    if let Some(metadata) = &analysis.metadata {
+        output.push_str(&format!("  <meta name=\"generator\" content=\"{} {}\">\n", metadata.tool, metadata.version));
+        output.push_str(&format!("  <meta name=\"date\" content=\"{}\">\n", metadata.generated_at));
+    }
     output.push_str("  <style>\n");
     output.push_str("    body { font-family: Arial, sans-serif; margin: 20px; }\n");
     output.push_str("    table { border-collapse: collapse; width: 100%; }\n");
@@ -252,6 +921,9 @@ fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
     output.push_str("    .outdated { color: #e74c3c; }\n");
     output.push_str("    .pinned { color: #3498db; }\n");
     output.push_str("    .uptodate { color: #2ecc71; }\n");
+    if graph.is_some() {
+        output.push_str(HTML_GRAPH_STYLE);
+    }
     output.push_str("  </style>\n");
     output.push_str("</head>\n");
     output.push_str("<body>\n");
@@ -261,26 +933,73 @@ fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
         analysis.name.as_deref().unwrap_or("unknown")));
     
     output.push_str("  <div class=\"summary\">\n");
+    if let Some(prefix) = &analysis.prefix {
+        output.push_str(&format!("    <p><strong>Prefix:</strong> {}</p>\n", prefix));
+    }
+    if let Some(platform) = &analysis.platform {
+        output.push_str(&format!("    <p><strong>Platform:</strong> {}</p>\n", platform));
+    }
     output.push_str(&format!("    <p><strong>Packages:</strong> {}</p>\n", analysis.packages.len()));
+    if analysis.variable_count > 0 {
+        output.push_str(&format!("    <p><strong>Environment variables:</strong> {}</p>\n", analysis.variable_count));
+    }
     
     if let Some(size) = analysis.total_size {
-        output.push_str(&format!("    <p><strong>Total size:</strong> {}</p>\n", utils::format_size(size)));
+        output.push_str(&format!("    <p><strong>Total size:</strong> {}</p>\n", utils::format_total_size(analysis, size)));
+    }
+    if let Some(size) = analysis.total_size_with_dependencies {
+        output.push_str(&format!("    <p><strong>Total size (incl. dependencies):</strong> {}</p>\n", utils::format_size(size)));
+    }
+    if let Some(download) = &analysis.download_size {
+        output.push_str(&format!("    <p><strong>Download size:</strong> {}</p>\n", utils::format_download_size(download)));
     }
     
     output.push_str(&format!("    <p><strong>Pinned packages:</strong> {}</p>\n", analysis.pinned_count));
     output.push_str(&format!("    <p><strong>Outdated packages:</strong> {}</p>\n", analysis.outdated_count));
+    if let Some(note) = analysis.check_failure_note() {
+        output.push_str(&format!("    <p><strong>Note:</strong> {}</p>\n", escape_html(&note)));
+    }
     output.push_str("  </div>\n");
     
     // Recommendations
     if !analysis.recommendations.is_empty() {
         output.push_str("  <h2>Recommendations</h2>\n");
+        for (category, recs) in group_recommendations(&analysis.recommendations) {
+            output.push_str(&format!("  <h3>{}</h3>\n", category.title()));
+            output.push_str("  <ul>\n");
+            for rec in recs {
+                output.push_str(&format!("    <li><strong>{}</strong>: {}</li>\n", rec.priority, escape_html(&rec.to_string())));
+            }
+            output.push_str("  </ul>\n");
+        }
+    }
+    
+    // Dependency graph metrics
+    if let Some(metrics) = &analysis.graph_metrics {
+        output.push_str("  <h2>Dependency Graph Metrics</h2>\n");
         output.push_str("  <ul>\n");
-        for rec in &analysis.recommendations {
-            output.push_str(&format!("    <li>{}</li>\n", rec));
+        output.push_str(&format!("    <li><strong>Max dependency depth:</strong> {}</li>\n", metrics.max_depth));
+        if let Some(name) = &metrics.most_depended_upon {
+            output.push_str(&format!("    <li><strong>Most depended upon:</strong> {} ({} dependents)</li>\n", name, metrics.max_fan_in));
         }
+        if let Some(name) = &metrics.most_dependencies {
+            output.push_str(&format!("    <li><strong>Most dependencies:</strong> {} ({} dependencies)</li>\n", name, metrics.max_fan_out));
+        }
+        output.push_str(&format!("    <li><strong>Average degree:</strong> {:.2}</li>\n", metrics.average_degree));
         output.push_str("  </ul>\n");
     }
     
+    // Dependency graph
+    if let Some(graph) = graph {
+        output.push_str("  <h2>Dependency Graph</h2>\n");
+        output.push_str("  <p>Green packages are not required by any other package; red packages are in a version conflict. ");
+        output.push_str("Click a package to highlight its dependencies and dependents.</p>\n");
+        output.push_str("  <p><label><input type=\"checkbox\" id=\"show-transitive\"> Show transitive dependencies</label></p>\n");
+        output.push_str("  <div class=\"graph\" id=\"dependency-graph\">\n");
+        output.push_str(&format_as_svg(graph));
+        output.push_str("  </div>\n");
+    }
+    
     // Packages
     output.push_str("  <h2>Package list</h2>\n");
     output.push_str("  <table>\n");
@@ -298,14 +1017,14 @@ fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
             } else {
                 ("outdated", "Outdated".to_string())
             }
-        } else if package.is_pinned {
+        } else if package.is_pinned() {
             ("pinned", "Pinned".to_string())
         } else {
             ("uptodate", "Up-to-date".to_string())
         };
         
         output.push_str("    <tr>\n");
-        output.push_str(&format!("      <td>{}</td>\n", package.name));
+        output.push_str(&format!("      <td>{}</td>\n", package.display_name()));
         output.push_str(&format!("      <td>{}</td>\n", version));
         output.push_str(&format!("      <td class=\"{}\">{}</td>\n", status_class, status_text));
         output.push_str("    </tr>\n");
@@ -315,8 +1034,27 @@ fn format_as_html(analysis: &EnvironmentAnalysis) -> String {
     
     // HTML footer
     output.push_str("  <footer>\n");
-    output.push_str("    <p><em>Generated by conda-env-inspect</em></p>\n");
+    match &analysis.metadata {
+        Some(metadata) => {
+            let ran = |check: bool| if check { "run" } else { "not run" };
+            output.push_str(&format!(
+                "    <p><em>Generated by {} {} at {}{}</em></p>\n",
+                metadata.tool,
+                metadata.version,
+                metadata.generated_at,
+                metadata.input.as_deref().map(|input| format!(" from {}", escape_html(input))).unwrap_or_default()
+            ));
+            output.push_str(&format!(
+                "    <p><em>Outdated check: {}. Vulnerability scan: {}.</em></p>\n",
+                ran(metadata.outdated_check), ran(metadata.vulnerability_check)
+            ));
+        },
+        None => output.push_str("    <p><em>Generated by conda-env-inspect</em></p>\n"),
+    }
     output.push_str("  </footer>\n");
+    if graph.is_some() {
+        output.push_str(HTML_GRAPH_SCRIPT);
+    }
     output.push_str("</body>\n");
     output.push_str("</html>\n");
     
@@ -337,7 +1075,7 @@ fn format_as_csv(analysis: &EnvironmentAnalysis) -> String {
         let size = package.size.map_or("".to_string(), |s| utils::format_size(s));
         let status = if package.is_outdated {
             "outdated"
-        } else if package.is_pinned {
+        } else if package.is_pinned() {
             "pinned"
         } else {
             "up-to-date"
@@ -351,24 +1089,164 @@ fn format_as_csv(analysis: &EnvironmentAnalysis) -> String {
     output
 }
 
-/// Export data to yaml format
-fn export_yaml<P: AsRef<Path>>(
-    analysis: &EnvironmentAnalysis,
-    output_path: Option<P>,
-) -> Result<()> {
-    let yaml_string = serde_yaml::to_string(analysis)?;
+/// Format analysis summary metrics as Prometheus gauges, labelled with the environment name
+fn format_as_prometheus(analysis: &EnvironmentAnalysis) -> String {
+    let mut output = String::new();
     
-    match output_path {
-        Some(path) => {
-            let mut file = File::create(path)?;
-            file.write_all(yaml_string.as_bytes())?;
-        },
-        None => {
-            println!("{}", yaml_string);
+    // Label values must escape backslashes, double quotes and newlines
+    let env_name = analysis.name.as_deref().unwrap_or("unknown")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    
+    let metrics = [
+        ("conda_env_packages_total", "Number of packages in the environment", analysis.packages.len() as u64),
+        ("conda_env_pinned_total", "Number of packages with pinned versions", analysis.pinned_count as u64),
+        ("conda_env_outdated_total", "Number of outdated packages", analysis.outdated_count as u64),
+        ("conda_env_recommendations_total", "Number of optimization recommendations", analysis.recommendations.len() as u64),
+    ];
+    
+    for (name, help, value) in metrics {
+        output.push_str(&format!("# HELP {} {}\n", name, help));
+        output.push_str(&format!("# TYPE {} gauge\n", name));
+        output.push_str(&format!("{}{{env=\"{}\"}} {}\n", name, env_name, value));
+    }
+    
+    if let Some(size) = analysis.total_size {
+        output.push_str("# HELP conda_env_size_bytes Total size of all packages in bytes\n");
+        output.push_str("# TYPE conda_env_size_bytes gauge\n");
+        output.push_str(&format!("conda_env_size_bytes{{env=\"{}\"}} {}\n", env_name, size));
+    }
+    if let Some(size) = analysis.total_size_with_dependencies {
+        output.push_str("# HELP conda_env_size_with_dependencies_bytes Total size of all packages and their transitive dependencies in bytes\n");
+        output.push_str("# TYPE conda_env_size_with_dependencies_bytes gauge\n");
+        output.push_str(&format!("conda_env_size_with_dependencies_bytes{{env=\"{}\"}} {}\n", env_name, size));
+    }
+    if let Some(download) = &analysis.download_size {
+        output.push_str("# HELP conda_env_download_size_bytes Compressed size of the package archives to download in bytes\n");
+        output.push_str("# TYPE conda_env_download_size_bytes gauge\n");
+        output.push_str(&format!("conda_env_download_size_bytes{{env=\"{}\"}} {}\n", env_name, download.bytes));
+    }
+    
+    output
+}
+
+/// CycloneDX specification version the SBOM declares
+const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+
+/// Package URL of a package: `pkg:pypi/...` for pip packages, `pkg:conda/...` otherwise.
+/// The version is only included when the package is pinned to exactly one
+pub fn package_url(package: &Package) -> String {
+    let is_pip = package.channel.as_deref()
+        .is_some_and(|channel| channel.eq_ignore_ascii_case("pip") || channel.eq_ignore_ascii_case("pypi"));
+    let mut purl = if is_pip {
+        // PyPI names are case-insensitive and treat `_` and `-` alike; purl normalizes them
+        format!("pkg:pypi/{}", purl_encode(&package.name.to_lowercase().replace('_', "-")))
+    } else {
+        format!("pkg:conda/{}", purl_encode(&package.name))
+    };
+    if let Some(version) = exact_version(package) {
+        purl.push('@');
+        purl.push_str(&purl_encode(version));
+    }
+    purl
+}
+
+/// The package's version, when the environment pins it to exactly one
+fn exact_version(package: &Package) -> Option<&str> {
+    package.version.as_deref().filter(|_| package.pin_status == PinStatus::Exact)
+}
+
+/// Percent-encode everything but the characters a purl segment may contain as-is
+fn purl_encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'-' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
         }
     }
+    encoded
+}
+
+/// Format the analysis as a CycloneDX JSON SBOM: one component per package, identified by
+/// its package URL, and one vulnerability entry per finding, linked to the affected component
+pub fn format_as_cyclonedx(analysis: &EnvironmentAnalysis, vulnerabilities: &[Vulnerability]) -> Result<String> {
+    let mut components = Vec::new();
+    let mut seen = HashSet::new();
+    let mut refs: HashMap<String, String> = HashMap::new();
+    for package in &analysis.packages {
+        let purl = package_url(package);
+        // Packages declared more than once with the same spec become a single component
+        if !seen.insert(purl.clone()) {
+            continue;
+        }
+        refs.entry(package.name.to_lowercase()).or_insert_with(|| purl.clone());
+        
+        let mut component = serde_json::json!({
+            "type": "library",
+            "bom-ref": purl,
+            "name": package.name,
+            "purl": purl,
+        });
+        if let Some(version) = exact_version(package) {
+            component["version"] = version.into();
+        }
+        if let Some(license) = &package.license {
+            component["licenses"] = serde_json::json!([{ "license": { "name": license } }]);
+        }
+        if let Some(md5) = &package.md5 {
+            component["hashes"] = serde_json::json!([{ "alg": "MD5", "content": md5 }]);
+        }
+        components.push(component);
+    }
     
-    Ok(())
+    let vulnerabilities: Vec<serde_json::Value> = vulnerabilities.iter()
+        .map(|v| {
+            let severity = v.severity.as_deref()
+                .and_then(Severity::from_name)
+                .map_or_else(|| "unknown".to_string(), |severity| severity.to_string());
+            let mut entry = serde_json::json!({
+                "source": { "name": v.source },
+                "ratings": [{ "severity": severity }],
+                "description": v.description,
+            });
+            if let Some(id) = &v.id {
+                entry["id"] = id.as_str().into();
+            }
+            if let Some(purl) = refs.get(&v.package.to_lowercase()) {
+                entry["affects"] = serde_json::json!([{ "ref": purl }]);
+            }
+            entry
+        })
+        .collect();
+    
+    let mut bom = serde_json::json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": CYCLONEDX_SPEC_VERSION,
+        "version": 1,
+        "metadata": {
+            "timestamp": utils::utc_timestamp(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": "application",
+                "name": analysis.name.as_deref().unwrap_or("environment"),
+            },
+        },
+        "components": components,
+    });
+    if !vulnerabilities.is_empty() {
+        bom["vulnerabilities"] = vulnerabilities.into();
+    }
+    
+    serde_json::to_string_pretty(&bom).with_context(|| "Failed to serialize CycloneDX SBOM")
 }
 
 /// Export data to CSV format
@@ -410,63 +1288,10 @@ fn write_csv_data<W: std::io::Write>(wtr: &mut csv::Writer<W>, analysis: &Enviro
             package.build.as_deref().unwrap_or(""),
             &package.size.map_or("".to_string(), |s| s.to_string()),
             &package.is_outdated.to_string(),
-            &package.is_pinned.to_string(),
+            &package.is_pinned().to_string(),
         ])?;
     }
     
     Ok(())
 }
 
-/// Export data to TOML format
-fn export_toml<P: AsRef<Path>>(
-    analysis: &EnvironmentAnalysis,
-    output_path: Option<P>,
-) -> Result<()> {
-    // Convert to TOML (this is a simplified approach)
-    let mut toml_string = String::new();
-    
-    if let Some(name) = &analysis.name {
-        toml_string.push_str(&format!("name = \"{}\"\n\n", name));
-    }
-    
-    toml_string.push_str(&format!("total_size = {}\n", analysis.total_size.unwrap_or(0)));
-    toml_string.push_str(&format!("pinned_count = {}\n", analysis.pinned_count));
-    toml_string.push_str(&format!("outdated_count = {}\n\n", analysis.outdated_count));
-    
-    toml_string.push_str("[[packages]]\n");
-    for package in &analysis.packages {
-        toml_string.push_str(&format!("name = \"{}\"\n", package.name));
-        if let Some(version) = &package.version {
-            toml_string.push_str(&format!("version = \"{}\"\n", version));
-        }
-        if let Some(channel) = &package.channel {
-            toml_string.push_str(&format!("channel = \"{}\"\n", channel));
-        }
-        if let Some(build) = &package.build {
-            toml_string.push_str(&format!("build = \"{}\"\n", build));
-        }
-        if let Some(size) = package.size {
-            toml_string.push_str(&format!("size = {}\n", size));
-        }
-        if let Some(latest) = &package.latest_version {
-            toml_string.push_str(&format!("latest_version = \"{}\"\n", latest));
-        }
-        toml_string.push_str(&format!("is_pinned = {}\n", package.is_pinned));
-        toml_string.push_str(&format!("is_outdated = {}\n\n", package.is_outdated));
-        
-        toml_string.push_str("[[packages]]\n");
-    }
-    
-    match output_path {
-        Some(path) => {
-            let mut file = File::create(path)?;
-            file.write_all(toml_string.as_bytes())?;
-        },
-        None => {
-            println!("{}", toml_string);
-        }
-    }
-    
-    Ok(())
-}
-
@@ -1,50 +1,75 @@
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use indicatif::{ProgressBar, ProgressStyle};
 use log::info;
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    buffer::Buffer,
+    style::{Color, Modifier, Style},
     symbols,
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Table, Row, Cell, canvas::Canvas},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Table, Row, Cell, Widget, canvas::Canvas},
     Terminal,
 };
 use std::io::{stdout, Stdout};
 use std::collections::HashMap;
 use std::cmp::max;
+use std::time::Duration;
 
-use crate::advanced_analysis::AdvancedDependencyGraph;
-use crate::models::EnvironmentAnalysis;
+use crate::advanced_analysis::{self, AdvancedDependencyGraph};
+use crate::models::{EnvironmentAnalysis, Package, Vulnerability};
+
+/// Graphs with more nodes than this open in the text tree view, since the canvas gets unreadable
+const TEXT_TREE_NODE_THRESHOLD: usize = 20;
+
+/// How long to wait for input before redrawing anyway
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Interactive UI for environment analysis
 #[derive(Debug)]
 pub struct InteractiveUI {
     analysis: EnvironmentAnalysis,
     advanced_graph: Option<AdvancedDependencyGraph>,
+    /// Vulnerabilities found in the environment, shown in the package detail pane
+    vulnerabilities: Vec<Vulnerability>,
     selected_tab: usize,
     selected_package: usize,
     graph_scroll: (u16, u16),
+    /// Whether the Dependencies tab shows the indented text tree instead of the canvas
+    deps_text_view: bool,
     viewport_width: u16,
     viewport_height: u16,
+    /// Whether to draw in color (see [`crate::utils::should_use_color`])
+    color: bool,
 }
 
 impl InteractiveUI {
-    /// Create a new interactive UI
-    pub fn new(analysis: EnvironmentAnalysis, advanced_graph: Option<AdvancedDependencyGraph>) -> Result<Self> {
+    /// Create a new interactive UI, drawn in color if `color` is set
+    pub fn new(
+        analysis: EnvironmentAnalysis,
+        advanced_graph: Option<AdvancedDependencyGraph>,
+        vulnerabilities: Vec<Vulnerability>,
+        color: bool,
+    ) -> Result<Self> {
+        let deps_text_view = advanced_graph.as_ref()
+            .is_some_and(|graph| graph.graph.node_count() > TEXT_TREE_NODE_THRESHOLD);
+        
         Ok(Self {
             analysis,
             advanced_graph,
+            vulnerabilities,
             selected_tab: 0,
             selected_package: 0,
             graph_scroll: (0, 0),
+            deps_text_view,
             viewport_width: 0,
             viewport_height: 0,
+            color,
         })
     }
     
@@ -56,65 +81,108 @@ impl InteractiveUI {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
         
+        // Restore the terminal even when the event loop fails
+        let result = self.event_loop(&mut terminal);
+        
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        terminal.show_cursor()?;
+        
+        result
+    }
+    
+    /// Redraw and handle events until the user quits. Input is polled rather than waited
+    /// for, so the UI is redrawn periodically and right after a resize
+    fn event_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
         loop {
-            terminal.draw(|f| self.render_ui(f))?;
-            
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Right => {
-                        if self.selected_tab == 2 && self.advanced_graph.is_some() {
-                            // In graph view, scroll right
-                            self.graph_scroll.0 = self.graph_scroll.0.saturating_add(5);
-                        } else {
-                            self.selected_tab = (self.selected_tab + 1) % 4;
-                        }
-                    },
-                    KeyCode::Left => {
-                        if self.selected_tab == 2 && self.advanced_graph.is_some() {
-                            // In graph view, scroll left
-                            self.graph_scroll.0 = self.graph_scroll.0.saturating_sub(5);
-                        } else {
-                            self.selected_tab = (self.selected_tab + 3) % 4;
-                        }
-                    },
-                    KeyCode::Down => {
-                        if self.selected_tab == 1 {
-                            // In packages tab
-                            self.selected_package = (self.selected_package + 1) % self.analysis.packages.len();
-                        } else if self.selected_tab == 2 && self.advanced_graph.is_some() {
-                            // In graph view, scroll down
-                            self.graph_scroll.1 = self.graph_scroll.1.saturating_add(3);
-                        }
-                    },
-                    KeyCode::Up => {
-                        if self.selected_tab == 1 {
-                            // In packages tab
-                            self.selected_package = (self.selected_package + self.analysis.packages.len() - 1) % self.analysis.packages.len();
-                        } else if self.selected_tab == 2 && self.advanced_graph.is_some() {
-                            // In graph view, scroll up
-                            self.graph_scroll.1 = self.graph_scroll.1.saturating_sub(3);
-                        }
-                    },
-                    KeyCode::Home => {
-                        if self.selected_tab == 2 && self.advanced_graph.is_some() {
-                            // Reset graph scroll position
-                            self.graph_scroll = (0, 0);
-                        }
-                    },
-                    _ => {}
+            terminal.draw(|f| {
+                self.render_ui(f);
+                if !self.color {
+                    f.render_widget(StripColors, f.size());
                 }
+            })?;
+            
+            if !event::poll(EVENT_POLL_INTERVAL)? {
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) => {
+                    let keep_running = !is_ctrl_c(&key) && self.handle_key(key.code);
+                    if !keep_running {
+                        return Ok(());
+                    }
+                },
+                Event::Resize(width, height) => {
+                    // The graph layout is recomputed from the new viewport on the next draw
+                    self.viewport_width = width;
+                    self.viewport_height = height;
+                    terminal.autoresize()?;
+                },
+                _ => {}
             }
         }
+    }
+    
+    /// Apply a key press to the UI state. Returns false when the UI should exit
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        let package_count = self.analysis.packages.len();
         
-        disable_raw_mode()?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-        terminal.show_cursor()?;
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Right => {
+                if self.selected_tab == 2 && self.advanced_graph.is_some() {
+                    // In graph view, scroll right
+                    self.graph_scroll.0 = self.graph_scroll.0.saturating_add(5);
+                } else {
+                    self.selected_tab = (self.selected_tab + 1) % 4;
+                }
+            },
+            KeyCode::Left => {
+                if self.selected_tab == 2 && self.advanced_graph.is_some() {
+                    // In graph view, scroll left
+                    self.graph_scroll.0 = self.graph_scroll.0.saturating_sub(5);
+                } else {
+                    self.selected_tab = (self.selected_tab + 3) % 4;
+                }
+            },
+            KeyCode::Down => {
+                if self.selected_tab == 1 {
+                    // In packages tab (nothing to select when there are no packages)
+                    if package_count > 0 {
+                        self.selected_package = (self.selected_package + 1) % package_count;
+                    }
+                } else if self.selected_tab == 2 && self.advanced_graph.is_some() {
+                    // In graph view, scroll down
+                    self.graph_scroll.1 = self.graph_scroll.1.saturating_add(3);
+                }
+            },
+            KeyCode::Up => {
+                if self.selected_tab == 1 {
+                    // In packages tab (nothing to select when there are no packages)
+                    if package_count > 0 {
+                        self.selected_package = (self.selected_package + package_count - 1) % package_count;
+                    }
+                } else if self.selected_tab == 2 && self.advanced_graph.is_some() {
+                    // In graph view, scroll up
+                    self.graph_scroll.1 = self.graph_scroll.1.saturating_sub(3);
+                }
+            },
+            KeyCode::Home if self.selected_tab == 2 && self.advanced_graph.is_some() => {
+                // Reset graph scroll position
+                self.graph_scroll = (0, 0);
+            },
+            KeyCode::Char('t') if self.selected_tab == 2 && self.advanced_graph.is_some() => {
+                // Switch between the canvas and the text tree
+                self.deps_text_view = !self.deps_text_view;
+                self.graph_scroll = (0, 0);
+            },
+            _ => {}
+        }
         
-        Ok(())
+        true
     }
     
-    fn render_ui(&mut self, f: &mut ratatui::Frame<CrosstermBackend<Stdout>>) {
+    fn render_ui<B: Backend>(&mut self, f: &mut ratatui::Frame<B>) {
         // Save viewport size for scrolling calculations
         self.viewport_width = f.size().width;
         self.viewport_height = f.size().height;
@@ -131,24 +199,28 @@ impl InteractiveUI {
             .block(Block::default().title("Tabs").borders(Borders::ALL))
             .select(self.selected_tab)
             .style(Style::default().fg(Color::White))
-            .highlight_style(Style::default().fg(Color::Yellow));
+            .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
         f.render_widget(tabs, chunks[0]);
         
         match self.selected_tab {
             0 => render_summary_tab(f, chunks[1], &self.analysis),
-            1 => render_packages_tab(f, chunks[1], &self.analysis, self.selected_package),
+            1 => self.render_packages_tab(f, chunks[1]),
             2 => self.render_deps_tab(f, chunks[1]),
             3 => render_recommendations_tab(f, chunks[1], &self.analysis),
             _ => unreachable!(),
         };
     }
     
-    fn render_deps_tab(&self, f: &mut ratatui::Frame<CrosstermBackend<Stdout>>, area: Rect) {
-        if let Some(graph) = &self.advanced_graph {
+    fn render_deps_tab<B: Backend>(&self, f: &mut ratatui::Frame<B>, area: Rect) {
+        if self.advanced_graph.as_ref().is_some_and(|graph| graph.graph.node_count() == 0) {
+            render_empty_message(f, area, "Dependency Graph");
+        } else if let (Some(graph), true) = (&self.advanced_graph, self.deps_text_view) {
+            self.render_deps_tree(f, area, graph);
+        } else if let Some(graph) = &self.advanced_graph {
             // Split the area into two parts: graph visualization and details
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Min(0), Constraint::Length(7)].as_ref())
+                .constraints([Constraint::Min(0), Constraint::Length(8)].as_ref())
                 .split(area);
             
             // Create a visual graph layout
@@ -177,8 +249,9 @@ impl InteractiveUI {
                     
                     // Create a lookup map for positions
                     let mut position_map = std::collections::HashMap::new();
-                    for (idx, name, x, y) in &positions_vec {
-                        position_map.insert(name.clone(), (*x, *y));
+                    // Edges attach to the middle of each label
+                    for (_, name, x, y) in &positions_vec {
+                        position_map.insert(name.clone(), (*x + name.chars().count() as u16 / 2, *y));
                     }
                     
                     // Draw edges first
@@ -250,6 +323,11 @@ impl InteractiveUI {
             let node_count = graph.graph.node_count();
             let edge_count = graph.graph.edge_count();
             let conflict_count = graph.conflicts.len();
+            let metrics = graph.metrics();
+            let leader = |name: &Option<String>, count: usize| match name {
+                Some(name) => format!("{} ({})", name, count),
+                None => "-".to_string(),
+            };
             
             let info_text = vec![
                 Line::from(vec![
@@ -260,10 +338,20 @@ impl InteractiveUI {
                     Span::raw("  Conflicts: "),
                     Span::styled(conflict_count.to_string(), Style::default().fg(Color::Red)),
                 ]),
+                Line::from(vec![
+                    Span::raw("Max depth: "),
+                    Span::styled(metrics.max_depth.to_string(), Style::default().fg(Color::Green)),
+                    Span::raw("  Most depended upon: "),
+                    Span::styled(leader(&metrics.most_depended_upon, metrics.max_fan_in), Style::default().fg(Color::Blue)),
+                    Span::raw("  Most dependencies: "),
+                    Span::styled(leader(&metrics.most_dependencies, metrics.max_fan_out), Style::default().fg(Color::Blue)),
+                    Span::raw("  Avg degree: "),
+                    Span::styled(format!("{:.2}", metrics.average_degree), Style::default().fg(Color::Green)),
+                ]),
                 Line::from(Span::raw("")),
                 Line::from(vec![
                     Span::styled("Navigation: ", Style::default().fg(Color::Yellow)),
-                    Span::raw("Arrow keys to move, Home to reset view")
+                    Span::raw("Arrow keys to move, Home to reset view, t for text tree")
                 ]),
                 Line::from(vec![
                     Span::styled("Legend: ", Style::default().fg(Color::Yellow)),
@@ -294,99 +382,248 @@ impl InteractiveUI {
     }
 }
 
-/// Calculate a layout for the graph visualization returning a vector of node data
-/// Each entry contains (node_index, name, x, y)
-fn calculate_graph_layout_vec(graph: &AdvancedDependencyGraph) -> (Vec<(petgraph::graph::NodeIndex, String, u16, u16)>, u16, u16) {
-    let mut positions_vec = Vec::new();
-    
-    // Organize nodes into layers based on dependencies
-    let mut layers = Vec::new();
-    let mut assigned = std::collections::HashSet::new();
-    
-    // First layer: nodes with no outgoing edges (leaf nodes)
-    let mut layer = Vec::new();
-    for node in graph.graph.node_indices() {
-        let name = &graph.graph[node];
-        if graph.graph.neighbors_directed(node, petgraph::Direction::Outgoing).count() == 0 {
-            layer.push((node, name.clone()));
-            assigned.insert(name.clone());
+impl InteractiveUI {
+    /// Render the package table next to a detail pane for the selected package
+    fn render_packages_tab<B: Backend>(&self, f: &mut ratatui::Frame<B>, area: Rect) {
+        if self.analysis.packages.is_empty() {
+            render_empty_message(f, area, "Packages");
+            return;
+        }
+        
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)].as_ref())
+            .split(area);
+        
+        render_packages_table(f, chunks[0], &self.analysis, self.selected_package);
+        if let Some(package) = self.analysis.packages.get(self.selected_package) {
+            self.render_package_detail(f, chunks[1], package);
         }
-    }
-    if !layer.is_empty() {
-        layers.push(layer);
     }
     
-    // Add subsequent layers
-    while assigned.len() < graph.graph.node_count() {
-        let mut next_layer = Vec::new();
+    /// Render everything known about one package: versions, origin, size, license,
+    /// its place in the dependency graph and its vulnerabilities
+    fn render_package_detail<B: Backend>(&self, f: &mut ratatui::Frame<B>, area: Rect, package: &Package) {
+        let field = |label: &str, value: String, color: Color| Line::from(vec![
+            Span::raw(format!("{}: ", label)),
+            Span::styled(value, Style::default().fg(color)),
+        ]);
         
-        for node in graph.graph.node_indices() {
-            let name = &graph.graph[node];
-            if assigned.contains(name) {
-                continue;
-            }
-            
-            // Check if all dependencies are already assigned
-            let mut all_deps_assigned = true;
-            for neighbor in graph.graph.neighbors_directed(node, petgraph::Direction::Outgoing) {
-                let neighbor_name = &graph.graph[neighbor];
-                if !assigned.contains(neighbor_name) {
-                    all_deps_assigned = false;
-                    break;
-                }
-            }
-            
-            if all_deps_assigned {
-                next_layer.push((node, name.clone()));
-                assigned.insert(name.clone());
-            }
+        let (status, status_color) = match (&package.latest_version, package.is_outdated) {
+            (None, _) => ("Not checked (use --check-outdated)", Color::DarkGray),
+            (Some(_), true) => ("Outdated", Color::Yellow),
+            (Some(_), false) => ("Up to date", Color::Green),
+        };
+        let size = match package.size {
+            Some(size) if package.size_estimated => format!("{} (estimated)", format_size(size)),
+            Some(size) => format_size(size),
+            None => "unknown".to_string(),
+        };
+        
+        let mut lines = vec![
+            Line::from(Span::styled(package.display_name(), Style::default().fg(Color::Green))),
+            field("Version", format!("{} ({})", package.version.as_deref().unwrap_or("N/A"), package.pin_status), Color::White),
+            field("Latest version", package.latest_version.clone().unwrap_or_else(|| "unknown".to_string()), Color::White),
+            field("Status", status.to_string(), status_color),
+            field("Channel", package.channel.clone().unwrap_or_else(|| "N/A".to_string()), Color::White),
+            field("Size", size, Color::Blue),
+        ];
+        if let Some(build) = &package.build {
+            lines.push(field("Build", build.clone(), Color::White));
+        }
+        if let Some(license) = &package.license {
+            lines.push(field("License", license.clone(), Color::White));
         }
         
-        if next_layer.is_empty() {
-            // If we can't assign more nodes normally, just add remaining nodes
-            for node in graph.graph.node_indices() {
-                let name = &graph.graph[node];
-                if !assigned.contains(name) {
-                    next_layer.push((node, name.clone()));
-                    assigned.insert(name.clone());
-                }
+        lines.push(Line::from(Span::raw("")));
+        match &self.advanced_graph {
+            Some(graph) => {
+                let list = |names: Option<Vec<String>>| match names {
+                    Some(names) if !names.is_empty() => names.join(", "),
+                    _ => "none".to_string(),
+                };
+                lines.push(field("Depends on", list(graph.direct_dependencies(&package.name)), Color::Cyan));
+                lines.push(field("Required by", list(graph.direct_dependents(&package.name)), Color::Cyan));
             }
+            None => lines.push(field("Dependencies", "not available (use --advanced-graph)".to_string(), Color::DarkGray)),
         }
         
-        if !next_layer.is_empty() {
-            layers.push(next_layer);
+        lines.push(Line::from(Span::raw("")));
+        let vulnerabilities: Vec<&Vulnerability> = self.vulnerabilities.iter()
+            .filter(|v| v.package.eq_ignore_ascii_case(&package.name))
+            .collect();
+        if vulnerabilities.is_empty() {
+            lines.push(field("Vulnerabilities", "none known".to_string(), Color::Green));
         } else {
-            break;
+            lines.push(field("Vulnerabilities", vulnerabilities.len().to_string(), Color::Red));
+            for v in vulnerabilities {
+                let id = v.id.as_deref().unwrap_or("no ID");
+                let severity = v.severity.as_deref().unwrap_or("unknown severity");
+                lines.push(Line::from(vec![
+                    Span::styled(format!("- {} ({}): ", id, severity), Style::default().fg(Color::Red)),
+                    Span::raw(v.description.clone()),
+                ]));
+            }
         }
+        
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title("Details").borders(Borders::ALL))
+            .wrap(ratatui::widgets::Wrap { trim: true });
+        
+        f.render_widget(paragraph, area);
     }
     
-    // Assign positions based on layers
-    let horizontal_spacing = 15;
-    let vertical_spacing = 4;
+    /// Render the dependency graph as a scrollable, indented text tree
+    fn render_deps_tree<B: Backend>(&self, f: &mut ratatui::Frame<B>, area: Rect, graph: &AdvancedDependencyGraph) {
+        let entries = advanced_analysis::dependency_tree(graph);
+        let lines: Vec<Line> = entries.iter()
+            .map(|entry| {
+                let color = if entry.depth == 0 { Color::Green } else { Color::Blue };
+                let mut spans = vec![
+                    Span::raw("  ".repeat(entry.depth)),
+                    Span::styled(entry.name.clone(), Style::default().fg(color)),
+                ];
+                if entry.repeated {
+                    spans.push(Span::styled(" (*)", Style::default().fg(Color::DarkGray)));
+                } else if let Some(count) = graph.transitive_dependency_count(&entry.name).filter(|&count| count > 0) {
+                    spans.push(Span::styled(format!(" [{} total]", count), Style::default().fg(Color::DarkGray)));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        
+        // Keep at least the last screenful of the tree visible
+        let max_scroll = (lines.len() as u16).saturating_sub(area.height.saturating_sub(2));
+        let title = format!(
+            "Dependency Tree ({} packages) - arrows to scroll, t for graph view, (*) = listed above, [n total] = all dependencies",
+            graph.graph.node_count()
+        );
+        
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .scroll((self.graph_scroll.1.min(max_scroll), self.graph_scroll.0));
+        
+        f.render_widget(paragraph, area);
+    }
+}
+
+/// Whether a key event is Ctrl-C, which raw mode delivers as a key press instead of a signal
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Calculate a layered layout for the graph visualization returning a vector of node data.
+/// Each entry contains (node_index, name, x, y), where x is the start of the node's label.
+///
+/// Layers come from longest-path layering: packages without dependencies are on layer 0 and
+/// every other package sits one layer above its highest dependency (packages in a cycle share
+/// a layer). Within a layer, nodes are ordered by the average position of their dependencies
+/// to limit edge crossings, spaced by label width so they never overlap, and the layer is
+/// centered on the widest one
+fn calculate_graph_layout_vec(graph: &AdvancedDependencyGraph) -> (Vec<(petgraph::graph::NodeIndex, String, u16, u16)>, u16, u16) {
+    const MARGIN: u16 = 2;
+    const LABEL_GAP: u16 = 3;
+    const VERTICAL_SPACING: u16 = 4;
+    
+    // Strongly connected components come dependencies first, so each component's
+    // dependencies outside it already have a layer
+    let mut layer_of: HashMap<petgraph::graph::NodeIndex, usize> = HashMap::new();
+    for component in petgraph::algo::tarjan_scc(&graph.graph) {
+        let layer = component.iter()
+            .flat_map(|&node| graph.graph.neighbors_directed(node, petgraph::Direction::Outgoing))
+            .filter(|dependency| !component.contains(dependency))
+            .filter_map(|dependency| layer_of.get(&dependency))
+            .map(|layer| layer + 1)
+            .max()
+            .unwrap_or(0);
+        for node in component {
+            layer_of.insert(node, layer);
+        }
+    }
+    
+    let layer_count = layer_of.values().max().map_or(0, |max| max + 1);
+    let mut layers: Vec<Vec<petgraph::graph::NodeIndex>> = vec![Vec::new(); layer_count];
+    for node in graph.graph.node_indices() {
+        layers[layer_of[&node]].push(node);
+    }
+    
+    // Order each layer by the barycenter of its dependencies in the layers below
+    let mut order: HashMap<petgraph::graph::NodeIndex, f64> = HashMap::new();
+    for layer in &mut layers {
+        let barycenter = |node: &petgraph::graph::NodeIndex| {
+            let positions: Vec<f64> = graph.graph.neighbors_directed(*node, petgraph::Direction::Outgoing)
+                .filter_map(|dependency| order.get(&dependency).copied())
+                .collect();
+            if positions.is_empty() {
+                f64::MAX
+            } else {
+                positions.iter().sum::<f64>() / positions.len() as f64
+            }
+        };
+        layer.sort_by(|a, b| barycenter(a).total_cmp(&barycenter(b))
+            .then_with(|| graph.graph[*a].cmp(&graph.graph[*b])));
+        for (position, node) in layer.iter().enumerate() {
+            order.insert(*node, position as f64);
+        }
+    }
+    
+    let label_width = |node: &petgraph::graph::NodeIndex| graph.graph[*node].chars().count() as u16;
+    let layer_width = |layer: &Vec<petgraph::graph::NodeIndex>| {
+        layer.iter().map(label_width).sum::<u16>() + LABEL_GAP * layer.len().saturating_sub(1) as u16
+    };
+    let widest = layers.iter().map(layer_width).max().unwrap_or(0);
+    
+    let mut positions_vec = Vec::new();
     let mut max_width = 0;
     let mut max_height = 0;
     
     for (layer_idx, layer) in layers.iter().enumerate() {
-        let y = layer_idx as u16 * vertical_spacing + 2;
+        let y = layer_idx as u16 * VERTICAL_SPACING + MARGIN;
+        let mut x = MARGIN + (widest - layer_width(layer)) / 2;
         
-        // Center the nodes in each layer
-        for (node_idx, (node, name)) in layer.iter().enumerate() {
-            let x = node_idx as u16 * horizontal_spacing + 2;
-            positions_vec.push((*node, name.clone(), x, y));
-            max_width = max(max_width, x + name.len() as u16);
-            max_height = max(max_height, y + 1);
+        for node in layer {
+            positions_vec.push((*node, graph.graph[*node].clone(), x, y));
+            x += label_width(node);
+            max_width = max(max_width, x);
+            x += LABEL_GAP;
         }
+        max_height = max(max_height, y + 1);
     }
     
     (positions_vec, max_width, max_height)
 }
 
-/// Display a progress bar
-pub fn create_progress_bar(len: u64, message: &str) -> ProgressBar {
+/// Rendered over a finished frame to remove its colors, for `--no-color`. Text attributes such
+/// as bold are kept, and highlighted cells (a colored background, e.g. the selected row) are
+/// drawn reversed instead
+struct StripColors;
+
+impl Widget for StripColors {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                if !matches!(cell.bg, Color::Reset | Color::Black) {
+                    cell.modifier.insert(Modifier::REVERSED);
+                }
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
+}
+
+/// Display a progress bar, in color if `color` is set
+pub fn create_progress_bar(len: u64, message: &str, color: bool) -> ProgressBar {
+    let template = if color {
+        "{spinner:.green} [{elapsed_precise}] {msg} [{wide_bar:.cyan/blue}] {pos}/{len} ({eta})"
+    } else {
+        "{spinner} [{elapsed_precise}] {msg} [{wide_bar}] {pos}/{len} ({eta})"
+    };
     let pb = ProgressBar::new(len);
     pb.set_style(
         ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] {msg} [{wide_bar:.cyan/blue}] {pos}/{len} ({eta})")
+            .template(template)
             .unwrap()
             .progress_chars("#>-"),
     );
@@ -411,17 +648,17 @@ fn format_size(size: u64) -> String {
     }
 }
 
-fn render_summary_tab(
-    f: &mut ratatui::Frame<CrosstermBackend<Stdout>>, 
+fn render_summary_tab<B: Backend>(
+    f: &mut ratatui::Frame<B>, 
     area: ratatui::layout::Rect, 
     analysis: &EnvironmentAnalysis
 ) {
     let total_packages = analysis.packages.len();
     let total_size = analysis.total_size.unwrap_or(0);
     let outdated_packages = analysis.packages.iter().filter(|p| p.is_outdated).count();
-    let pinned_packages = analysis.packages.iter().filter(|p| p.is_pinned).count();
+    let pinned_packages = analysis.packages.iter().filter(|p| p.is_pinned()).count();
     
-    let summary_text = vec![
+    let mut summary_text = vec![
         Line::from(vec![
             Span::raw("Total packages: "),
             Span::styled(total_packages.to_string(), Style::default().fg(Color::Green)),
@@ -429,7 +666,22 @@ fn render_summary_tab(
         Line::from(vec![
             Span::raw("Total size: "),
             Span::styled(format_size(total_size), Style::default().fg(Color::Blue)),
+            Span::raw(if analysis.has_estimated_sizes() { " (partially estimated)" } else { "" }),
         ]),
+    ];
+    if let Some(size) = analysis.total_size_with_dependencies {
+        summary_text.push(Line::from(vec![
+            Span::raw("Total size (incl. dependencies): "),
+            Span::styled(format_size(size), Style::default().fg(Color::Blue)),
+        ]));
+    }
+    if let Some(download) = &analysis.download_size {
+        summary_text.push(Line::from(vec![
+            Span::raw("Download size: "),
+            Span::styled(crate::utils::format_download_size(download), Style::default().fg(Color::Blue)),
+        ]));
+    }
+    summary_text.extend([
         Line::from(vec![
             Span::raw("Outdated packages: "),
             Span::styled(outdated_packages.to_string(), Style::default().fg(Color::Yellow)),
@@ -438,7 +690,13 @@ fn render_summary_tab(
             Span::raw("Pinned packages: "),
             Span::styled(pinned_packages.to_string(), Style::default().fg(Color::Cyan)),
         ]),
-    ];
+    ]);
+    if analysis.variable_count > 0 {
+        summary_text.push(Line::from(vec![
+            Span::raw("Environment variables: "),
+            Span::styled(analysis.variable_count.to_string(), Style::default().fg(Color::Magenta)),
+        ]));
+    }
     
     let summary_paragraph = Paragraph::new(summary_text)
         .block(Block::default().title("Summary").borders(Borders::ALL))
@@ -448,8 +706,8 @@ fn render_summary_tab(
     f.render_widget(summary_paragraph, area);
 }
 
-fn render_packages_tab(
-    f: &mut ratatui::Frame<CrosstermBackend<Stdout>>, 
+fn render_packages_table<B: Backend>(
+    f: &mut ratatui::Frame<B>, 
     area: ratatui::layout::Rect, 
     analysis: &EnvironmentAnalysis,
     selected_package: usize
@@ -472,7 +730,7 @@ fn render_packages_tab(
         };
         
         Row::new(vec![
-            Cell::from(pkg.name.as_str()),
+            Cell::from(pkg.display_name()),
             Cell::from(pkg.version.as_deref().unwrap_or("N/A")),
             Cell::from(pkg.channel.as_deref().unwrap_or("N/A")),
             Cell::from(format_size(pkg.size.unwrap_or(0))),
@@ -492,15 +750,31 @@ fn render_packages_tab(
     f.render_widget(table, area);
 }
 
-fn render_recommendations_tab(
-    f: &mut ratatui::Frame<CrosstermBackend<Stdout>>, 
+/// Render a friendly placeholder for views that have no packages to show
+fn render_empty_message<B: Backend>(
+    f: &mut ratatui::Frame<B>,
+    area: ratatui::layout::Rect,
+    title: &str
+) {
+    let paragraph = Paragraph::new(vec![Line::from(Span::raw("No packages to display."))])
+        .block(Block::default().title(title.to_string()).borders(Borders::ALL))
+        .alignment(ratatui::layout::Alignment::Center);
+    
+    f.render_widget(paragraph, area);
+}
+
+fn render_recommendations_tab<B: Backend>(
+    f: &mut ratatui::Frame<B>, 
     area: ratatui::layout::Rect,
     analysis: &EnvironmentAnalysis
 ) {
     let recommendations = &analysis.recommendations;
     
     let items: Vec<ListItem> = recommendations.iter().map(|rec| {
-        let mut lines = vec![Line::from(Span::raw(&rec.description))];
+        let mut lines = vec![Line::from(vec![
+            Span::styled(format!("[{}/{}] ", rec.category, rec.priority), Style::default().fg(Color::Yellow)),
+            Span::raw(&rec.description),
+        ])];
         
         if let Some(ref details) = rec.details {
             lines.push(Line::from(Span::raw(details)));
@@ -529,4 +803,35 @@ fn calculate_graph_layout(graph: &AdvancedDependencyGraph) -> (HashMap<String, (
     // to avoid duplication of logic
     
     (positions, 0, 0)
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn empty_ui() -> InteractiveUI {
+        let analysis = crate::utils::analyze_environment_from_str(
+            "name: empty\ndependencies: []\n",
+            crate::parsers::FileFormat::Yaml,
+            &crate::utils::AnalysisOptions::default(),
+        ).unwrap();
+        let graph = advanced_analysis::create_advanced_dependency_graph(&analysis.packages, &HashMap::new(), None);
+        InteractiveUI::new(analysis, Some(graph), Vec::new(), false).unwrap()
+    }
+
+    #[test]
+    fn empty_analysis_handles_keys_and_renders_every_tab() {
+        let mut ui = empty_ui();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let keys = [KeyCode::Down, KeyCode::Up, KeyCode::Home, KeyCode::Char('t'), KeyCode::Down, KeyCode::Right, KeyCode::Left];
+
+        for tab in 0..4 {
+            ui.selected_tab = tab;
+            for key in keys {
+                assert!(ui.handle_key(key));
+                terminal.draw(|f| ui.render_ui(f)).unwrap();
+            }
+        }
+        assert!(!ui.handle_key(KeyCode::Char('q')));
+    }
+}
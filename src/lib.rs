@@ -1,17 +1,26 @@
 pub mod advanced_analysis;
 pub mod analysis;
+pub mod audit;
 pub mod cli;
 pub mod conda_api;
+pub mod config;
 pub mod exporters;
 pub mod interactive;
+pub mod licenses;
+pub mod matrix;
 pub mod models;
 pub mod parsers;
 pub mod performance;
+pub mod platforms;
+pub mod select;
 pub mod utils;
+pub mod validate;
+pub mod version;
 
 // Re-export commonly used modules and types
 pub use models::{Package, EnvironmentAnalysis};
-pub use parsers::parse_environment_file;
+pub use parsers::{parse_environment_file, FileFormat};
+pub use utils::{analyze_environment_from_str, analyze_environment_lazy, analyze_environment_with_rules, AnalysisOptions, PackageFilters, RecommendationRule};
 
 // Make these functions public in their modules
 pub use analysis::generate_recommendations; 
\ No newline at end of file
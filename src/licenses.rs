@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::exporters::ExportFormat;
+use crate::models::Package;
+
+/// Label used for packages whose license could not be determined
+const UNKNOWN_LICENSE: &str = "unknown";
+
+/// Packages of an environment grouped by license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseReport {
+    /// Name of the environment
+    pub environment: Option<String>,
+    /// One group per license, sorted by license name
+    pub licenses: Vec<LicenseGroup>,
+}
+
+/// All packages distributed under a single license
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseGroup {
+    /// License as reported by the package metadata, or "unknown"
+    pub license: String,
+    /// Whether the license is a strong copyleft license (GPL or AGPL)
+    pub copyleft: bool,
+    /// Names of the packages under this license, sorted
+    pub packages: Vec<String>,
+}
+
+impl LicenseReport {
+    /// Groups whose license is copyleft
+    pub fn copyleft(&self) -> impl Iterator<Item = &LicenseGroup> {
+        self.licenses.iter().filter(|group| group.copyleft)
+    }
+}
+
+/// Whether a license string names GPL or AGPL. LGPL is weak copyleft and isn't flagged
+pub fn is_copyleft(license: &str) -> bool {
+    let upper = license.to_uppercase();
+    upper.split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| {
+            let word = word.trim_end_matches(|c: char| c.is_ascii_digit());
+            word == "GPL" || word == "AGPL" || word == "GPLV" || word == "AGPLV"
+        })
+        || upper.contains("GNU GENERAL PUBLIC")
+        || upper.contains("GNU AFFERO")
+}
+
+/// Groups the packages by license
+pub fn build_license_report(environment: Option<String>, packages: &[Package]) -> LicenseReport {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for package in packages {
+        let license = package.license.as_deref()
+            .map(str::trim)
+            .filter(|license| !license.is_empty())
+            .unwrap_or(UNKNOWN_LICENSE);
+        groups.entry(license.to_string()).or_default().push(package.name.clone());
+    }
+
+    let licenses = groups.into_iter()
+        .map(|(license, mut packages)| {
+            packages.sort_by_key(|name| name.to_lowercase());
+            LicenseGroup {
+                copyleft: is_copyleft(&license),
+                license,
+                packages,
+            }
+        })
+        .collect();
+
+    LicenseReport { environment, licenses }
+}
+
+/// Formats the license report as text, JSON, YAML, Markdown or CSV
+pub fn format_license_report(report: &LicenseReport, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(report)
+            .with_context(|| "Failed to serialize license report to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(report)
+            .with_context(|| "Failed to serialize license report to YAML"),
+        ExportFormat::Markdown => Ok(format_license_markdown(report)),
+        ExportFormat::Csv => format_license_csv(report),
+        _ => Ok(format_license_text(report)),
+    }
+}
+
+fn format_license_text(report: &LicenseReport) -> String {
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(vec![
+        Cell::new("License"),
+        Cell::new("Count"),
+        Cell::new("Packages"),
+    ]));
+
+    for group in &report.licenses {
+        let license = if group.copyleft { format!("{} (!)", group.license) } else { group.license.clone() };
+        table.add_row(Row::new(vec![
+            Cell::new(&license),
+            Cell::new(&group.packages.len().to_string()),
+            Cell::new(&group.packages.join(", ")),
+        ]));
+    }
+
+    let copyleft_count: usize = report.copyleft().map(|group| group.packages.len()).sum();
+    format!(
+        "{}\n{} licenses, {} packages under a copyleft license (!)\n",
+        table, report.licenses.len(), copyleft_count
+    )
+}
+
+fn format_license_markdown(report: &LicenseReport) -> String {
+    let mut output = String::new();
+
+    output.push_str("# License Report\n\n");
+    if let Some(name) = &report.environment {
+        output.push_str(&format!("Environment: **{}**\n\n", name));
+    }
+    output.push_str("| License | Count | Packages |\n");
+    output.push_str("|---------|-------|----------|\n");
+
+    for group in &report.licenses {
+        let license = if group.copyleft { format!("⚠️ {}", group.license) } else { group.license.clone() };
+        output.push_str(&format!(
+            "| {} | {} | {} |\n",
+            license, group.packages.len(), group.packages.join(", ")
+        ));
+    }
+
+    output
+}
+
+fn format_license_csv(report: &LicenseReport) -> Result<String> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.write_record(["Package", "License", "Copyleft"])?;
+
+    for group in &report.licenses {
+        for package in &group.packages {
+            wtr.write_record([package.as_str(), group.license.as_str(), &group.copyleft.to_string()])?;
+        }
+    }
+
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
@@ -1,48 +1,100 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches};
 use env_logger::Env;
 use indicatif::ProgressBar;
 use log::{debug, info, warn};
+use std::cell::Cell;
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use std::process::Command;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use conda_env_inspect::{
     advanced_analysis,
-    cli::{Cli, Commands},
+    analysis::DotOptions,
+    audit,
+    conda_api,
+    cli::{CacheAction, Cli, Commands},
+    config::Config,
     interactive::{self, create_progress_bar},
+    licenses,
+    matrix,
+    parsers,
+    performance::{self, CachePolicy},
+    platforms,
+    select::Selector,
     utils,
+    validate,
 };
 use conda_env_inspect::exporters::{self, ExportFormat};
-use conda_env_inspect::models::EnvironmentAnalysis;
+use conda_env_inspect::models::{Channel, CondaEnvironment, EnvironmentAnalysis, SortKey, VulnerabilityReport};
 
 fn main() -> Result<()> {
     let start_time = Instant::now();
     
-    // Initialize logger
-    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
-        .format_timestamp(None)
-        .init();
-    
-    info!("Starting conda-env-inspect v{}", env!("CARGO_PKG_VERSION"));
+    // Parse command line arguments, then fill in defaults from the config file
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let config_file = Config::find();
+    if let Some(path) = &config_file {
+        Config::load(path)?.apply(&mut cli, &matches)?;
+    }
     
-    // Check if conda is available and log warning if not
-    check_conda_availability();
+    // Initialize logger; quiet mode only shows warnings and errors
+    let default_level = if cli.quiet || cli.check { "warn" } else { "info" };
+    let mut logger = env_logger::Builder::from_env(Env::default().default_filter_or(default_level));
+    if utils::color_disabled(cli.no_color) {
+        logger.write_style(env_logger::WriteStyle::Never);
+    }
+    logger.format_timestamp(None).init();
     
-    // Parse command line arguments
-    let cli = Cli::parse();
+    info!("Starting conda-env-inspect v{}", env!("CARGO_PKG_VERSION"));
+    if let Some(path) = &config_file {
+        info!("Using config file: {:?}", path);
+    }
     debug!("Parsed command-line arguments: {:?}", cli);
+    
+    // Parallel lookups run on a pool of --max-concurrency threads, which caps simultaneous requests
+    let pool = performance::thread_pool(cli.max_concurrency as usize)?;
+    pool.install(|| run(&cli))?;
 
-    // Create progress bar for long operations
-    let pb = create_progress_bar(100, "Analyzing environment...");
+    info!("Completed successfully in {:.2?}", start_time.elapsed());
+    Ok(())
+}
+
+/// Run the command given on the command line
+fn run(cli: &Cli) -> Result<()> {
+    // Create progress bar for long operations, unless quiet or the output is redirected
+    let pb = if cli.quiet || cli.check || !std::io::stdout().is_terminal() {
+        ProgressBar::hidden()
+    } else {
+        create_progress_bar(100, "Analyzing environment...", !utils::color_disabled(cli.no_color))
+    };
+    let ctx = AnalysisContext::from_cli(cli, &pb)?;
+    let lookup = &ctx.options.lookup;
+    let dot_options = DotOptions {
+        rankdir: cli.rankdir,
+        cluster_by_channel: cli.cluster_by_channel,
+    };
     pb.set_position(0);
 
+    if cli.check {
+        if cli.command.is_some() {
+            return Err(anyhow::anyhow!("--check can't be combined with a subcommand"));
+        }
+        let passed = run_check(&ctx, &cli.file)?;
+        if !passed || (cli.strict && ctx.unchecked.get() > 0) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Handle commands
     match &cli.command {
         Some(Commands::Analyze {
-            file,
+            files,
+            combined,
             check_outdated,
             flag_pinned,
             generate_graph,
@@ -50,58 +102,130 @@ fn main() -> Result<()> {
             graph_output,
             interactive,
             advanced_graph,
+            report_dir,
         }) => {
-            info!("Analyzing environment file: {:?}", file);
-            pb.set_position(10);
-            
-            let mut analysis = if *check_outdated {
-                pb.set_message("Enriching package information...");
-                utils::analyze_environment_parallel(file, *check_outdated, *flag_pinned)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", file))?
-            } else {
-                utils::analyze_environment(file, *check_outdated, *flag_pinned)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", file))?
-            };
-            
-            pb.set_position(50);
-            pb.set_message("Processing dependencies...");
+            let files = matrix::expand_environment_paths(files)?;
+            let separate = files.len() > 1 && !*combined;
+            if separate && *interactive {
+                return Err(anyhow::anyhow!("--interactive needs a single environment file (or --combined)"));
+            }
+            if separate && *generate_graph && graph_output.is_some() {
+                return Err(anyhow::anyhow!("--graph-output names a single file; use --output-dir to write a graph per environment"));
+            }
             
-            let advanced_deps = if *advanced_graph {
-                Some(create_advanced_dependency_graph(&analysis, pb.clone())?)
-            } else {
-                None
-            };
+            // Caches are shared across the files, so each package is only looked up once per run
+            let mut analyses = Vec::new();
+            for file in &files {
+                info!("Analyzing environment file: {:?}", file);
+                pb.set_position(10);
+                
+                if *check_outdated {
+                    pb.set_message("Enriching package information...");
+                }
+                let analysis = ctx.analyze(file, *check_outdated, *flag_pinned, *check_outdated)?;
+                analyses.push((file.clone(), analysis));
+            }
+            if *combined && analyses.len() > 1 {
+                let mut union = utils::combine_analyses(&analyses);
+                if cli.download_size {
+                    utils::add_download_size(&mut union, lookup);
+                }
+                analyses = vec![(files[0].clone(), union)];
+            }
             
-            pb.set_position(80);
+            let format = convert_format(cli.format);
+            // A report bundle replaces the usual report, unless an output path asks for it too
+            let bundle_only = report_dir.is_some() && cli.output.is_none() && cli.output_dir.is_none();
+            // Several analyses go into one report unless each gets its own file in --output-dir
+            let single_report = separate && !bundle_only && (cli.output.is_some() || cli.output_dir.is_none());
+            let mut reports = Vec::new();
+            let mut used_stems = HashSet::new();
             
-            // Generate dependency graph if requested
-            if *generate_graph {
-                if let Some(graph_path) = graph_output {
-                    info!("Generating dependency graph: {:?}", graph_path);
-                    if let Err(e) = utils::generate_dependency_graph(file, graph_path) {
-                        warn!("Failed to generate full dependency graph: {}", e);
-                        println!("Note: Generated a basic dependency graph without all relationships. For complete dependency analysis, please run in an environment with conda installed.");
+            for (file, mut analysis) in analyses {
+                pb.set_position(50);
+                pb.set_message("Processing dependencies...");
+                
+                let advanced_deps = if *advanced_graph {
+                    Some(create_advanced_dependency_graph(&analysis, pb.clone(), cli.depth, lookup)?)
+                } else {
+                    None
+                };
+                if let Some(graph) = &advanced_deps {
+                    analysis.recommendations.extend(advanced_analysis::cycle_recommendations(graph));
+                    analysis.graph_metrics = Some(graph.metrics());
+                }
+                
+                pb.set_position(80);
+                
+                // Environments sharing a name are told apart by file name, so their artifacts don't collide
+                let mut artifact_stem = utils::artifact_stem(&analysis, &file);
+                if !used_stems.insert(artifact_stem.clone()) {
+                    let file_stem = file.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+                    artifact_stem = format!("{}-{}", artifact_stem, file_stem);
+                    used_stems.insert(artifact_stem.clone());
+                }
+                
+                // Generate dependency graph if requested
+                if *generate_graph {
+                    let graph_path = utils::resolve_artifact_path(
+                        graph_output.as_ref(), cli.output_dir.as_ref(), &artifact_stem, "dot")?;
+                    if let Some(graph_path) = &graph_path {
+                        info!("Generating dependency graph: {:?}", graph_path);
+                        if let Err(e) = utils::generate_dependency_graph(&analysis.packages, graph_path, &dot_options, lookup) {
+                            warn!("Failed to generate full dependency graph: {}", e);
+                            println!("Note: Generated a basic dependency graph without all relationships. For complete dependency analysis, please run in an environment with conda installed.");
+                        } else {
+                            println!("Dependency graph saved to: {:?}", graph_path);
+                        }
                     } else {
-                        println!("Dependency graph saved to: {:?}", graph_path);
+                        warn!("No output path specified for dependency graph");
+                        return Err(anyhow::anyhow!("No output path specified for dependency graph (use --graph-output or --output-dir)"));
                     }
+                }
+                
+                pb.set_position(90);
+                
+                if let Some(report_dir) = report_dir {
+                    pb.set_message("Writing report bundle...");
+                    let dir = if separate { report_dir.join(&artifact_stem) } else { report_dir.clone() };
+                    let ignore = ctx.vulnerability_ignore_list(&file)?;
+                    let written = write_report_bundle(&dir, &ignore, &analysis, advanced_deps.as_ref(), &dot_options, lookup)?;
+                    let names: Vec<String> = written.iter()
+                        .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+                        .collect();
+                    println!("Report written to {}: {}", dir.display(), names.join(", "));
+                }
+                
+                // If interactive mode is enabled, launch the TUI
+                if *interactive {
+                    pb.finish_and_clear();
+                    info!("Starting interactive UI");
+                    let vulnerabilities = ctx.scan_vulnerabilities(&file, &analysis.packages)?;
+                    let mut ui = interactive::InteractiveUI::new(analysis, advanced_deps, vulnerabilities, utils::should_use_color(cli.no_color))?;
+                    ui.run()?;
+                } else if bundle_only {
+                    continue;
+                } else if single_report {
+                    reports.push((file, analysis, advanced_deps));
                 } else {
-                    warn!("No output path specified for dependency graph");
-                    return Err(anyhow::anyhow!("No output path specified for dependency graph"));
+                    pb.set_message("Exporting results...");
+                    let output_path = utils::resolve_artifact_path(
+                        cli.output.as_ref(), cli.output_dir.as_ref(), &artifact_stem, format.extension())?;
+                    exporters::export_analysis_with_graph(&analysis, advanced_deps.as_ref(), format, output_path.as_ref(), lookup)
+                        .with_context(|| "Failed to export analysis")?;
                 }
             }
-            
-            pb.set_position(90);
-            
-            // If interactive mode is enabled, launch the TUI
-            if *interactive {
-                pb.finish_and_clear();
-                info!("Starting interactive UI");
-                let mut ui = interactive::InteractiveUI::new(analysis, advanced_deps)?;
-                ui.run()?;
-            } else {
+                
+            if single_report {
                 pb.set_message("Exporting results...");
-                exporters::export_analysis(&analysis, convert_format(cli.format), cli.output.as_ref())
+                let sections: Vec<_> = reports.iter()
+                    .map(|(file, analysis, graph)| (file.as_path(), analysis, graph.as_ref()))
+                    .collect();
+                let content = exporters::format_analyses(&sections, format, lookup)
                     .with_context(|| "Failed to export analysis")?;
+                exporters::write_output(&content, cli.output.as_ref())?;
+            }
+            if !*interactive {
                 pb.finish_with_message("Analysis complete!");
             }
         }
@@ -109,35 +233,60 @@ fn main() -> Result<()> {
             info!("Exporting environment file: {:?}", file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = utils::analyze_environment(file, false, false)
-                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let analysis = ctx.analyze(file, false, false, false)?;
             
             pb.set_position(80);
             pb.set_message("Exporting results...");
             
             info!("Exporting in format: {:?}", format);
-            exporters::export_analysis(&analysis, convert_format(*format), output.as_ref())
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), &utils::artifact_stem(&analysis, file), format.extension())?;
+            exporters::export_analysis(&analysis, format, output_path.as_ref(), lookup)
                 .with_context(|| "Failed to export analysis")?;
             
             pb.finish_with_message("Export complete!");
         }
+        Some(Commands::Inspect { env_name, format, output, check_outdated }) => {
+            info!("Inspecting installed environment: {}", env_name);
+            pb.set_message("Reading installed packages...");
+            
+            let analysis = ctx.analyze_installed(env_name, *check_outdated, true)?;
+            
+            pb.set_position(80);
+            pb.set_message("Exporting results...");
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), env_name, format.extension())?;
+            exporters::export_analysis(&analysis, format, output_path.as_ref(), lookup)
+                .with_context(|| "Failed to export analysis")?;
+            
+            pb.finish_with_message("Inspection complete!");
+        }
         Some(Commands::Graph { file, output, advanced }) => {
             info!("Generating dependency graph for: {:?}", file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = utils::analyze_environment(file, false, false)
-                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let analysis = ctx.analyze(file, false, false, false)?;
             
             pb.set_position(50);
             pb.set_message("Generating graph...");
             
+            let output = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), &utils::artifact_stem(&analysis, file), "dot")?
+                .unwrap_or_else(|| PathBuf::from("dependency_graph.dot"));
+            
             if *advanced {
-                let advanced_deps = create_advanced_dependency_graph(&analysis, pb.clone())?;
-                advanced_analysis::export_advanced_dependency_graph(&advanced_deps, output)
+                let advanced_deps = create_advanced_dependency_graph(&analysis, pb.clone(), cli.depth, lookup)?;
+                advanced_analysis::export_advanced_dependency_graph(&advanced_deps, &analysis.packages, &output, &dot_options)
                     .with_context(|| "Failed to generate advanced dependency graph")?;
                 println!("Advanced dependency graph saved to: {:?}", output);
+                for cycle in &advanced_deps.cycles {
+                    println!("Circular dependency: {} -> {}", cycle.join(" -> "), cycle[0]);
+                }
             } else {
-                if let Err(e) = utils::generate_dependency_graph(file, output) {
+                if let Err(e) = utils::generate_dependency_graph(&analysis.packages, &output, &dot_options, lookup) {
                     warn!("Failed to generate full dependency graph: {}", e);
                     println!("Note: Generated a basic dependency graph without all relationships. For complete dependency analysis, please run in an environment with conda installed.");
                 } else {
@@ -147,71 +296,329 @@ fn main() -> Result<()> {
             
             pb.finish_with_message("Graph generation complete!");
         }
-        Some(Commands::Recommend { file, check_outdated }) => {
+        Some(Commands::Recommend { file, check_outdated, only, format, output }) => {
             info!("Generating recommendations for: {:?}", file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = utils::analyze_environment(file, *check_outdated, true)
-                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let mut analysis = ctx.analyze(file, *check_outdated, true, false)?;
+            if !only.is_empty() {
+                analysis.recommendations.retain(|rec| only.contains(&rec.category));
+            }
             
             pb.finish_and_clear();
             
-            if analysis.recommendations.is_empty() {
-                println!("No recommendations available for this environment.");
-            } else {
-                println!("Recommendations for environment: {:?}", file);
-                for (i, rec) in analysis.recommendations.iter().enumerate() {
-                    println!("{}. {}", i + 1, rec);
-                }
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "recommendations", format.extension())?;
+            let mut content = exporters::format_recommendations(&analysis.recommendations, format)?;
+            if matches!(format, ExportFormat::Text) && !analysis.recommendations.is_empty() {
+                content.insert_str(0, &format!("Recommendations for environment: {:?}\n", file));
             }
+            exporters::write_output(&content, output_path.as_ref())?;
         }
         Some(Commands::Interactive { file, check_outdated, advanced_graph }) => {
             info!("Starting interactive analysis for: {:?}", file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = if *check_outdated {
-                utils::analyze_environment_parallel(file, *check_outdated, true)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", file))?
-            } else {
-                utils::analyze_environment(file, *check_outdated, true)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", file))?
-            };
+            let mut analysis = ctx.analyze(file, *check_outdated, true, *check_outdated)?;
             
             pb.set_position(60);
             pb.set_message("Processing dependencies...");
             
             let advanced_deps = if *advanced_graph {
-                Some(create_advanced_dependency_graph(&analysis, pb.clone())?)
+                Some(create_advanced_dependency_graph(&analysis, pb.clone(), cli.depth, lookup)?)
             } else {
                 None
             };
+            if let Some(graph) = &advanced_deps {
+                analysis.recommendations.extend(advanced_analysis::cycle_recommendations(graph));
+            }
+            
+            pb.set_position(80);
+            pb.set_message("Checking vulnerabilities...");
+            let vulnerabilities = ctx.scan_vulnerabilities(file, &analysis.packages)?;
             
             pb.finish_and_clear();
             
             info!("Starting interactive UI");
-            let mut ui = interactive::InteractiveUI::new(analysis, advanced_deps)?;
+            let mut ui = interactive::InteractiveUI::new(analysis, advanced_deps, vulnerabilities, utils::should_use_color(cli.no_color))?;
             ui.run()?;
         }
-        Some(Commands::Vulnerabilities { file }) => {
+        Some(Commands::Vulnerabilities { file, format, output, fail_on, ignore_vuln }) => {
             info!("Checking for vulnerabilities in: {:?}", file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = utils::analyze_environment(file, true, false)
-                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let analysis = ctx.analyze(file, true, false, false)?;
             
             pb.set_position(50);
             pb.set_message("Checking vulnerabilities...");
             
-            let vulnerabilities = advanced_analysis::find_vulnerabilities(&analysis.packages);
+            let mut ignore = ctx.vulnerability_ignore_list(file)?;
+            for entry in ignore_vuln {
+                ignore.add(entry);
+            }
+            let (vulnerabilities, suppressed) =
+                advanced_analysis::find_vulnerabilities_with_ignore(&analysis.packages, &ignore, lookup);
+            
+            let report = VulnerabilityReport {
+                environment: analysis.name.clone(),
+                packages_scanned: analysis.packages.len(),
+                vulnerabilities,
+                suppressed,
+            };
+            
+            pb.finish_and_clear();
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "vulnerabilities", format.extension())?;
+            let content = exporters::format_vulnerabilities(&report, format)?;
+            exporters::write_output(&content, output_path.as_ref())?;
+            
+            if let Some(threshold) = fail_on {
+                let failing = report.vulnerabilities.iter()
+                    .filter(|v| v.is_at_least(*threshold))
+                    .count();
+                if failing > 0 {
+                    eprintln!("{} vulnerabilities at or above {} severity found", failing, threshold);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Audit { file, format, output, fail_on }) => {
+            info!("Auditing environment file: {:?}", file);
+            pb.set_message("Analyzing environment...");
+            
+            let analysis = ctx.analyze(file, true, false, true)?;
+            
+            pb.set_position(50);
+            pb.set_message("Checking conflicts and vulnerabilities...");
+            
+            let ignore = ctx.vulnerability_ignore_list(file)?;
+            let report = audit::build_audit_report(&analysis, &ignore, lookup);
+            
+            pb.finish_and_clear();
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "audit", format.extension())?;
+            let content = audit::format_audit_report(&report, format)?;
+            exporters::write_output(&content, output_path.as_ref())
+                .with_context(|| "Failed to write audit report")?;
+            
+            if let Some(threshold) = fail_on {
+                if report.grade >= *threshold {
+                    eprintln!("Audit grade {} is {} or worse", report.grade, threshold);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Licenses { file, format, output }) => {
+            info!("Collecting licenses for: {:?}", file);
+            pb.set_message("Analyzing environment...");
+            
+            let mut analysis = ctx.analyze(file, false, false, false)?;
+            
+            pb.set_position(50);
+            pb.set_message("Looking up licenses...");
+            
+            performance::fetch_licenses(&mut analysis.packages, lookup);
+            let report = licenses::build_license_report(analysis.name.clone(), &analysis.packages);
+            
+            pb.finish_and_clear();
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "licenses", format.extension())?;
+            let content = licenses::format_license_report(&report, format)?;
+            exporters::write_output(&content, output_path.as_ref())
+                .with_context(|| "Failed to write license report")?;
+        }
+        Some(Commands::Stats { file, check_outdated }) => {
+            info!("Summarizing environment file: {:?}", file);
+            pb.set_message("Analyzing environment...");
+            
+            let env = parsers::parse_environment_with_overlays(file, &cli.overlay, ctx.options.document, lookup)
+                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let analysis = ctx.analyze_parsed(&env, *check_outdated, true, *check_outdated)
+                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            
+            pb.finish_and_clear();
+            
+            // Colors only when printing to a terminal, so piped output stays plain
+            let color = cli.output.is_none() && utils::should_use_color(cli.no_color);
+            // `nodefaults` only takes the defaults channels away
+            let channels = env.channels.iter().filter(|c| Channel::parse(c) != Channel::NoDefaults).count();
+            let content = exporters::format_stats(&analysis, channels, color);
+            exporters::write_output(&content, cli.output.as_ref())
+                .with_context(|| "Failed to write stats")?;
+        }
+        Some(Commands::Why { package, file }) => {
+            info!("Explaining why {} is in: {:?}", package, file);
+            pb.set_message("Analyzing environment...");
+            
+            let analysis = ctx.analyze(file, false, false, false)?;
+            
+            pb.set_position(50);
+            pb.set_message("Resolving dependencies...");
+            
+            let graph = create_advanced_dependency_graph(&analysis, pb.clone(), cli.depth, lookup)?;
+            let chains = advanced_analysis::dependency_chains(&graph, package)
+                .ok_or_else(|| anyhow::anyhow!("{} is not in the environment {:?}", package, file))?;
+            
+            pb.finish_and_clear();
+            
+            if chains.is_empty() {
+                println!("{} is a direct dependency: no other package in the environment requires it", package);
+            } else {
+                println!("{} is required through {} dependency chains:", package, chains.len());
+                for chain in &chains {
+                    println!("  {}", chain.join(" -> "));
+                }
+                if chains.len() == advanced_analysis::MAX_DEPENDENCY_CHAINS {
+                    println!("  (only the first {} chains are shown)", advanced_analysis::MAX_DEPENDENCY_CHAINS);
+                }
+            }
+        }
+        Some(Commands::Minimize { file, output }) => {
+            info!("Minimizing environment file: {:?}", file);
+            pb.set_message("Resolving dependencies...");
+            
+            let env = parsers::parse_environment_with_overlays(file, &cli.overlay, ctx.options.document, lookup)
+                .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+            let (minimal, dropped) = conda_env_inspect::analysis::minimize_environment(&env, lookup);
+            
+            pb.finish_and_clear();
+            
+            let content = serde_yaml::to_string(&minimal)
+                .with_context(|| "Failed to serialize minimal environment to YAML")?;
+            // println! adds the final newline on stdout
+            let content = if output.is_some() { content.as_str() } else { content.trim_end() };
+            exporters::write_output(content, output.as_ref())
+                .with_context(|| "Failed to write minimal environment")?;
+            
+            // Keep stdout a valid environment file when it is the destination
+            let summary = if dropped.is_empty() {
+                "No packages dropped: every package is needed at the top level".to_string()
+            } else {
+                format!("Dropped {} packages installed as dependencies of others: {}", dropped.len(), dropped.join(", "))
+            };
+            if output.is_some() {
+                println!("{}", summary);
+            } else {
+                eprintln!("{}", summary);
+            }
+        }
+        Some(Commands::Validate { file, format, output }) => {
+            info!("Validating environment file: {:?}", file);
+            
+            let report = validate::validate_file(file, ctx.options.document, lookup)?;
+            
+            pb.finish_and_clear();
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "validation", format.extension())?;
+            let content = validate::format_validation_report(&report, format)?;
+            exporters::write_output(&content, output_path.as_ref())
+                .with_context(|| "Failed to write validation report")?;
+            
+            if !report.is_valid() {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Sizes { file, top }) => {
+            info!("Listing the largest packages in: {:?}", file);
+            pb.set_message("Analyzing environment...");
+            
+            let analysis = ctx.analyze(file, false, false, false)?;
+            
+            pb.set_position(50);
+            pb.set_message("Resolving dependencies...");
+            
+            let graph = create_advanced_dependency_graph(&analysis, pb.clone(), cli.depth, lookup)?;
             
             pb.finish_and_clear();
             
-            if vulnerabilities.is_empty() {
-                println!("No known vulnerabilities found in the environment.");
+            let mut sized: Vec<(&str, Option<&str>, u64)> = analysis.packages.iter()
+                .filter_map(|p| p.size.map(|size| (p.name.as_str(), p.version.as_deref(), size)))
+                .collect();
+            sized.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+            let total: u64 = sized.iter().map(|&(_, _, size)| size).sum();
+            // A package listed in the environment file is direct, whatever else depends on it
+            let declared: HashSet<&str> = analysis.packages.iter().map(|p| p.name.as_str()).collect();
+            
+            if total == 0 {
+                println!("No package sizes could be determined for {:?} (try --estimate-sizes)", file);
             } else {
-                println!("Found {} potential security vulnerabilities:", vulnerabilities.len());
-                for (i, (pkg, ver, desc)) in vulnerabilities.iter().enumerate() {
-                    println!("{}. {} {} - {}", i + 1, pkg, ver, desc);
+                println!("Largest packages in {:?} (total {} across {} packages):",
+                    file, utils::format_size(total), sized.len());
+                println!("{:>4}  {:>10}  {:>6}  {:>10}  Package", "#", "Size", "Share", "Cumulative");
+                let mut cumulative = 0;
+                for (i, &(name, version, size)) in sized.iter().take(*top).enumerate() {
+                    cumulative += size;
+                    let kind = if declared.contains(name) { "direct" } else { "transitive" };
+                    let kind = match graph.direct_dependents(name) {
+                        Some(dependents) if !dependents.is_empty() => {
+                            format!("{}, required by {}", kind, dependents.join(", "))
+                        }
+                        _ => kind.to_string(),
+                    };
+                    println!(
+                        "{:>4}  {:>10}  {:>5.1}%  {:>9.1}%  {} {} ({})",
+                        i + 1,
+                        utils::format_size(size),
+                        size as f64 * 100.0 / total as f64,
+                        cumulative as f64 * 100.0 / total as f64,
+                        name,
+                        version.unwrap_or("unknown"),
+                        kind,
+                    );
+                }
+            }
+            
+            let unknown = analysis.packages.len() - sized.len();
+            if unknown > 0 {
+                println!("{} packages with an unknown size are not included", unknown);
+            }
+        }
+        Some(Commands::Matrix { files, format, output }) => {
+            let files = matrix::expand_environment_paths(files)?;
+            info!("Building environment matrix for {} files", files.len());
+            pb.set_message("Parsing environments...");
+            
+            let env_matrix = matrix::build_matrix_from_files(&files, ctx.options.document, lookup)?;
+            
+            pb.finish_and_clear();
+            
+            let format = convert_format(*format);
+            let output_path = utils::resolve_artifact_path(
+                output.as_ref(), cli.output_dir.as_ref(), "matrix", format.extension())?;
+            let content = matrix::format_matrix(&env_matrix, format)?;
+            exporters::write_output(&content, output_path.as_ref())
+                .with_context(|| "Failed to write environment matrix")?;
+        }
+        Some(Commands::Cache { action }) => {
+            pb.finish_and_clear();
+            let cache = performance::CacheManager::new()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine the cache directory"))?;
+            
+            match action {
+                CacheAction::Info => {
+                    let info = cache.info()?;
+                    println!("Cache location: {}", info.location.display());
+                    if info.files.is_empty() {
+                        println!("The cache is empty");
+                    }
+                    for file in &info.files {
+                        let entries = file.entries.map_or_else(String::new, |count| format!(", {} entries", count));
+                        println!("  {} ({}{})", file.name, utils::format_size(file.size), entries);
+                    }
+                    println!("Total size: {}", utils::format_size(info.total_size));
+                }
+                CacheAction::Clear => {
+                    let freed = cache.clear()?;
+                    println!("Cleared {} ({} freed)", cache.dir().display(), utils::format_size(freed));
                 }
             }
         }
@@ -220,23 +627,23 @@ fn main() -> Result<()> {
             info!("Using default behavior for file: {:?}", cli.file);
             pb.set_message("Analyzing environment...");
             
-            let analysis = if cli.check_outdated {
+            if cli.check_outdated {
                 pb.set_message("Enriching package information...");
-                utils::analyze_environment_parallel(&cli.file, cli.check_outdated, cli.flag_pinned)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", cli.file))?
-            } else {
-                utils::analyze_environment(&cli.file, cli.check_outdated, cli.flag_pinned)
-                    .with_context(|| format!("Failed to analyze environment file: {:?}", cli.file))?
-            };
+            }
+            let analysis = ctx.analyze(&cli.file, cli.check_outdated, cli.flag_pinned, cli.check_outdated)?;
             
             pb.set_position(50);
             
+            let artifact_stem = utils::artifact_stem(&analysis, &cli.file);
+            
             // Generate dependency graph if requested
             if cli.generate_graph {
                 pb.set_message("Generating dependency graph...");
-                if let Some(graph_path) = &cli.graph_output {
+                let graph_path = utils::resolve_artifact_path(
+                    cli.graph_output.as_ref(), cli.output_dir.as_ref(), &artifact_stem, "dot")?;
+                if let Some(graph_path) = &graph_path {
                     info!("Generating dependency graph: {:?}", graph_path);
-                    if let Err(e) = utils::generate_dependency_graph(&cli.file, graph_path) {
+                    if let Err(e) = utils::generate_dependency_graph(&analysis.packages, graph_path, &dot_options, lookup) {
                         warn!("Failed to generate full dependency graph: {}", e);
                         println!("Note: Generated a basic dependency graph without all relationships. For complete dependency analysis, please run in an environment with conda installed.");
                     } else {
@@ -244,7 +651,7 @@ fn main() -> Result<()> {
                     }
                 } else {
                     warn!("No output path specified for dependency graph");
-                    return Err(anyhow::anyhow!("No output path specified for dependency graph"));
+                    return Err(anyhow::anyhow!("No output path specified for dependency graph (use --graph-output or --output-dir)"));
                 }
             }
             
@@ -252,49 +659,391 @@ fn main() -> Result<()> {
             pb.set_message("Exporting results...");
             
             info!("Exporting analysis results");
-            exporters::export_analysis(&analysis, convert_format(cli.format), cli.output.as_ref())
+            let format = convert_format(cli.format);
+            let output_path = utils::resolve_artifact_path(
+                cli.output.as_ref(), cli.output_dir.as_ref(), &artifact_stem, format.extension())?;
+            exporters::export_analysis(&analysis, format, output_path.as_ref(), lookup)
                 .with_context(|| "Failed to export analysis")?;
             
             pb.finish_with_message("Analysis complete!");
         }
     }
 
-    info!("Completed successfully in {:.2?}", start_time.elapsed());
+    if cli.strict && ctx.unchecked.get() > 0 {
+        eprintln!("{} packages could not be checked for updates (--strict)", ctx.unchecked.get());
+        std::process::exit(1);
+    }
+
     Ok(())
 }
 
-/// Check if conda is available in the system and log warning if not
-fn check_conda_availability() {
-    match Command::new("conda").arg("--version").output() {
-        Ok(output) => {
-            if output.status.success() {
-                let version = String::from_utf8_lossy(&output.stdout);
-                info!("Found conda: {}", version.trim());
-            } else {
-                warn!("Conda is installed but returned an error: {}", 
-                      String::from_utf8_lossy(&output.stderr));
+/// Options shared by every command that analyzes an environment file
+struct AnalysisContext<'a> {
+    /// Overlay files merged on top of the base environment file
+    overlays: &'a [PathBuf],
+    /// Target platforms from `--platforms`, overriding those declared in the file
+    platforms: &'a [String],
+    /// Platform the environment is analyzed for from `--platform`, instead of this machine's
+    platform: Option<&'a str>,
+    /// Whether to drop the install prefix from the analysis (`--strip-prefix`)
+    strip_prefix: bool,
+    /// Whether to add the size of transitive dependencies (`--include-transitive`)
+    include_transitive: bool,
+    /// Whether to add the compressed download size of the packages (`--download-size`)
+    download_size: bool,
+    /// Whether to check which packages are published on conda and PyPI (`--check-availability`)
+    check_availability: bool,
+    /// Package ordering from `--sort-by`, and whether it is descending
+    sort: Option<(SortKey, bool)>,
+    /// Progress bar ticked per package during parallel enrichment
+    progress: ProgressBar,
+    /// Number of packages whose outdated check failed, across every analysis (for `--strict`)
+    unchecked: Cell<usize>,
+    /// Accepted vulnerabilities from the config file, added to each environment's ignore file
+    ignored_vulnerabilities: &'a [String],
+    /// Analysis options from the command line (`--from-history`, `--estimate-sizes`,
+    /// `--include`/`--exclude`, `--select`, `--document`); each analysis sets its own outdated and pinned checks
+    options: utils::AnalysisOptions,
+}
+
+impl<'a> AnalysisContext<'a> {
+    /// Build the context from the command line, validating `--select` up front
+    fn from_cli(cli: &'a Cli, progress: &ProgressBar) -> Result<Self> {
+        let selector = cli.select.as_deref()
+            .map(Selector::parse)
+            .transpose()?;
+        
+        Ok(Self {
+            overlays: &cli.overlay,
+            platforms: &cli.platforms,
+            platform: cli.platform.as_deref(),
+            strip_prefix: cli.strip_prefix,
+            include_transitive: cli.include_transitive,
+            download_size: cli.download_size,
+            check_availability: cli.check_availability,
+            sort: cli.sort_by.map(|key| (key, cli.descending)),
+            progress: progress.clone(),
+            unchecked: Cell::new(0),
+            ignored_vulnerabilities: &cli.ignored_vulnerabilities,
+            options: utils::AnalysisOptions {
+                from_history: cli.from_history,
+                lookup_sizes: needs_package_sizes(cli, selector.as_ref()),
+                estimate_sizes: cli.estimate_sizes,
+                filters: utils::PackageFilters::new(&cli.include, &cli.exclude)?,
+                select: selector,
+                document: cli.document.map(|n| n as usize),
+                lookup: conda_api::LookupOptions {
+                    offline: cli.offline,
+                    network: conda_api::NetworkConfig {
+                        timeout_secs: cli.timeout,
+                        retries: cli.retries,
+                    },
+                    command_timeout_secs: cli.conda_timeout,
+                    backend: cli.backend,
+                    cache: if cli.no_cache {
+                        CachePolicy::DISABLED
+                    } else if cli.refresh {
+                        CachePolicy::REFRESH
+                    } else {
+                        CachePolicy::DEFAULT
+                    },
+                    safety_db_ttl: Duration::from_secs(cli.db_ttl * 60 * 60),
+                    refresh_safety_db: cli.refresh_db,
+                    local_vulnerabilities: advanced_analysis::load_user_vulnerability_db(cli.vuln_db.as_deref())?,
+                },
+                ..Default::default()
+            },
+        })
+    }
+    
+    /// Analyze an environment file, merging any overlays on top of it first
+    fn analyze(
+        &self,
+        file: &Path,
+        check_outdated: bool,
+        flag_pinned: bool,
+        parallel: bool,
+    ) -> Result<EnvironmentAnalysis> {
+        let env = parsers::parse_environment_with_overlays(file, self.overlays, self.options.document, &self.options.lookup)
+            .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+        
+        let mut analysis = self.analyze_parsed(&env, check_outdated, flag_pinned, parallel)
+            .with_context(|| format!("Failed to analyze environment file: {:?}", file))?;
+        if let Some(metadata) = &mut analysis.metadata {
+            metadata.input = Some(file.display().to_string());
+        }
+        
+        // Installed environments always have a prefix; only a file that hard-codes one is a problem
+        if let Some(mut recommendation) = conda_env_inspect::analysis::hardcoded_prefix_recommendation(&env) {
+            warn!("{:?} hard-codes an absolute `prefix:`, so it won't install portably on other machines", file);
+            if self.strip_prefix {
+                recommendation.value = "(omitted)".to_string();
             }
-        },
-        Err(_) => {
-            warn!("Conda is not available in the system PATH. Some features will use fallback mechanisms.");
-            warn!("For complete functionality, please install conda and ensure it's in your PATH.");
+            analysis.recommendations.push(recommendation);
+        }
+        
+        Ok(analysis)
+    }
+    
+    /// The vulnerabilities accepted for an environment file: its `.conda-inspect-ignore`
+    /// entries plus the config file's `ignore_vulns`
+    fn vulnerability_ignore_list(&self, env_file: &Path) -> Result<advanced_analysis::VulnerabilityIgnoreList> {
+        let mut ignore = load_vulnerability_ignore_file(env_file)?;
+        for entry in self.ignored_vulnerabilities {
+            ignore.add(entry);
+        }
+        Ok(ignore)
+    }
+    
+    /// Scan packages for vulnerabilities, leaving out the ones accepted for the environment file
+    fn scan_vulnerabilities(&self, env_file: &Path, packages: &[conda_env_inspect::models::Package]) -> Result<Vec<conda_env_inspect::models::Vulnerability>> {
+        let ignore = self.vulnerability_ignore_list(env_file)?;
+        Ok(advanced_analysis::find_vulnerabilities_with_ignore(packages, &ignore, &self.options.lookup).0)
+    }
+    
+    /// Analyze the packages installed in a named environment (overlays do not apply)
+    fn analyze_installed(&self, env_name: &str, check_outdated: bool, flag_pinned: bool) -> Result<EnvironmentAnalysis> {
+        let env = conda_api::read_installed_environment(env_name, &self.options.lookup)?;
+        
+        let mut analysis = self.analyze_parsed(&env, check_outdated, flag_pinned, true)
+            .with_context(|| format!("Failed to analyze environment: {}", env_name))?;
+        if let Some(metadata) = &mut analysis.metadata {
+            metadata.input = Some(env_name.to_string());
+        }
+        Ok(analysis)
+    }
+    
+    /// Analyze a parsed environment and apply the prefix, transitive size, platform and sort options
+    fn analyze_parsed(
+        &self,
+        env: &CondaEnvironment,
+        check_outdated: bool,
+        flag_pinned: bool,
+        parallel: bool,
+    ) -> Result<EnvironmentAnalysis> {
+        let options = utils::AnalysisOptions {
+            check_outdated,
+            flag_pinned,
+            ..self.options.clone()
+        };
+        let mut analysis = if parallel {
+            utils::analyze_parsed_environment_parallel_with_progress(env, &options, Some(&self.progress))
+        } else {
+            utils::analyze_parsed_environment(env, &options)
+        }?;
+        
+        if self.strip_prefix {
+            analysis.prefix = None;
+        }
+        
+        // Sizes and availability are looked up for the analyzing platform, so they won't
+        // match a lock file made for another one
+        let analyzing_platform = self.platform.or(platforms::current_platform());
+        match (&analysis.platform, analyzing_platform) {
+            (Some(locked), Some(analyzing)) if locked != analyzing => warn!(
+                "This lock file targets {} but is analyzed for {}; package sizes and availability may not match (use --platform {} to analyze it for its own platform)",
+                locked, analyzing, locked
+            ),
+            (None, _) => analysis.platform = self.platform.map(str::to_string),
+            _ => {},
+        }
+        
+        if !analysis.check_failures.is_empty() {
+            warn!("{} packages could not be checked for updates", analysis.check_failures.len());
+            self.unchecked.set(self.unchecked.get() + analysis.check_failures.len());
+        }
+        
+        if self.include_transitive {
+            utils::add_transitive_size(&mut analysis, &self.options.lookup);
+        }
+        
+        if self.download_size {
+            utils::add_download_size(&mut analysis, &self.options.lookup);
+        }
+        
+        if self.check_availability {
+            analysis.recommendations.extend(conda_env_inspect::analysis::availability_recommendations(&analysis.packages, &self.options.lookup));
+        }
+        
+        // Flag packages that won't install on every target platform
+        let platforms = if self.platforms.is_empty() {
+            platforms::declared_platforms(env)
+        } else {
+            self.platforms.to_vec()
+        };
+        analysis.recommendations.extend(platforms::platform_recommendations(&analysis.packages, &platforms));
+        analysis.recommendations.extend(conda_env_inspect::analysis::channel_mixing_recommendation(env));
+        
+        if let Some((key, descending)) = self.sort {
+            analysis.sort_packages(key, descending);
+        }
+        
+        Ok(analysis)
+    }
+}
+
+/// Whether the command's output shows package sizes or anything derived from them, so the
+/// analysis has to look them up
+fn needs_package_sizes(cli: &Cli, selector: Option<&Selector>) -> bool {
+    let shows_sizes = match &cli.command {
+        Some(Commands::Analyze { .. } | Commands::Export { .. } | Commands::Inspect { .. }
+            | Commands::Recommend { .. } | Commands::Interactive { .. } | Commands::Stats { .. }
+            | Commands::Sizes { .. }) => true,
+        None => !cli.check,
+        _ => false,
+    };
+    shows_sizes
+        || cli.include_transitive
+        || cli.sort_by == Some(SortKey::Size)
+        || selector.is_some_and(Selector::uses_size)
+}
+
+/// Run the `--check` gate: analyze the file for outdated packages, conda/pip version
+/// conflicts and vulnerabilities, and print a summary. Returns whether the check passed
+fn run_check(ctx: &AnalysisContext, file: &Path) -> Result<bool> {
+    let analysis = ctx.analyze(file, true, true, true)?;
+    
+    let outdated: Vec<String> = analysis.packages.iter()
+        .filter(|p| p.is_outdated)
+        .map(|p| format!(
+            "{} {} -> {}",
+            p.name,
+            p.version.as_deref().unwrap_or("unknown"),
+            p.latest_version.as_deref().unwrap_or("unknown")
+        ))
+        .collect();
+    let dependencies = conda_env_inspect::analysis::get_real_package_dependencies(&analysis.packages, &ctx.options.lookup);
+    let mut conflicts: Vec<String> = advanced_analysis::detect_conflicts(&dependencies)
+        .into_iter()
+        .map(|(_, _, description)| description)
+        .collect();
+    conflicts.extend(analysis.packages.iter()
+        .filter(|p| p.has_conflicting_pip_version())
+        .map(|p| format!(
+            "{} (conda {}, pip {})",
+            p.name,
+            p.version.as_deref().unwrap_or("unknown"),
+            p.pip_version.as_deref().unwrap_or("unknown")
+        )));
+    
+    let vulnerabilities = ctx.scan_vulnerabilities(file, &analysis.packages)?;
+    let vulnerable: Vec<String> = vulnerabilities.iter()
+        .map(|v| match &v.id {
+            Some(id) => format!("{} {} ({})", v.package, v.version, id),
+            None => format!("{} {}", v.package, v.version),
+        })
+        .collect();
+    
+    let note = analysis.check_failure_note();
+    if outdated.is_empty() && conflicts.is_empty() && vulnerable.is_empty() {
+        println!("{}: OK ({} packages checked)", file.display(), analysis.packages.len());
+        if let Some(note) = note {
+            println!("  {}", note);
+        }
+        return Ok(true);
+    }
+    
+    println!("{}: FAILED", file.display());
+    for (label, findings) in [("outdated", &outdated), ("conflicts", &conflicts), ("vulnerabilities", &vulnerable)] {
+        if !findings.is_empty() {
+            println!("  {} {}: {}", findings.len(), label, findings.join(", "));
+        }
+    }
+    if let Some(note) = note {
+        println!("  {}", note);
+    }
+    Ok(false)
+}
+
+/// Write the report bundle of one analysis into `dir` (created if missing): the analysis as
+/// JSON, Markdown and HTML, the dependency graph (the advanced one when it was built) and the
+/// vulnerability scan without the findings on `ignore`. Returns the paths written; a graph
+/// that can't be built is skipped
+fn write_report_bundle(
+    dir: &Path,
+    ignore: &advanced_analysis::VulnerabilityIgnoreList,
+    analysis: &EnvironmentAnalysis,
+    graph: Option<&advanced_analysis::AdvancedDependencyGraph>,
+    dot_options: &DotOptions,
+    lookup: &conda_api::LookupOptions,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create report directory: {:?}", dir))?;
+    let mut written = Vec::new();
+    
+    // The bundle carries its own vulnerability scan, so the reports record that it was run
+    let mut analysis = analysis.clone();
+    if let Some(metadata) = &mut analysis.metadata {
+        metadata.vulnerability_check = true;
+    }
+    
+    for format in [ExportFormat::Json, ExportFormat::Markdown, ExportFormat::Html] {
+        let path = dir.join(format!("analysis.{}", format.extension()));
+        let content = exporters::format_analysis_with_graph(&analysis, graph, format, lookup)?;
+        exporters::write_output(&content, Some(&path))
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        written.push(path);
+    }
+    
+    let graph_path = dir.join("graph.dot");
+    let graph_result = match graph {
+        Some(graph) => advanced_analysis::export_advanced_dependency_graph(graph, &analysis.packages, &graph_path, dot_options),
+        None => utils::generate_dependency_graph(&analysis.packages, &graph_path, dot_options, lookup),
+    };
+    match graph_result {
+        Ok(()) => written.push(graph_path),
+        Err(e) => warn!("Skipping graph.dot: {}", e),
+    }
+    
+    let (vulnerabilities, suppressed) = advanced_analysis::find_vulnerabilities_with_ignore(&analysis.packages, ignore, lookup);
+    let report = VulnerabilityReport {
+        environment: analysis.name.clone(),
+        packages_scanned: analysis.packages.len(),
+        vulnerabilities,
+        suppressed,
+    };
+    let path = dir.join("vulnerabilities.json");
+    exporters::write_output(&exporters::format_vulnerabilities(&report, ExportFormat::Json)?, Some(&path))
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    written.push(path);
+    
+    Ok(written)
+}
+
+/// Load the `.conda-inspect-ignore` file next to the environment file, or else the one in
+/// the current directory. Returns an empty list when there is neither
+fn load_vulnerability_ignore_file(env_file: &Path) -> Result<advanced_analysis::VulnerabilityIgnoreList> {
+    let env_dir = env_file.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    
+    for dir in [env_dir, Path::new(".")] {
+        let path = dir.join(advanced_analysis::VULNERABILITY_IGNORE_FILE);
+        if path.is_file() {
+            info!("Using vulnerability ignore file: {:?}", path);
+            return advanced_analysis::VulnerabilityIgnoreList::load(&path);
         }
     }
+    
+    Ok(advanced_analysis::VulnerabilityIgnoreList::default())
 }
 
-/// Create advanced dependency graph with progress bar
+/// Create advanced dependency graph with progress bar, expanding transitive
+/// dependencies at most `max_depth` levels deep (`--depth`)
 fn create_advanced_dependency_graph(
     analysis: &conda_env_inspect::models::EnvironmentAnalysis,
     pb: ProgressBar,
+    max_depth: Option<usize>,
+    lookup: &conda_api::LookupOptions,
 ) -> Result<conda_env_inspect::advanced_analysis::AdvancedDependencyGraph> {
     // First get the dependency map
-    let deps = conda_env_inspect::analysis::get_real_package_dependencies(&analysis.packages);
+    let deps = conda_env_inspect::analysis::get_real_package_dependencies(&analysis.packages, lookup);
     
     pb.set_position(70);
     pb.set_message("Creating advanced dependency graph...");
     
     // Create the advanced graph
-    let graph = conda_env_inspect::advanced_analysis::create_advanced_dependency_graph(&analysis.packages, &deps);
+    let graph = conda_env_inspect::advanced_analysis::create_advanced_dependency_graph(&analysis.packages, &deps, max_depth);
     
     pb.set_position(80);
     
@@ -306,9 +1055,12 @@ fn convert_format(format: conda_env_inspect::cli::OutputFormat) -> ExportFormat
     match format {
         conda_env_inspect::cli::OutputFormat::Text => ExportFormat::Text,
         conda_env_inspect::cli::OutputFormat::Json => ExportFormat::Json,
+        conda_env_inspect::cli::OutputFormat::Yaml => ExportFormat::Yaml,
+        conda_env_inspect::cli::OutputFormat::Toml => ExportFormat::Toml,
         conda_env_inspect::cli::OutputFormat::Markdown => ExportFormat::Markdown,
+        conda_env_inspect::cli::OutputFormat::Html => ExportFormat::Html,
         conda_env_inspect::cli::OutputFormat::Csv => ExportFormat::Csv,
-        // For formats not directly supported, fall back to text
-        _ => ExportFormat::Text,
+        conda_env_inspect::cli::OutputFormat::Prometheus => ExportFormat::Prometheus,
+        conda_env_inspect::cli::OutputFormat::CycloneDx => ExportFormat::CycloneDx,
     }
 }
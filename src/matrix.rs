@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use log::{debug, info};
+use prettytable::{Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::conda_api::LookupOptions;
+use crate::exporters::ExportFormat;
+use crate::models::Package;
+use crate::parsers;
+
+/// Package × environment version matrix across several environment files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentMatrix {
+    /// Environment labels, in column order
+    pub environments: Vec<String>,
+    /// One row per package, sorted by package name
+    pub rows: Vec<MatrixRow>,
+}
+
+/// Versions of a single package across all environments of a matrix
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixRow {
+    /// Name of the package
+    pub package: String,
+    /// Declared version per environment: `None` when absent, `"*"` when present but unversioned
+    pub versions: Vec<Option<String>>,
+    /// Whether the environments declare different versions of this package
+    pub drift: bool,
+}
+
+impl EnvironmentMatrix {
+    /// Rows where the environments disagree on the version
+    pub fn drifted(&self) -> impl Iterator<Item = &MatrixRow> {
+        self.rows.iter().filter(|row| row.drift)
+    }
+}
+
+/// Expands the given paths, treating any path containing glob characters as a pattern
+pub fn expand_environment_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+
+    for path in paths {
+        let pattern = path.to_string_lossy();
+        if pattern.contains(['*', '?', '[']) {
+            let matches = glob::glob(&pattern)
+                .with_context(|| format!("Invalid glob pattern: {}", pattern))?
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                return Err(anyhow::anyhow!("No environment files match: {}", pattern));
+            }
+            expanded.extend(matches);
+        } else {
+            expanded.push(path.clone());
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Parses each environment file and builds the version matrix. `document` selects the YAML
+/// document of multi-document files, as in [`parsers::parse_environment_file_with_document`]
+pub fn build_matrix_from_files(paths: &[PathBuf], document: Option<usize>, lookup: &LookupOptions) -> Result<EnvironmentMatrix> {
+    let mut environments = Vec::new();
+
+    for path in paths {
+        debug!("Parsing environment for matrix: {:?}", path);
+        let env = parsers::parse_environment_file_with_document(path, document, lookup)
+            .with_context(|| format!("Failed to parse environment file: {:?}", path))?;
+        let label = env.name.clone()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| file_label(path));
+        environments.push((label, parsers::extract_packages(&env)));
+    }
+
+    // Fall back to file paths when environment names are not unique
+    let mut seen = HashSet::new();
+    if !environments.iter().all(|(label, _)| seen.insert(label.clone())) {
+        for ((label, _), path) in environments.iter_mut().zip(paths) {
+            *label = path.display().to_string();
+        }
+    }
+
+    info!("Building environment matrix across {} environments", environments.len());
+    Ok(build_matrix(&environments))
+}
+
+/// Builds the version matrix from labelled package lists
+pub fn build_matrix(environments: &[(String, Vec<Package>)]) -> EnvironmentMatrix {
+    let mut versions: BTreeMap<String, Vec<Option<String>>> = BTreeMap::new();
+
+    for (i, (_, packages)) in environments.iter().enumerate() {
+        for package in packages {
+            let entry = versions
+                .entry(package.name.to_lowercase())
+                .or_insert_with(|| vec![None; environments.len()]);
+            entry[i] = Some(package.version.clone().unwrap_or_else(|| "*".to_string()));
+        }
+    }
+
+    let rows = versions.into_iter()
+        .map(|(package, versions)| {
+            let distinct: BTreeSet<&str> = versions.iter()
+                .flatten()
+                .map(String::as_str)
+                .filter(|v| *v != "*")
+                .collect();
+            MatrixRow {
+                package,
+                drift: distinct.len() > 1,
+                versions,
+            }
+        })
+        .collect();
+
+    EnvironmentMatrix {
+        environments: environments.iter().map(|(label, _)| label.clone()).collect(),
+        rows,
+    }
+}
+
+/// Formats the matrix as text, JSON, YAML, Markdown or CSV
+pub fn format_matrix(matrix: &EnvironmentMatrix, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(matrix)
+            .with_context(|| "Failed to serialize matrix to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(matrix)
+            .with_context(|| "Failed to serialize matrix to YAML"),
+        ExportFormat::Markdown => Ok(format_matrix_markdown(matrix)),
+        ExportFormat::Csv => format_matrix_csv(matrix),
+        _ => Ok(format_matrix_text(matrix)),
+    }
+}
+
+fn format_matrix_text(matrix: &EnvironmentMatrix) -> String {
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+
+    let mut header = vec![Cell::new("Package")];
+    header.extend(matrix.environments.iter().map(|env| Cell::new(env)));
+    table.set_titles(Row::new(header));
+
+    for row in &matrix.rows {
+        let name = if row.drift { format!("{} (!)", row.package) } else { row.package.clone() };
+        let mut cells = vec![Cell::new(&name)];
+        cells.extend(row.versions.iter().map(|v| Cell::new(v.as_deref().unwrap_or(""))));
+        table.add_row(Row::new(cells));
+    }
+
+    let drift_count = matrix.drifted().count();
+    format!(
+        "{}\n{} packages across {} environments, {} with version drift (!)\n",
+        table, matrix.rows.len(), matrix.environments.len(), drift_count
+    )
+}
+
+fn format_matrix_markdown(matrix: &EnvironmentMatrix) -> String {
+    let mut output = String::new();
+
+    output.push_str("# Environment Matrix\n\n");
+    output.push_str(&format!("| Package | {} |\n", matrix.environments.join(" | ")));
+    output.push_str(&format!("|---------|{}\n", "---------|".repeat(matrix.environments.len())));
+
+    for row in &matrix.rows {
+        let cells: Vec<String> = row.versions.iter()
+            .map(|v| match v {
+                Some(v) if row.drift && v != "*" => format!("**{}**", v),
+                Some(v) => v.clone(),
+                None => String::new(),
+            })
+            .collect();
+        let name = if row.drift { format!("⚠️ {}", row.package) } else { row.package.clone() };
+        output.push_str(&format!("| {} | {} |\n", name, cells.join(" | ")));
+    }
+
+    output
+}
+
+fn format_matrix_csv(matrix: &EnvironmentMatrix) -> Result<String> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+
+    let mut header = vec!["Package".to_string()];
+    header.extend(matrix.environments.iter().cloned());
+    header.push("Drift".to_string());
+    wtr.write_record(&header)?;
+
+    for row in &matrix.rows {
+        let mut record = vec![row.package.clone()];
+        record.extend(row.versions.iter().map(|v| v.clone().unwrap_or_default()));
+        record.push(row.drift.to_string());
+        wtr.write_record(&record)?;
+    }
+
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+/// Label for an environment without a name: its file stem
+fn file_label(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
+use crate::version::CondaVersion;
+
 /// Represents a complete Conda environment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CondaEnvironment {
@@ -13,11 +16,42 @@ pub struct CondaEnvironment {
     /// Dependencies (packages) in the environment
     #[serde(default)]
     pub dependencies: Vec<Dependency>,
+    /// Install location recorded by `conda env export` (reveals the source machine's path)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Environment variables set when the environment is activated (`variables:`).
+    /// Numbers and booleans are kept as their string form
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_variables")]
+    pub variables: Option<HashMap<String, String>>,
     /// Additional properties not explicitly modeled
     #[serde(flatten)]
     pub extra: HashMap<String, serde_yaml::Value>,
 }
 
+/// Reads a `variables:` mapping, converting scalar values to strings like conda does
+fn deserialize_variables<'de, D>(deserializer: D) -> Result<Option<HashMap<String, String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let variables: Option<HashMap<String, serde_yaml::Value>> = Option::deserialize(deserializer)?;
+    variables
+        .map(|variables| {
+            variables.into_iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        serde_yaml::Value::String(value) => value,
+                        serde_yaml::Value::Number(value) => value.to_string(),
+                        serde_yaml::Value::Bool(value) => value.to_string(),
+                        serde_yaml::Value::Null => String::new(),
+                        _ => return Err(serde::de::Error::custom(format!("variable `{}` must be a string", name))),
+                    };
+                    Ok((name, value))
+                })
+                .collect()
+        })
+        .transpose()
+}
+
 /// Represents a dependency in a Conda environment.
 /// Can be a simple string like "numpy=1.19.2" or a complex specification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,34 +66,203 @@ pub enum Dependency {
 /// Represents a complex dependency specification with pip packages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComplexDependency {
-    /// Name of the package, like "pip"
+    /// Name of the package, like "pip". Often omitted for pip blocks; any other name
+    /// is treated as a regular conda package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// The pip packages to install
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pip: Option<Vec<String>>,
     /// Additional properties not explicitly modeled
     #[serde(flatten)]
     pub extra: HashMap<String, serde_yaml::Value>,
 }
 
-/// Represents a parsed package with its details
+/// A channel of an environment file or package spec, by the kind of location it names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    /// A channel name, resolved by conda against anaconda.org (e.g. "conda-forge",
+    /// "pkgs/main", "conda-forge/label/dev" or "defaults")
+    Named(String),
+    /// A remote channel given by URL (e.g. "https://conda.anaconda.org/bioconda" or a private server)
+    Url(String),
+    /// `nodefaults`, which removes the defaults channels instead of adding a channel
+    NoDefaults,
+    /// A channel on the local filesystem (a `file://` URL or a path)
+    Local(String),
+}
+
+/// Hosts whose channel URLs are served by anaconda.org, where the first path segment is the
+/// channel (`repo.anaconda.com` serves `pkgs/<channel>`)
+const ANACONDA_HOSTS: &[&str] = &["conda.anaconda.org", "anaconda.org", "repo.anaconda.com"];
+
+impl Channel {
+    /// Classify a channel as written in an environment file
+    pub fn parse(channel: &str) -> Self {
+        let channel = channel.trim().trim_end_matches('/');
+        let lower = channel.to_ascii_lowercase();
+        let is_windows_path = channel.as_bytes().get(1) == Some(&b':')
+            && channel.as_bytes()[0].is_ascii_alphabetic();
+        
+        if lower == "nodefaults" {
+            Channel::NoDefaults
+        } else if lower.starts_with("file://") || channel.starts_with(['/', '.', '~', '\\']) || is_windows_path {
+            Channel::Local(channel.to_string())
+        } else if channel.contains("://") {
+            Channel::Url(channel.to_string())
+        } else {
+            Channel::Named(channel.to_string())
+        }
+    }
+    
+    /// Name of the channel on anaconda.org, for API lookups: named channels (`defaults` and
+    /// `pkgs/<name>` are the defaults' `main` and `<name>`, and labels such as
+    /// `conda-forge/label/dev` belong to their channel) and anaconda.org URLs. `None` for other
+    /// URLs, local channels, `nodefaults`, and the "pip"/"pypi" markers of pip packages
+    pub fn anaconda_name(&self) -> Option<&str> {
+        let path = match self {
+            Channel::Named(name) => name.as_str(),
+            Channel::Url(url) => {
+                let (_, rest) = url.split_once("://")?;
+                let (host, path) = rest.split_once('/')?;
+                let host = host.rsplit('@').next().unwrap_or(host).split(':').next().unwrap_or(host);
+                if !ANACONDA_HOSTS.iter().any(|known| host.eq_ignore_ascii_case(known)) {
+                    return None;
+                }
+                path
+            },
+            Channel::NoDefaults | Channel::Local(_) => return None,
+        };
+        
+        let path = path.strip_prefix("pkgs/").unwrap_or(path);
+        match path.split('/').next().unwrap_or(path) {
+            "" | "pip" | "pypi" | "unknown" => None,
+            "defaults" => Some("main"),
+            name => Some(name),
+        }
+    }
+    
+    /// Whether this is the "pip"/"pypi" marker of a package installed from PyPI
+    pub fn is_pip(&self) -> bool {
+        matches!(self, Channel::Named(name) if name.eq_ignore_ascii_case("pip") || name.eq_ignore_ascii_case("pypi"))
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Named(name) => write!(f, "{}", name),
+            Channel::Url(url) | Channel::Local(url) => write!(f, "{}", url),
+            Channel::NoDefaults => write!(f, "nodefaults"),
+        }
+    }
+}
+
+/// Represents a parsed package with its details. Two packages are equal (and hash alike)
+/// when their name and version match, whatever their other metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Package {
     /// Name of the package
     pub name: String,
-    /// Version of the package
+    /// Exact version of the package; specs that only constrain the version leave it unset
     pub version: Option<String>,
+    /// Declared version constraint when it doesn't name a single version (`>=1.21,<2`, `1.21.*`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<String>,
     /// Build string (if available)
     pub build: Option<String>,
     /// Channel the package comes from
     pub channel: Option<String>,
     /// Size of the package (if available)
     pub size: Option<u64>,
-    /// Whether the package version is pinned
-    pub is_pinned: bool,
+    /// Whether the spec pins an exact version, constrains a range, or accepts any version
+    #[serde(default)]
+    pub pin_status: PinStatus,
     /// Whether the package is outdated
     pub is_outdated: bool,
     /// Latest available version (if known)
     pub latest_version: Option<String>,
+    /// MD5 checksum of the package archive (from explicit lock files)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// License as reported by the package metadata (e.g. "BSD-3-Clause")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Whether the package is declared both as a conda dependency and in the `pip:` section.
+    /// The conda entry is kept and the pip declaration is recorded in `pip_version`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub declared_in_both: bool,
+    /// Version requested by the overridden pip declaration, if it gave one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pip_version: Option<String>,
+    /// Whether `size` is a placeholder estimate rather than real metadata (`--estimate-sizes`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub size_estimated: bool,
+    /// Optional features requested for a pip package (`uvicorn[standard]` has `["standard"]`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<String>,
+}
+
+/// Kind of issue a recommendation addresses, in the order they are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecommendationCategory {
+    /// Known or likely vulnerabilities
+    Security,
+    /// Outdated or deprecated packages
+    Update,
+    /// Ways to make the environment smaller
+    SizeOptimization,
+    /// Anything that makes the environment resolve or install differently elsewhere
+    Reproducibility,
+    /// Packages declared more than once or in both the conda and pip sections
+    Redundancy,
+}
+
+impl RecommendationCategory {
+    /// Heading used when recommendations are grouped by category
+    pub fn title(&self) -> &'static str {
+        match self {
+            RecommendationCategory::Security => "Security",
+            RecommendationCategory::Update => "Updates",
+            RecommendationCategory::SizeOptimization => "Size optimization",
+            RecommendationCategory::Reproducibility => "Reproducibility",
+            RecommendationCategory::Redundancy => "Redundancy",
+        }
+    }
+}
+
+impl fmt::Display for RecommendationCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecommendationCategory::Security => "security",
+            RecommendationCategory::Update => "update",
+            RecommendationCategory::SizeOptimization => "size-optimization",
+            RecommendationCategory::Reproducibility => "reproducibility",
+            RecommendationCategory::Redundancy => "redundancy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How urgently a recommendation should be acted on, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 /// Represents a recommendation for environment optimization
@@ -71,6 +274,26 @@ pub struct Recommendation {
     pub value: String,
     /// Optional detailed explanation
     pub details: Option<String>,
+    /// Kind of issue the recommendation addresses
+    pub category: RecommendationCategory,
+    /// How urgently the recommendation should be acted on
+    pub priority: Priority,
+}
+
+/// Groups recommendations by category, in category order, with the highest priority
+/// first within each group (keeping the original order among equal priorities)
+pub fn group_recommendations(recommendations: &[Recommendation]) -> Vec<(RecommendationCategory, Vec<&Recommendation>)> {
+    let mut sorted: Vec<&Recommendation> = recommendations.iter().collect();
+    sorted.sort_by(|a, b| a.category.cmp(&b.category).then_with(|| b.priority.cmp(&a.priority)));
+    
+    let mut groups: Vec<(RecommendationCategory, Vec<&Recommendation>)> = Vec::new();
+    for recommendation in sorted {
+        match groups.last_mut() {
+            Some((category, group)) if *category == recommendation.category => group.push(recommendation),
+            _ => groups.push((recommendation.category, vec![recommendation])),
+        }
+    }
+    groups
 }
 
 impl fmt::Display for Recommendation {
@@ -79,15 +302,102 @@ impl fmt::Display for Recommendation {
     }
 }
 
+/// Summary of how completely package metadata could be enriched from external sources
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnrichmentReport {
+    /// Number of packages that were enriched
+    pub total_packages: usize,
+    /// Number of packages whose latest version was resolved, keyed by source
+    pub version_sources: BTreeMap<String, usize>,
+    /// Number of packages whose size was resolved, keyed by source
+    pub size_sources: BTreeMap<String, usize>,
+    /// Packages for which no source returned real data (size estimates don't count)
+    pub failed: Vec<String>,
+}
+
+impl fmt::Display for EnrichmentReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format_sources = |sources: &BTreeMap<String, usize>| {
+            if sources.is_empty() {
+                "none".to_string()
+            } else {
+                sources.iter()
+                    .map(|(source, count)| format!("{} {}", count, source))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+        
+        write!(
+            f,
+            "latest versions: {}; sizes: {}; {} of {} packages could not be resolved",
+            format_sources(&self.version_sources),
+            format_sources(&self.size_sources),
+            self.failed.len(),
+            self.total_packages
+        )
+    }
+}
+
+/// Provenance of an exported report: which tool version made it, when, from which input,
+/// and which checks were run, so "nothing found" can be told apart from "not checked"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportMetadata {
+    /// Name of the tool that generated the report
+    pub tool: String,
+    /// Version of the tool
+    pub version: String,
+    /// When the report was generated, as an ISO 8601 UTC timestamp
+    pub generated_at: String,
+    /// Environment file (or installed environment name) the report was generated from
+    pub input: Option<String>,
+    /// Whether packages were checked for newer versions
+    pub outdated_check: bool,
+    /// Whether packages were scanned for known vulnerabilities
+    pub vulnerability_check: bool,
+}
+
+impl ReportMetadata {
+    /// Metadata for a report generated now by this version of the tool
+    pub fn new(input: Option<String>, outdated_check: bool, vulnerability_check: bool) -> Self {
+        ReportMetadata {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: crate::utils::utc_timestamp(),
+            input,
+            outdated_check,
+            vulnerability_check,
+        }
+    }
+}
+
 /// Represents the analysis results for an environment
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnvironmentAnalysis {
+    /// Report provenance, included in JSON and HTML exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ReportMetadata>,
     /// Name of the environment
     pub name: Option<String>,
+    /// Install prefix recorded in the environment file, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Platform the environment was locked for (e.g. "linux-64"), from an explicit lock file or `--platform`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    /// Number of environment variables the file sets on activation (`variables:`)
+    #[serde(default)]
+    pub variable_count: usize,
     /// Parsed packages in the environment
     pub packages: Vec<Package>,
     /// Total size of all packages combined
     pub total_size: Option<u64>,
+    /// Total size including every transitive dependency (only present with `--include-transitive`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size_with_dependencies: Option<u64>,
+    /// Compressed size of the package archives to download (only present with `--download-size`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_size: Option<DownloadSize>,
     /// Count of pinned packages
     pub pinned_count: usize,
     /// Count of outdated packages
@@ -95,4 +405,386 @@ pub struct EnvironmentAnalysis {
     /// Recommendations for environment optimization
     #[serde(default)]
     pub recommendations: Vec<Recommendation>,
+    /// Packages whose outdated check failed, each with the reason (e.g. a network error).
+    /// Their `is_outdated` is false because it is unknown, not because they are up to date
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub check_failures: Vec<(String, String)>,
+    /// Enrichment data-quality report (only present when outdated checks were run)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<EnrichmentReport>,
+    /// Dependency graph complexity metrics (only present when an advanced graph was built)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graph_metrics: Option<GraphMetrics>,
+    /// Whether the file looks like (or, with `--from-history`, is treated as) a
+    /// `conda env export --from-history` spec listing only explicitly requested packages
+    #[serde(default)]
+    pub is_history_export: bool,
+}
+
+/// Download size of an environment's packages, summed from the archive sizes on anaconda.org.
+/// This is what fetching the packages costs, not the (larger) installed size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadSize {
+    /// Compressed size in bytes of the archives that were found
+    pub bytes: u64,
+    /// Packages without a known archive size (pip packages are never looked up)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+/// Complexity metrics of the direct dependency edges of a dependency graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphMetrics {
+    /// Length of the longest dependency chain, in edges (packages in a cycle count as one)
+    pub max_depth: usize,
+    /// Package that the most other packages depend on (highest fan-in)
+    pub most_depended_upon: Option<String>,
+    /// Number of packages depending on `most_depended_upon`
+    pub max_fan_in: usize,
+    /// Package with the most direct dependencies (highest fan-out)
+    pub most_dependencies: Option<String>,
+    /// Number of direct dependencies of `most_dependencies`
+    pub max_fan_out: usize,
+    /// Average number of dependencies plus dependents per package
+    pub average_degree: f64,
+}
+
+impl fmt::Display for GraphMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max depth {}", self.max_depth)?;
+        if let Some(name) = &self.most_depended_upon {
+            write!(f, "; most depended upon: {} ({})", name, self.max_fan_in)?;
+        }
+        if let Some(name) = &self.most_dependencies {
+            write!(f, "; most dependencies: {} ({})", name, self.max_fan_out)?;
+        }
+        write!(f, "; average degree {:.2}", self.average_degree)
+    }
+}
+
+/// Package field to order the package list by (`--sort-by`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Package name (case-insensitive)
+    Name,
+    /// Package size
+    Size,
+    /// Package version, using conda's version ordering
+    Version,
+    /// Outdated packages first, then pinned, then the rest
+    Status,
+}
+
+/// How tightly a dependency spec constrains the package version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinStatus {
+    /// Pinned to a single version (`numpy==1.21.0`, `numpy=1.21.0`, or a locked package)
+    Exact,
+    /// Constrained to a range of versions (`numpy>=1.20,<1.22`, `numpy=1.21.*`)
+    Range,
+    /// Any version is accepted (`numpy`)
+    #[default]
+    Unpinned,
+}
+
+impl PinStatus {
+    /// Status of a declared version: none is unpinned, a wildcard or comparison is a range
+    pub fn of_version(version: Option<&str>) -> Self {
+        match version.map(str::trim) {
+            None | Some("") | Some("*") => PinStatus::Unpinned,
+            Some(version) if version.contains(['*', '<', '>', '!', '~', ',', '|']) => PinStatus::Range,
+            Some(_) => PinStatus::Exact,
+        }
+    }
+}
+
+impl fmt::Display for PinStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinStatus::Exact => write!(f, "exact"),
+            PinStatus::Range => write!(f, "range"),
+            PinStatus::Unpinned => write!(f, "unpinned"),
+        }
+    }
+}
+
+impl Package {
+    /// A package known only by name: no version, build, channel or metadata. Chain the
+    /// `with_*` methods to fill in the rest
+    pub fn new(name: impl Into<String>) -> Self {
+        Package {
+            name: name.into(),
+            version: None,
+            constraint: None,
+            build: None,
+            channel: None,
+            size: None,
+            pin_status: PinStatus::Unpinned,
+            is_outdated: false,
+            latest_version: None,
+            md5: None,
+            license: None,
+            declared_in_both: false,
+            pip_version: None,
+            size_estimated: false,
+            extras: Vec::new(),
+        }
+    }
+
+    /// Sets the declared version, deriving the pin status from it like a parsed spec would.
+    /// A range or wildcard is stored as the `constraint` instead
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.set_declared_version(Some(version.into()));
+        self
+    }
+
+    /// Whether the package is pinned to an exact version
+    pub fn is_pinned(&self) -> bool {
+        self.pin_status == PinStatus::Exact
+    }
+
+    /// Records a declared version and its pin status: an exact version goes to `version`,
+    /// anything else that constrains it to `constraint`
+    pub fn set_declared_version(&mut self, version: Option<String>) {
+        self.pin_status = PinStatus::of_version(version.as_deref());
+        let version = version.map(|v| v.trim().to_string());
+        (self.version, self.constraint) = match self.pin_status {
+            PinStatus::Exact => (version, None),
+            PinStatus::Range => (None, version),
+            PinStatus::Unpinned => (None, None),
+        };
+    }
+
+    /// Sets the build string
+    pub fn with_build(mut self, build: impl Into<String>) -> Self {
+        self.build = Some(build.into());
+        self
+    }
+
+    /// Sets the channel ("pip" for packages from the `pip:` section)
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    /// Sets the size in bytes
+    pub fn with_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the latest available version and whether the package is behind it
+    pub fn with_latest_version(mut self, latest: impl Into<String>, is_outdated: bool) -> Self {
+        self.latest_version = Some(latest.into());
+        self.is_outdated = is_outdated;
+        self
+    }
+
+    /// Sets the license
+    pub fn with_license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Name with any pip extras, as it would be written in a requirement (`uvicorn[standard]`)
+    pub fn display_name(&self) -> String {
+        if self.extras.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}[{}]", self.name, self.extras.join(","))
+        }
+    }
+    
+    /// Fills in the metadata this package is missing from `other`, another record of the
+    /// same package (e.g. from a second environment file). Known values are kept
+    pub fn merge(&mut self, other: &Package) {
+        if self.build.is_none() {
+            self.build = other.build.clone();
+        }
+        if self.channel.is_none() {
+            self.channel = other.channel.clone();
+        }
+        if self.size.is_none() || (self.size_estimated && !other.size_estimated && other.size.is_some()) {
+            self.size = other.size;
+            self.size_estimated = other.size_estimated;
+        }
+        if self.latest_version.is_none() {
+            self.latest_version = other.latest_version.clone();
+            self.is_outdated = other.is_outdated;
+        }
+        if self.md5.is_none() {
+            self.md5 = other.md5.clone();
+        }
+        if self.license.is_none() {
+            self.license = other.license.clone();
+        }
+        if self.extras.is_empty() {
+            self.extras = other.extras.clone();
+        }
+    }
+
+    /// Whether the package is declared in both the conda and pip sections with different versions
+    pub fn has_conflicting_pip_version(&self) -> bool {
+        match (&self.version, &self.pip_version) {
+            (Some(conda), Some(pip)) => self.declared_in_both && !crate::version::versions_match(conda, pip),
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for Package {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.version == other.version
+    }
+}
+
+impl Eq for Package {}
+
+impl std::hash::Hash for Package {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.version.hash(state);
+    }
+}
+
+impl EnvironmentAnalysis {
+    /// Iterate over the packages, enriching each one with its registry metadata only when it
+    /// is pulled, e.g. to display packages as they arrive instead of waiting for the whole
+    /// list. Pair it with [`analyze_environment_lazy`](crate::utils::analyze_environment_lazy),
+    /// which leaves the packages unenriched; packages already enriched are yielded as they are
+    pub fn enriched_packages<'a>(&'a self, lookup: &'a crate::conda_api::LookupOptions) -> crate::performance::EnrichedPackages<'a> {
+        crate::performance::EnrichedPackages::new(&self.packages, lookup)
+    }
+    
+    /// "N packages could not be checked" note listing each package and reason, if any check failed
+    pub fn check_failure_note(&self) -> Option<String> {
+        if self.check_failures.is_empty() {
+            return None;
+        }
+        let failures: Vec<String> = self.check_failures.iter()
+            .map(|(package, reason)| format!("{} ({})", package, reason))
+            .collect();
+        Some(format!("{} packages could not be checked: {}", failures.len(), failures.join(", ")))
+    }
+    
+    /// Whether the total size includes estimated package sizes
+    pub fn has_estimated_sizes(&self) -> bool {
+        self.packages.iter().any(|p| p.size_estimated && p.size.is_some())
+    }
+    
+    /// Reorders the packages by `key`. The sort is stable, so packages that compare equal
+    /// keep their file order; packages with an unknown size or version always go last
+    pub fn sort_packages(&mut self, key: SortKey, descending: bool) {
+        let direction = |ordering: Ordering| if descending { ordering.reverse() } else { ordering };
+        let status_rank = |p: &Package| if p.is_outdated { 0 } else if p.is_pinned() { 1 } else { 2 };
+        let version = |p: &Package| p.version.as_deref().and_then(CondaVersion::parse);
+        
+        self.packages.sort_by(|a, b| match key {
+            SortKey::Name => direction(a.name.to_lowercase().cmp(&b.name.to_lowercase())),
+            SortKey::Size => known_first(a.size, b.size, |a, b| direction(a.cmp(&b))),
+            SortKey::Version => known_first(version(a), version(b), |a, b| direction(a.cmp(&b))),
+            SortKey::Status => direction(status_rank(a).cmp(&status_rank(b))),
+        });
+    }
+}
+
+/// Orders known values with `cmp`, placing unknown values after all known ones
+fn known_first<T>(a: Option<T>, b: Option<T>, cmp: impl FnOnce(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Vulnerability severity levels, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Parse a severity name as reported by a vulnerability source ("moderate" means medium)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "low" => Some(Severity::Low),
+            "medium" | "moderate" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A known or suspected vulnerability affecting an installed package version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vulnerability {
+    /// Name of the affected package
+    pub package: String,
+    /// Affected version found in the environment
+    pub version: String,
+    /// Advisory identifier (e.g. a CVE, GHSA or PyUp ID), if known
+    pub id: Option<String>,
+    /// Severity ("low", "medium", "high" or "critical"), if the source reports one
+    pub severity: Option<String>,
+    /// Human-readable description of the issue
+    pub description: String,
+    /// Where the finding came from: "local-db", "osv", "safety-db", "pypi-yanked" or "version-gap"
+    pub source: String,
+}
+
+impl Vulnerability {
+    /// Whether this finding is at or above `threshold`. Findings whose source doesn't
+    /// report a severity always count, so an unknown severity never slips through a gate
+    pub fn is_at_least(&self, threshold: Severity) -> bool {
+        match self.severity.as_deref().and_then(Severity::from_name) {
+            Some(severity) => severity >= threshold,
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for Vulnerability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} - {}", self.package, self.version, self.description)?;
+        if let Some(id) = &self.id {
+            write!(f, " ({})", id)?;
+        }
+        if let Some(severity) = &self.severity {
+            write!(f, " [{}]", severity)?;
+        }
+        Ok(())
+    }
+}
+
+/// Vulnerability scan results for an environment (the `vulnerabilities --format json` schema)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VulnerabilityReport {
+    /// Name of the environment
+    pub environment: Option<String>,
+    /// Number of packages scanned
+    pub packages_scanned: usize,
+    /// Findings, deduplicated
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// Number of findings left out because they are on the ignore list
+    #[serde(default)]
+    pub suppressed: usize,
 }
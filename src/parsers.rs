@@ -1,57 +1,1075 @@
 use anyhow::{Context, Result};
+use log::{debug, warn};
 use std::fs;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use crate::models::{CondaEnvironment, Dependency, Package};
+use crate::conda_api::LookupOptions;
+use crate::models::{ComplexDependency, CondaEnvironment, Dependency, Package, PinStatus};
 
-/// Parses a Conda environment file (YAML or JSON) and returns the environment data
+/// Marker line that starts the package list of an explicit spec file (`conda list --explicit`)
+const EXPLICIT_MARKER: &str = "@EXPLICIT";
+
+/// Format of an environment file's contents
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Conda environment YAML (environment.yml, conda-lock.yml)
+    Yaml,
+    /// Conda environment as JSON (.json, .conda)
+    Json,
+    /// Explicit spec / lock file with an `@EXPLICIT` package URL list
+    Explicit,
+    /// pip requirements file (requirements.txt)
+    Requirements,
+    /// Python project metadata (pyproject.toml) with PEP 621 or Poetry dependencies
+    Pyproject,
+}
+
+impl FileFormat {
+    /// Determines the format from the file extension, falling back to the contents for
+    /// explicit spec files, which are often saved as .txt or without an extension.
+    /// Other .txt files are read as pip requirements files
+    pub fn detect<P: AsRef<Path>>(file_path: P, content: &str) -> Result<Self> {
+        let extension = file_path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+
+        match extension.to_lowercase().as_str() {
+            "yml" | "yaml" => Ok(FileFormat::Yaml),
+            "conda" | "json" => Ok(FileFormat::Json),
+            "lock" => Ok(FileFormat::Explicit),
+            _ if content.lines().any(|line| line.trim() == EXPLICIT_MARKER) => Ok(FileFormat::Explicit),
+            "txt" => Ok(FileFormat::Requirements),
+            "toml" => Ok(FileFormat::Pyproject),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported file format: {}. Only .yml, .yaml, .conda, .json, .lock, .txt, .toml or @EXPLICIT files are supported.",
+                extension
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileFormat::Yaml => write!(f, "YAML"),
+            FileFormat::Json => write!(f, "JSON"),
+            FileFormat::Explicit => write!(f, "explicit lock file"),
+            FileFormat::Requirements => write!(f, "requirements file"),
+            FileFormat::Pyproject => write!(f, "pyproject.toml"),
+        }
+    }
+}
+
+/// Parses a Conda environment file (YAML, JSON, an explicit lock file or a pip requirements file)
+/// and returns the environment data. Requirements files included from `pip:` lists with
+/// `-r`/`-c` are read relative to the environment file
 pub fn parse_environment_file<P: AsRef<Path>>(file_path: P) -> Result<CondaEnvironment> {
-    let file_path = file_path.as_ref();
-    let extension = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
-
-    match extension.to_lowercase().as_str() {
-        "yml" | "yaml" => parse_yaml_file(file_path),
-        "conda" | "json" => parse_json_file(file_path),
+    parse_environment_file_with_document(file_path, None, &LookupOptions::default())
+}
+
+/// Parses an environment file like [`parse_environment_file`], reading the given 1-based
+/// document of a multi-document YAML file (`--document N`); `None` picks it automatically.
+/// A file given as a URL is downloaded according to `lookup`
+pub fn parse_environment_file_with_document<P: AsRef<Path>>(
+    file_path: P,
+    document: Option<usize>,
+    lookup: &LookupOptions,
+) -> Result<CondaEnvironment> {
+    let (content, format) = read_environment_file(&file_path, lookup)?;
+    
+    let mut env = parse_environment_str_with_document(&content, format, document)
+        .with_context(|| format!("Failed to parse {} content from: {:?}", format, file_path.as_ref()))?;
+    if !is_url(&file_path.as_ref().to_string_lossy()) {
+        resolve_pip_includes(&mut env, file_path.as_ref());
+    }
+    Ok(env)
+}
+
+/// The path of a `-r`/`--requirement` (`false`) or `-c`/`--constraint` (`true`) entry in a pip list
+fn pip_include(spec: &str) -> Option<(bool, &str)> {
+    let spec = spec.trim();
+    [("-r", false), ("--requirement", false), ("-c", true), ("--constraint", true)].iter()
+        .find_map(|(flag, constraint)| {
+            let rest = spec.strip_prefix(flag)?;
+            let path = rest.strip_prefix('=').or_else(|| rest.strip_prefix(char::is_whitespace))?;
+            Some((*constraint, path.trim()))
+        })
+        .filter(|(_, path)| !path.is_empty())
+}
+
+/// Replaces `-r <file>` entries of the `pip:` lists with the requirements of the referenced
+/// file, following nested includes, and applies `-c <file>` constraints to requirements that
+/// don't constrain their version themselves. Paths are relative to the including file. A file
+/// is read at most once, so circular includes end; unreadable files are skipped with a warning
+pub fn resolve_pip_includes(env: &mut CondaEnvironment, env_file: &Path) {
+    let mut visited: HashSet<PathBuf> = env_file.canonicalize().into_iter().collect();
+    let base_dir = env_file.parent().unwrap_or(Path::new("")).to_path_buf();
+    
+    for dependency in &mut env.dependencies {
+        let Dependency::Complex(complex) = dependency else {
+            continue;
+        };
+        let Some(specs) = complex.pip.take() else {
+            continue;
+        };
+        
+        let mut constraints = HashMap::new();
+        let mut resolved = expand_pip_includes(specs, &base_dir, &mut visited, &mut constraints);
+        if !constraints.is_empty() {
+            for spec in &mut resolved {
+                apply_pip_constraint(spec, &constraints);
+            }
+        }
+        complex.pip = Some(resolved);
+    }
+}
+
+/// Expands the includes of a list of pip specs, collecting constraints by lowercase name
+fn expand_pip_includes(
+    specs: Vec<String>,
+    base_dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    constraints: &mut HashMap<String, String>,
+) -> Vec<String> {
+    let mut expanded = Vec::new();
+    
+    for spec in specs {
+        let Some((is_constraint, include)) = pip_include(&spec) else {
+            expanded.push(spec);
+            continue;
+        };
+        
+        let path = base_dir.join(include);
+        let Ok(canonical) = path.canonicalize() else {
+            warn!("Skipping pip include {}: {:?} not found", spec.trim(), path);
+            continue;
+        };
+        if !visited.insert(canonical) {
+            warn!("Skipping pip include {}: {:?} was already included (circular or repeated include)", spec.trim(), path);
+            continue;
+        }
+        
+        let content = match fs::read(&path).map_err(anyhow::Error::from).and_then(decode_text) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Skipping pip include {}: {}", spec.trim(), e);
+                continue;
+            }
+        };
+        debug!("Including pip requirements from {:?}", path);
+        
+        let included = requirement_lines(&content);
+        let nested_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let included = expand_pip_includes(included, &nested_dir, visited, constraints);
+        if is_constraint {
+            for constraint in included {
+                constraints.insert(spec_name(&constraint).to_lowercase(), constraint);
+            }
+        } else {
+            expanded.extend(included);
+        }
+    }
+    
+    expanded
+}
+
+/// Gives a requirement without its own version constraint the version of its constraint
+/// ("numpy" with the constraint "numpy==1.24.0" becomes "numpy==1.24.0"), keeping extras
+/// and markers
+fn apply_pip_constraint(spec: &mut String, constraints: &HashMap<String, String>) {
+    let (requirement, marker) = match spec.split_once(';') {
+        Some((requirement, marker)) => (requirement, Some(marker.trim())),
+        None => (spec.as_str(), None),
+    };
+    if requirement.contains("://") {
+        return;
+    }
+    let (name, extras, version) = split_pip_requirement(requirement);
+    if !version.is_empty() {
+        return;
+    }
+    let Some(constraint) = constraints.get(&name.to_lowercase()) else {
+        return;
+    };
+    let (_, _, constraint_version) = split_pip_requirement(constraint.split(';').next().unwrap_or(constraint));
+    if constraint_version.is_empty() {
+        return;
+    }
+    
+    let mut constrained = format!("{}{}{}", name, extras, constraint_version);
+    if let Some(marker) = marker {
+        constrained.push_str("; ");
+        constrained.push_str(marker);
+    }
+    *spec = constrained;
+}
+
+/// Splits a pip requirement (without markers) into its name, extras (with the brackets)
+/// and version constraint
+fn split_pip_requirement(requirement: &str) -> (&str, &str, &str) {
+    let requirement = requirement.trim();
+    let name = spec_name(requirement);
+    let rest = requirement[requirement.find(name).map_or(0, |idx| idx + name.len())..].trim_start();
+    let extras_len = if rest.starts_with('[') { rest.find(']').map_or(rest.len(), |idx| idx + 1) } else { 0 };
+    (name, &rest[..extras_len], rest[extras_len..].trim())
+}
+
+/// Reads an environment file and detects its format. The file may be an `http://` or
+/// `https://` URL, and may be gzip-compressed (a `.gz` suffix, e.g. `environment.yml.gz`);
+/// the format is then detected from the name without the `.gz`
+pub fn read_environment_file<P: AsRef<Path>>(file_path: P, lookup: &LookupOptions) -> Result<(String, FileFormat)> {
+    let source = file_path.as_ref().to_string_lossy();
+    let (mut bytes, mut name) = if is_url(&source) {
+        // Query strings and fragments would otherwise hide the extension
+        let name = source.split(['?', '#']).next().unwrap_or(&source).to_string();
+        (crate::conda_api::fetch_url(&source, lookup)?, PathBuf::from(name))
+    } else {
+        let bytes = fs::read(&file_path)
+            .with_context(|| format!("Failed to read file: {:?}", file_path.as_ref()))?;
+        (bytes, file_path.as_ref().to_path_buf())
+    };
+    
+    if name.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) || bytes.starts_with(GZIP_MAGIC) {
+        debug!("Decompressing gzip file: {:?}", file_path.as_ref());
+        bytes = decompress_gzip(&bytes)
+            .with_context(|| format!("Failed to decompress gzip file: {:?}", file_path.as_ref()))?;
+        if name.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) {
+            name.set_extension("");
+        }
+    }
+    
+    let content = decode_text(bytes)
+        .with_context(|| format!("Failed to read file: {:?}", file_path.as_ref()))?;
+    let format = FileFormat::detect(&name, &content)?;
+    
+    Ok((content, format))
+}
+
+/// First bytes of every gzip stream
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+/// Whether an environment file argument is an `http://` or `https://` URL rather than a path
+pub fn is_url(source: &str) -> bool {
+    let lower = source.trim_start().to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// Decompresses a gzip stream
+fn decompress_gzip(bytes: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+    
+    let mut decompressed = Vec::new();
+    flate2::read::MultiGzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Parses environment file contents held in memory
+pub fn parse_environment_str(content: &str, format: FileFormat) -> Result<CondaEnvironment> {
+    parse_environment_str_with_document(content, format, None)
+}
+
+/// Parses environment file contents held in memory, reading the given 1-based document of
+/// multi-document YAML; `None` picks it automatically
+pub fn parse_environment_str_with_document(content: &str, format: FileFormat, document: Option<usize>) -> Result<CondaEnvironment> {
+    // Content not read through `read_text_file` may still carry a byte order mark
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    
+    match format {
+        FileFormat::Yaml => {
+            let mut value = select_yaml_document(content, document)?;
+            // Resolve `<<: *anchor` merge keys, which serde_yaml leaves as plain keys
+            value.apply_merge()?;
+            validate_environment_structure(&value)?;
+            Ok(serde_yaml::from_value(value)?)
+        },
+        FileFormat::Json => Ok(serde_json::from_str(content)?),
+        FileFormat::Explicit => parse_explicit(content),
+        FileFormat::Requirements => Ok(parse_requirements(content)),
+        FileFormat::Pyproject => parse_pyproject(content),
+    }
+}
+
+/// Picks the environment document of a YAML file that may contain several (`---` separated):
+/// the 1-based `document` when given (`--document N`), else the only document that looks like
+/// a conda environment (a mapping with `dependencies` or `channels`), else the first document
+fn select_yaml_document(content: &str, document: Option<usize>) -> Result<serde_yaml::Value> {
+    use serde::Deserialize;
+    
+    let documents = serde_yaml::Deserializer::from_str(content)
+        .map(serde_yaml::Value::deserialize)
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    if let Some(selected) = document.filter(|selected| *selected > 0) {
+        let count = documents.len();
+        return documents.into_iter().nth(selected - 1).ok_or_else(|| anyhow::anyhow!(
+            "--document {} is out of range: the file has {} YAML document(s)", selected, count
+        ));
+    }
+    if documents.len() <= 1 {
+        return Ok(documents.into_iter().next().unwrap_or(serde_yaml::Value::Null));
+    }
+    
+    let environments: Vec<usize> = documents.iter()
+        .enumerate()
+        .filter(|(_, document)| ["dependencies", "channels"].iter().any(|key| document.get(key).is_some()))
+        .map(|(i, _)| i)
+        .collect();
+    match environments.as_slice() {
+        [] => Ok(documents.into_iter().find(|document| !document.is_null()).unwrap_or(serde_yaml::Value::Null)),
+        [index] => {
+            debug!("Using YAML document {} of {}", index + 1, documents.len());
+            Ok(documents.into_iter().nth(*index).unwrap_or(serde_yaml::Value::Null))
+        },
         _ => Err(anyhow::anyhow!(
-            "Unsupported file format: {}. Only .yml, .yaml, .conda, or .json files are supported.",
-            extension
+            "The file contains {} conda environments (YAML documents {}); choose one with --document N",
+            environments.len(),
+            environments.iter().map(|i| (i + 1).to_string()).collect::<Vec<_>>().join(", ")
         )),
     }
 }
 
-/// Parses a YAML environment file
-fn parse_yaml_file<P: AsRef<Path>>(file_path: P) -> Result<CondaEnvironment> {
-    let content = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read YAML file: {:?}", file_path.as_ref()))?;
+/// Checks the top-level structure of a YAML environment before it is deserialized, so
+/// that a mistake such as `dependencies` being a mapping is reported by key instead of
+/// with serde's generic type error
+pub fn validate_environment_structure(value: &serde_yaml::Value) -> Result<()> {
+    use serde_yaml::Value;
     
-    serde_yaml::from_str(&content)
-        .with_context(|| format!("Failed to parse YAML content from: {:?}", file_path.as_ref()))
+    let Some(mapping) = value.as_mapping() else {
+        return Err(anyhow::anyhow!(
+            "Environment file must be a mapping with keys such as `name`, `channels` and `dependencies`, but it is a {}",
+            yaml_kind(value)
+        ));
+    };
+    let get = |key: &str| mapping.get(Value::String(key.to_string()));
+    
+    for key in ["name", "prefix"] {
+        if let Some(field) = get(key) {
+            if !matches!(field, Value::String(_) | Value::Null) {
+                return Err(anyhow::anyhow!("`{}` must be a string, but it is a {}", key, yaml_kind(field)));
+            }
+        }
+    }
+    
+    if let Some(variables) = get("variables").filter(|v| !v.is_null()) {
+        let Some(variables) = variables.as_mapping() else {
+            return Err(anyhow::anyhow!(
+                "`variables` must be a mapping of variable names to values, but it is a {}", yaml_kind(variables)
+            ));
+        };
+        for (name, value) in variables {
+            let Some(name) = name.as_str() else {
+                return Err(anyhow::anyhow!("`variables` names must be strings, but one is a {}", yaml_kind(name)));
+            };
+            if matches!(value, Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_)) {
+                return Err(anyhow::anyhow!("`variables.{}` must be a string, but it is a {}", name, yaml_kind(value)));
+            }
+        }
+    }
+    
+    // An empty key (`channels:`) is null, which deserializes as an empty list
+    if let Some(channels) = get("channels").filter(|v| !v.is_null()) {
+        let Some(channels) = channels.as_sequence() else {
+            return Err(anyhow::anyhow!(
+                "`channels` must be a list of channel names, but it is a {}", yaml_kind(channels)
+            ));
+        };
+        if let Some((i, channel)) = channels.iter().enumerate().find(|(_, c)| !c.is_string()) {
+            return Err(anyhow::anyhow!(
+                "`channels[{}]` must be a channel name, but it is a {}", i, yaml_kind(channel)
+            ));
+        }
+    }
+    
+    if let Some(dependencies) = get("dependencies").filter(|v| !v.is_null()) {
+        let Some(dependencies) = dependencies.as_sequence() else {
+            let hint = if dependencies.is_mapping() {
+                " (write each package as a list item starting with `- `, e.g. `- numpy=1.24`)"
+            } else {
+                ""
+            };
+            return Err(anyhow::anyhow!(
+                "`dependencies` must be a list, but it is a {}{}", yaml_kind(dependencies), hint
+            ));
+        };
+        for (i, dependency) in dependencies.iter().enumerate() {
+            match dependency {
+                Value::String(_) => {},
+                Value::Mapping(entry) => {
+                    if let Some(pip) = entry.get(Value::String("pip".to_string())).filter(|v| !v.is_null()) {
+                        let Some(pip) = pip.as_sequence() else {
+                            return Err(anyhow::anyhow!(
+                                "`dependencies[{}].pip` must be a list of pip requirements, but it is a {}", i, yaml_kind(pip)
+                            ));
+                        };
+                        if let Some((j, spec)) = pip.iter().enumerate().find(|(_, s)| !s.is_string()) {
+                            return Err(anyhow::anyhow!(
+                                "`dependencies[{}].pip[{}]` must be a requirement string, but it is a {}", i, j, yaml_kind(spec)
+                            ));
+                        }
+                    }
+                },
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "`dependencies[{}]` must be a package spec string or a `pip:` block, but it is a {}",
+                        i, yaml_kind(other)
+                    ));
+                },
+            }
+        }
+    }
+    
+    Ok(())
 }
 
-/// Parses a JSON environment file (like .conda files)
-fn parse_json_file<P: AsRef<Path>>(file_path: P) -> Result<CondaEnvironment> {
-    let content = fs::read_to_string(&file_path)
-        .with_context(|| format!("Failed to read JSON file: {:?}", file_path.as_ref()))?;
+/// Human-readable name of a YAML value's type, for error messages
+fn yaml_kind(value: &serde_yaml::Value) -> &'static str {
+    match value {
+        serde_yaml::Value::Null => "null value",
+        serde_yaml::Value::Bool(_) => "boolean",
+        serde_yaml::Value::Number(_) => "number",
+        serde_yaml::Value::String(_) => "string",
+        serde_yaml::Value::Sequence(_) => "list",
+        serde_yaml::Value::Mapping(_) => "mapping",
+        serde_yaml::Value::Tagged(_) => "tagged value",
+    }
+}
+
+/// Parses a base environment file and merges the given overlay files on top of it, in order.
+/// `document` selects the YAML document of every file, as in [`parse_environment_file_with_document`]
+pub fn parse_environment_with_overlays<P: AsRef<Path>>(
+    file_path: P,
+    overlays: &[PathBuf],
+    document: Option<usize>,
+    lookup: &LookupOptions,
+) -> Result<CondaEnvironment> {
+    let mut env = parse_environment_file_with_document(&file_path, document, lookup)?;
+    
+    for overlay_path in overlays {
+        debug!("Applying overlay: {:?}", overlay_path);
+        let overlay = parse_environment_file_with_document(overlay_path, document, lookup)
+            .with_context(|| format!("Failed to parse overlay file: {:?}", overlay_path))?;
+        merge_overlay(&mut env, overlay);
+    }
     
-    serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse JSON content from: {:?}", file_path.as_ref()))
+    Ok(env)
 }
 
-/// Extracts the name, version, and build string from a package specification
-pub fn parse_package_spec(spec: &str) -> Package {
-    let mut package = Package {
-        name: String::new(),
-        version: None,
+/// Merges an overlay environment into a base environment.
+///
+/// Overlay dependencies replace base dependencies with the same package name (conda and pip
+/// entries are matched separately) and are otherwise added. The overlay always wins on
+/// conflicts, including the environment name, variables and any extra keys. Overlay channels that are
+/// not already present are given priority over the base channels.
+pub fn merge_overlay(base: &mut CondaEnvironment, overlay: CondaEnvironment) {
+    if overlay.name.is_some() {
+        base.name = overlay.name;
+    }
+    if overlay.prefix.is_some() {
+        base.prefix = overlay.prefix;
+    }
+    if let Some(variables) = overlay.variables {
+        base.variables.get_or_insert_with(Default::default).extend(variables);
+    }
+    
+    let new_channels: Vec<String> = overlay.channels.into_iter()
+        .filter(|channel| !base.channels.contains(channel))
+        .collect();
+    base.channels.splice(0..0, new_channels);
+    
+    for dep in overlay.dependencies {
+        match dep {
+            Dependency::Simple(spec) => {
+                let name = spec_name(&spec).to_string();
+                // A `pip:` block is never a named package entry, even with `name: pip`
+                let existing = base.dependencies.iter_mut().find(|d| match d {
+                    Dependency::Complex(c) => c.pip.is_none() && c.name.as_deref().map(spec_name) == Some(name.as_str()),
+                    Dependency::Simple(base_spec) => spec_name(base_spec) == name,
+                });
+                
+                match existing {
+                    Some(existing) => *existing = Dependency::Simple(spec),
+                    None => base.dependencies.push(Dependency::Simple(spec)),
+                }
+            },
+            Dependency::Complex(mut complex) => {
+                if let Some(pip_specs) = complex.pip.take() {
+                    merge_pip_specs(base, pip_specs);
+                }
+                
+                // Whatever remains (a named package entry) is merged by name
+                let name = complex.name.as_deref()
+                    .map(spec_name)
+                    .filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("pip"))
+                    .map(str::to_string);
+                if let Some(name) = name {
+                    let existing = base.dependencies.iter_mut().find(|d| match d {
+                        Dependency::Complex(c) => c.name.as_deref().map(spec_name) == Some(name.as_str()),
+                        Dependency::Simple(spec) => spec_name(spec) == name,
+                    });
+                    
+                    match existing {
+                        Some(existing) => *existing = Dependency::Complex(complex),
+                        None => base.dependencies.push(Dependency::Complex(complex)),
+                    }
+                }
+            }
+        }
+    }
+    
+    base.extra.extend(overlay.extra);
+}
+
+/// Merges overlay pip specs into the pip blocks of the base environment. Specs replace a
+/// same-named entry in any pip block; new specs go into the first pip block
+fn merge_pip_specs(base: &mut CondaEnvironment, pip_specs: Vec<String>) {
+    let mut new_specs = Vec::new();
+    
+    for spec in pip_specs {
+        let name = spec_name(&spec).to_lowercase();
+        let existing = base.dependencies.iter_mut()
+            .filter_map(|d| match d {
+                Dependency::Complex(c) => c.pip.as_mut(),
+                _ => None,
+            })
+            .flat_map(|specs| specs.iter_mut())
+            .find(|s| spec_name(s).to_lowercase() == name);
+        
+        match existing {
+            Some(existing) => *existing = spec,
+            None => new_specs.push(spec),
+        }
+    }
+    
+    if new_specs.is_empty() {
+        return;
+    }
+    
+    let pip_block = base.dependencies.iter_mut().find_map(|d| match d {
+        Dependency::Complex(c) => c.pip.as_mut(),
+        _ => None,
+    });
+    
+    match pip_block {
+        Some(base_specs) => base_specs.extend(new_specs),
+        None => base.dependencies.push(Dependency::Complex(ComplexDependency {
+            name: None,
+            pip: Some(new_specs),
+            extra: Default::default(),
+        })),
+    }
+}
+
+/// Returns the bare package name of a conda or pip spec, without channel prefix,
+/// version constraint, build string or extras (e.g. "conda-forge::numpy>=1.20" -> "numpy")
+pub fn spec_name(spec: &str) -> &str {
+    let spec = spec.trim();
+    let spec = match spec.find("::") {
+        Some(idx) => &spec[idx + 2..],
+        None => spec,
+    };
+    
+    let end = spec
+        .find(|c: char| matches!(c, '=' | '<' | '>' | '!' | '~' | '[' | ';' | '@') || c.is_whitespace())
+        .unwrap_or(spec.len());
+    spec[..end].trim()
+}
+
+/// Parses an explicit lock file, as written by `conda list --explicit` or conda-lock's `.lock`
+/// output. Each package is a URL such as
+/// `https://conda.anaconda.org/conda-forge/linux-64/numpy-1.21.0-py39h_0.tar.bz2#<md5>`
+pub fn parse_explicit_lock_file<P: AsRef<Path>>(file_path: P) -> Result<CondaEnvironment> {
+    let content = read_text_file(&file_path)
+        .with_context(|| format!("Failed to read file: {:?}", file_path.as_ref()))?;
+    
+    parse_explicit(&content)
+        .with_context(|| format!("Failed to parse explicit lock file: {:?}", file_path.as_ref()))
+}
+
+/// Parses the contents of an explicit lock file into an environment whose dependencies
+/// carry the exact version, build, channel and md5 of each package
+pub fn parse_explicit(content: &str) -> Result<CondaEnvironment> {
+    let mut lines = content.lines().map(str::trim);
+    
+    // `conda list --explicit` writes a `# platform: linux-64` comment before the marker
+    let mut platform = None;
+    let mut has_marker = false;
+    for line in lines.by_ref() {
+        if line == EXPLICIT_MARKER {
+            has_marker = true;
+            break;
+        }
+        if let Some(value) = line.strip_prefix('#').and_then(|c| c.trim().strip_prefix("platform:")) {
+            platform = Some(value.trim().to_string());
+        }
+    }
+    if !has_marker {
+        return Err(anyhow::anyhow!("Missing {} marker", EXPLICIT_MARKER));
+    }
+    
+    let mut env = CondaEnvironment {
+        name: None,
+        channels: Vec::new(),
+        dependencies: Vec::new(),
+        prefix: None,
+        variables: None,
+        extra: Default::default(),
+    };
+    
+    for line in lines.filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let Some((name, version, build, channel, md5)) = parse_explicit_url(line) else {
+            warn!("Skipping unrecognized line in explicit lock file: {}", line);
+            continue;
+        };
+        
+        // Without a header, the first platform-specific package subdir gives the platform
+        if platform.is_none() {
+            platform = explicit_url_subdir(line)
+                .filter(|subdir| *subdir != "noarch")
+                .map(str::to_string);
+        }
+        
+        if !env.channels.contains(&channel) {
+            env.channels.push(channel.clone());
+        }
+        
+        let mut extra = std::collections::HashMap::new();
+        extra.insert("version".to_string(), serde_yaml::Value::String(version));
+        extra.insert("build".to_string(), serde_yaml::Value::String(build));
+        extra.insert("channel".to_string(), serde_yaml::Value::String(channel));
+        if let Some(md5) = md5 {
+            extra.insert("md5".to_string(), serde_yaml::Value::String(md5));
+        }
+        env.dependencies.push(Dependency::Complex(ComplexDependency {
+            name: Some(name),
+            pip: None,
+            extra,
+        }));
+    }
+    
+    if let Some(platform) = platform.filter(|p| !p.is_empty()) {
+        debug!("Explicit lock file targets platform {}", platform);
+        env.extra.insert("platform".to_string(), serde_yaml::Value::String(platform));
+    }
+    
+    debug!("Parsed {} packages from explicit lock file", env.dependencies.len());
+    Ok(env)
+}
+
+/// Platform subdir of a package URL, e.g. "linux-64" or "noarch"
+fn explicit_url_subdir(line: &str) -> Option<&str> {
+    let url = line.split('#').next()?.trim();
+    let mut segments = url.rsplit('/').filter(|s| !s.is_empty());
+    segments.next()?;
+    segments.next()
+}
+
+/// Splits a package URL into name, version, build, channel and (if present) md5 hash.
+/// The channel is the URL path between the host and the platform subdir,
+/// e.g. "conda-forge" or "pkgs/main"
+fn parse_explicit_url(line: &str) -> Option<(String, String, String, String, Option<String>)> {
+    let (url, hash) = match line.split_once('#') {
+        Some((url, hash)) => (url, Some(hash.trim())),
+        None => (line, None),
+    };
+    let (_, path) = url.split_once("://")?;
+    
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.len() < 4 {
+        return None;
+    }
+    let filename = segments[segments.len() - 1];
+    let channel = segments[1..segments.len() - 2].join("/");
+    
+    let stem = filename.strip_suffix(".tar.bz2")
+        .or_else(|| filename.strip_suffix(".conda"))?;
+    let mut parts = stem.rsplitn(3, '-');
+    let build = parts.next()?;
+    let version = parts.next()?;
+    let name = parts.next()?;
+    
+    // conda writes a bare md5; newer tools may write "sha256:<hash>" instead
+    let md5 = hash
+        .filter(|h| h.len() == 32 && h.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_string);
+    
+    Some((name.to_string(), version.to_string(), build.to_string(), channel, md5))
+}
+
+/// Parses the contents of a pip requirements file into an environment with a single
+/// `pip:` block. Options such as `--index-url` are ignored; `-r`/`-c` includes are kept
+/// as `-r <path>` entries for [`resolve_pip_includes`], since the referenced files can't be
+/// resolved from the contents alone
+pub fn parse_requirements(content: &str) -> CondaEnvironment {
+    let specs = requirement_lines(content);
+    
+    debug!("Parsed {} requirements", specs.len());
+    CondaEnvironment {
+        name: None,
+        channels: Vec::new(),
+        dependencies: vec![Dependency::Complex(ComplexDependency {
+            name: Some("pip".to_string()),
+            pip: Some(specs),
+            extra: Default::default(),
+        })],
+        prefix: None,
+        variables: None,
+        extra: Default::default(),
+    }
+}
+
+/// Parses a `pyproject.toml` into an environment with a single `pip:` block holding the
+/// PEP 621 `[project] dependencies` (PDM, Hatch, Flit, Poetry 2) and Poetry's
+/// `[tool.poetry.dependencies]`. Poetry constraints are translated to PEP 440 (`^1.2` to
+/// `>=1.2,<2.0`, `~1.2` to `>=1.2,<1.3`, a bare `1.2` to `==1.2`), and git, path and URL
+/// dependencies become `name @ <url>` references. Optional dependency groups and the `python`
+/// requirement are left out
+pub fn parse_pyproject(content: &str) -> Result<CondaEnvironment> {
+    let document: toml::Value = toml::from_str(content)?;
+    let project = document.get("project");
+    let poetry = document.get("tool").and_then(|tool| tool.get("poetry"));
+    if project.is_none() && poetry.is_none() {
+        return Err(anyhow::anyhow!("No [project] or [tool.poetry] table found"));
+    }
+    
+    let mut specs: Vec<String> = project
+        .and_then(|project| project.get("dependencies"))
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str)
+        .map(str::to_string)
+        .collect();
+    
+    let poetry_dependencies = poetry
+        .and_then(|poetry| poetry.get("dependencies"))
+        .and_then(toml::Value::as_table);
+    for (name, value) in poetry_dependencies.into_iter().flatten() {
+        if name.eq_ignore_ascii_case("python") {
+            continue;
+        }
+        // PEP 621 wins when a project declares a package in both places
+        if specs.iter().any(|spec| spec_name(spec).eq_ignore_ascii_case(name)) {
+            continue;
+        }
+        match poetry_requirement(name, value) {
+            Some(spec) => specs.push(spec),
+            None => warn!("Skipping Poetry dependency {} with an unrecognized specification", name),
+        }
+    }
+    
+    let name = project.and_then(|project| project.get("name"))
+        .or_else(|| poetry.and_then(|poetry| poetry.get("name")))
+        .and_then(toml::Value::as_str)
+        .map(str::to_string);
+    
+    debug!("Parsed {} pyproject.toml dependencies", specs.len());
+    Ok(CondaEnvironment {
+        name,
+        channels: Vec::new(),
+        dependencies: vec![Dependency::Complex(ComplexDependency {
+            name: Some("pip".to_string()),
+            pip: Some(specs),
+            extra: Default::default(),
+        })],
+        prefix: None,
+        variables: None,
+        extra: Default::default(),
+    })
+}
+
+/// A Poetry dependency as a PEP 508 requirement. The value is a constraint string or a table
+/// with `version` and `extras`, or with a `git`, `path` or `url` source; for multiple
+/// constraints (an array of tables) the first one is used
+fn poetry_requirement(name: &str, value: &toml::Value) -> Option<String> {
+    let value = match value {
+        toml::Value::Array(constraints) => constraints.first()?,
+        value => value,
+    };
+    
+    let (constraint, table) = match value {
+        toml::Value::String(constraint) => (constraint.as_str(), None),
+        toml::Value::Table(table) => (table.get("version").and_then(toml::Value::as_str).unwrap_or("*"), Some(table)),
+        _ => return None,
+    };
+    
+    let extras: Vec<&str> = table
+        .and_then(|table| table.get("extras"))
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(toml::Value::as_str)
+        .collect();
+    let name = if extras.is_empty() { name.to_string() } else { format!("{}[{}]", name, extras.join(",")) };
+    
+    if let Some(table) = table {
+        if let Some(git) = table.get("git").and_then(toml::Value::as_str) {
+            let reference = ["rev", "tag", "branch"].iter()
+                .find_map(|key| table.get(*key).and_then(toml::Value::as_str))
+                .map(|reference| format!("@{}", reference))
+                .unwrap_or_default();
+            return Some(format!("{} @ git+{}{}", name, git, reference));
+        }
+        if let Some(path) = table.get("path").and_then(toml::Value::as_str) {
+            return Some(format!("{} @ file://{}", name, path));
+        }
+        if let Some(url) = table.get("url").and_then(toml::Value::as_str) {
+            return Some(format!("{} @ {}", name, url));
+        }
+    }
+    
+    Some(format!("{}{}", name, poetry_constraint(constraint)?))
+}
+
+/// Translates a Poetry version constraint to PEP 440: `^` allows changes that don't modify the
+/// leftmost non-zero component, `~` allows patch changes (minor changes for a lone major), a
+/// bare version is an exact pin, and `*` is any version. Comma separated parts are translated
+/// one by one; PEP 440 operators are kept as they are
+fn poetry_constraint(constraint: &str) -> Option<String> {
+    let constraint = constraint.trim();
+    if constraint.is_empty() || constraint == "*" {
+        return Some(String::new());
+    }
+    // "||" alternatives have no PEP 440 equivalent
+    if constraint.contains('|') {
+        return None;
+    }
+    
+    let parts: Option<Vec<String>> = constraint.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            if let Some(version) = part.strip_prefix('^') {
+                caret_range(version.trim())
+            } else if part.starts_with("~=") {
+                Some(part.to_string())
+            } else if let Some(version) = part.strip_prefix('~') {
+                tilde_range(version.trim())
+            } else if part.starts_with(|c: char| c.is_ascii_digit()) {
+                Some(format!("=={}", part))
+            } else {
+                Some(part.replace(' ', ""))
+            }
+        })
+        .collect();
+    Some(parts?.join(","))
+}
+
+/// The numeric release components of a version, e.g. [1, 2] for "1.2"
+fn release_components(version: &str) -> Option<Vec<u64>> {
+    let release: Vec<u64> = version.split('.')
+        .map_while(|component| component.parse().ok())
+        .collect();
+    (!release.is_empty()).then_some(release)
+}
+
+/// `^1.2.3` is `>=1.2.3,<2.0.0`, `^0.2.3` is `>=0.2.3,<0.3.0` and `^0.0.3` is `>=0.0.3,<0.0.4`
+fn caret_range(version: &str) -> Option<String> {
+    let release = release_components(version)?;
+    let bump = release.iter().position(|&component| component != 0).unwrap_or(release.len() - 1);
+    Some(format!(">={},<{}", version, bumped(&release, bump)))
+}
+
+/// `~1.2.3` is `>=1.2.3,<1.3.0`, `~1.2` is `>=1.2,<1.3` and `~1` is `>=1,<2`
+fn tilde_range(version: &str) -> Option<String> {
+    let release = release_components(version)?;
+    Some(format!(">={},<{}", version, bumped(&release, release.len().min(2) - 1)))
+}
+
+/// The release with the component at `index` incremented and the later ones zeroed
+fn bumped(release: &[u64], index: usize) -> String {
+    release.iter().enumerate()
+        .map(|(i, &component)| match i.cmp(&index) {
+            std::cmp::Ordering::Less => component.to_string(),
+            std::cmp::Ordering::Equal => (component + 1).to_string(),
+            std::cmp::Ordering::Greater => "0".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The requirements of a pip requirements file, one spec (or `-r`/`-c` include) per entry
+fn requirement_lines(content: &str) -> Vec<String> {
+    let mut specs = Vec::new();
+    
+    // Join backslash line continuations before looking at individual requirements
+    let joined = content.replace("\\\r\n", " ").replace("\\\n", " ");
+    for line in joined.lines() {
+        // Comments start at a '#' at the beginning of the line or after whitespace
+        // ('#' inside URLs, as in "#egg=name", is not a comment)
+        let line = match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        
+        if let Some((is_constraint, path)) = pip_include(line) {
+            specs.push(format!("{} {}", if is_constraint { "-c" } else { "-r" }, path));
+            continue;
+        }
+        
+        let is_editable = line.starts_with("-e ") || line.starts_with("--editable ");
+        if line.starts_with('-') && !is_editable {
+            debug!("Ignoring requirements option: {}", line);
+            continue;
+        }
+        
+        // Drop per-requirement options such as --hash
+        let spec = line.split_whitespace()
+            .take_while(|token| !token.starts_with("--hash"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        specs.push(spec);
+    }
+    
+    specs
+}
+
+/// Parses a pip requirement (PEP 508) into a package with `channel = "pip"`.
+///
+/// Only exact pins (`==`/`===`) set a version; ranges such as `>=2.0` leave it unset.
+/// Extras (`uvicorn[standard]`) are kept in `extras` and environment markers after `;` are dropped. Editable installs and direct URL references
+/// (`git+https://...#egg=name`, `name @ https://...`) take their name from the `name @`
+/// prefix, the `#egg=` fragment, or the last URL path segment. Returns `None` for
+/// requirements without a recognizable name, such as `-e .`
+pub fn parse_pip_spec(spec: &str) -> Option<Package> {
+    let spec = spec.trim();
+    let spec = spec.strip_prefix("-e ")
+        .or_else(|| spec.strip_prefix("--editable "))
+        .unwrap_or(spec)
+        .trim();
+    let spec = spec.split(';').next().unwrap_or(spec).trim();
+    
+    let extras = pip_extras(spec.split("://").next().unwrap_or(spec));
+    
+    let (name, version, constraint, pin_status) = if let Some(idx) = spec.find("://") {
+        let (before, url) = (&spec[..idx], &spec[idx..]);
+        let name = match before.split_once('@') {
+            // PEP 508 direct reference: "name @ https://..."
+            Some((name, _)) => spec_name(name).to_string(),
+            None => url_requirement_name(url)?,
+        };
+        (name, None, None, PinStatus::Unpinned)
+    } else {
+        let name = spec_name(spec);
+        let constraint = spec[spec.find(name).map_or(0, |idx| idx + name.len())..].trim();
+        // Skip extras like "[standard]" before the version constraint
+        let constraint = match constraint.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or("", |(_, rest)| rest).trim(),
+            None => constraint,
+        };
+        let version = constraint.strip_prefix("===")
+            .or_else(|| constraint.strip_prefix("=="))
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && !v.contains(',') && !v.contains('*'))
+            .map(str::to_string);
+        let pin_status = match (&version, constraint.is_empty()) {
+            (Some(_), _) => PinStatus::Exact,
+            (None, false) => PinStatus::Range,
+            (None, true) => PinStatus::Unpinned,
+        };
+        let constraint = Some(constraint.to_string()).filter(|_| pin_status == PinStatus::Range);
+        (name.to_string(), version, constraint, pin_status)
+    };
+    
+    if name.is_empty() || name == "." || name.contains('/') {
+        return None;
+    }
+    
+    Some(Package {
+        name,
+        pin_status,
+        constraint,
+        version,
         build: None,
-        channel: None,
+        channel: Some("pip".to_string()),
         size: None,
-        is_pinned: false,
         is_outdated: false,
         latest_version: None,
+        md5: None,
+        license: None,
+        declared_in_both: false,
+        pip_version: None,
+        size_estimated: false,
+        extras,
+    })
+}
+
+/// Extras listed in brackets right after a requirement's name ("uvicorn[standard, http2]")
+fn pip_extras(spec: &str) -> Vec<String> {
+    let name = spec_name(spec);
+    let rest = spec[spec.find(name).map_or(0, |idx| idx + name.len())..].trim_start();
+    rest.strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(extras, _)| extras.split(',')
+            .map(str::trim)
+            .filter(|extra| !extra.is_empty())
+            .map(str::to_string)
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Name of a URL requirement, from its `#egg=` fragment or its last path segment
+/// (e.g. "git+https://github.com/org/repo.git@v1.0" -> "repo")
+fn url_requirement_name(url: &str) -> Option<String> {
+    if let Some((_, fragment)) = url.split_once('#') {
+        if let Some(egg) = fragment.split('&').find_map(|part| part.strip_prefix("egg=")) {
+            return Some(spec_name(egg).to_string());
+        }
+    }
+    
+    let path = url.split(['#', '?']).next()?.trim_end_matches('/');
+    let segment = path.rsplit('/').next()?;
+    let segment = segment.split('@').next()?;
+    let name = segment.strip_suffix(".git").unwrap_or(segment);
+    // Archive URLs name the distribution as "<name>-<version>.tar.gz" or a wheel filename
+    let name = if name.ends_with(".whl") || name.ends_with(".tar.gz") || name.ends_with(".zip") {
+        name.split('-').next()?
+    } else {
+        name
     };
+    
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Reads a text file as UTF-8, stripping a leading byte order mark (common in files
+/// saved by Windows editors). Invalid UTF-8 is reported with the offending byte offset.
+/// Used for every hand-edited input: environment, requirements, config and ignore files
+pub fn read_text_file<P: AsRef<Path>>(file_path: P) -> Result<String> {
+    let bytes = fs::read(&file_path)?;
+    decode_text(bytes)
+}
+
+/// Decodes file contents as UTF-8, stripping a leading byte order mark
+fn decode_text(mut bytes: Vec<u8>) -> Result<String> {
+    const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+    
+    // Offsets in errors count the stripped mark, so they match the file on disk
+    let bom_len = if bytes.starts_with(UTF8_BOM) {
+        debug!("Stripping UTF-8 byte order mark");
+        bytes.drain(..UTF8_BOM.len());
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+    
+    String::from_utf8(bytes).map_err(|e| {
+        let offset = e.utf8_error().valid_up_to();
+        let byte = e.as_bytes()[offset];
+        anyhow::anyhow!(
+            "File is not valid UTF-8: invalid byte 0x{:02X} at offset {}. Re-save the file with UTF-8 encoding.",
+            byte, offset + bom_len
+        )
+    })
+}
+
+/// Extracts the name, version, and build string from a package specification
+pub fn parse_package_spec(spec: &str) -> Package {
+    let mut package = Package::new("");
 
     // Check for channel prefix (package::channel)
     if let Some(channel_idx) = spec.find("::") {
@@ -65,38 +1083,34 @@ pub fn parse_package_spec(spec: &str) -> Package {
         parse_name_version_build(spec, &mut package);
     }
 
-    // Check if version is pinned (has an exact version)
-    if package.version.is_some() {
-        package.is_pinned = true;
-    }
-
     package
 }
 
-/// Helper function to parse name, version, and build from a package spec
+/// Helper function to parse name, version, build and pin status from a package spec such as
+/// `numpy`, `numpy=1.21.0`, `numpy==1.21.0`, `numpy=1.21.0=py39h_0` or `numpy 1.21.0 py39h_0`.
+/// Operator and wildcard specs (`numpy>=1.21`, `numpy 1.21.*`, `numpy 1.21|1.22`) don't name a
+/// single version, so they are kept as the `constraint` and leave the version unset
 fn parse_name_version_build(spec: &str, package: &mut Package) {
-    // Check for build string
-    if let Some(build_idx) = spec.find('=') {
-        if let Some(second_equal) = spec[build_idx + 1..].find('=') {
-            let name_ver = &spec[..build_idx + 1 + second_equal];
-            let build = &spec[build_idx + 1 + second_equal + 1..];
-            package.build = Some(build.to_string());
-            
-            // Parse name and version
-            if let Some(ver_idx) = name_ver.find('=') {
-                package.name = name_ver[..ver_idx].to_string();
-                package.version = Some(name_ver[ver_idx + 1..name_ver.len() - 1].to_string());
-            }
-        } else {
-            // No build string, just name and version
-            if let Some(ver_idx) = spec.find('=') {
-                package.name = spec[..ver_idx].to_string();
-                package.version = Some(spec[ver_idx + 1..].to_string());
-            }
-        }
-    } else {
-        // No version or build, just package name
-        package.name = spec.to_string();
+    let spec = spec.trim();
+    let name = spec_name(spec);
+    package.name = name.to_string();
+    
+    let rest = spec[spec.find(name).unwrap_or(0) + name.len()..].trim();
+    // A comparison operator starts a constraint, whose "=" (">=1.21") doesn't separate a build
+    let is_operator_spec = rest.starts_with(['<', '>', '!', '~']);
+    // "==" is an exact match just like a single "="
+    let rest = match is_operator_spec {
+        true => rest,
+        false => rest.strip_prefix("==").or_else(|| rest.strip_prefix('=')).unwrap_or(rest).trim(),
+    };
+    let (version, build) = match rest.split_once(|c: char| (c == '=' && !is_operator_spec) || c.is_whitespace()) {
+        Some((version, build)) => (version, Some(build)),
+        None => (rest, None),
+    };
+    
+    package.set_declared_version(Some(version.to_string()));
+    if package.is_pinned() {
+        package.build = build.map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
     }
 }
 
@@ -108,50 +1122,176 @@ pub fn extract_packages(env: &crate::models::CondaEnvironment) -> Vec<crate::mod
     for dep in &env.dependencies {
         match dep {
             crate::models::Dependency::Simple(spec) => {
-                let parts: Vec<&str> = spec.split('=').collect();
-                let name = parts[0].trim().to_string();
-                let version = if parts.len() > 1 { Some(parts[1].trim().to_string()) } else { None };
-                let is_pinned = version.is_some();
-                
-                packages.push(crate::models::Package {
-                    name,
-                    version,
-                    build: None,
-                    channel: None,
-                    size: None,
-                    is_pinned,
-                    is_outdated: false,
-                    latest_version: None,
-                });
+                packages.push(parse_package_spec(spec));
             },
             crate::models::Dependency::Complex(complex) => {
-                // Handle pip packages
-                if let Some(pip_pkgs) = &complex.pip {
-                    for pip_spec in pip_pkgs {
-                        let parts: Vec<&str> = pip_spec.split('=').collect();
-                        let name = parts[0].trim().to_string();
-                        let version = if parts.len() > 1 { 
-                            Some(parts[1].trim().to_string()) 
-                        } else { 
-                            None 
-                        };
-                        let is_pinned = version.is_some();
-                        
-                        packages.push(crate::models::Package {
-                            name,
-                            version,
-                            build: None,
-                            channel: Some("pip".to_string()),
-                            size: None,
-                            is_pinned,
-                            is_outdated: false,
-                            latest_version: None,
-                        });
-                    }
-                }
+                packages.extend(extract_complex_dependency(complex));
             }
         }
     }
     
     packages
 }
+
+/// Extract packages from a complex (mapping) dependency entry.
+///
+/// A `pip` list is always processed, whether or not the entry carries `name: pip`.
+/// An entry named after anything other than pip (e.g. `{name: cudatoolkit, version: "11.2"}`)
+/// is treated as a conda package, with optional `version`, `build`, `channel`, `md5`, `license` and `size` keys.
+pub fn extract_complex_dependency(complex: &ComplexDependency) -> Vec<Package> {
+    let mut packages = Vec::new();
+    
+    // Handle pip packages
+    if let Some(pip_pkgs) = &complex.pip {
+        for pip_spec in pip_pkgs {
+            if pip_include(pip_spec).is_some() {
+                warn!("Skipping unresolved pip include: {}", pip_spec.trim());
+                continue;
+            }
+            match parse_pip_spec(pip_spec) {
+                Some(package) => packages.push(package),
+                None => warn!("Skipping pip requirement without a package name: {}", pip_spec),
+            }
+        }
+    }
+    
+    // Handle named, non-pip entries as regular packages
+    if let Some(name) = complex.name.as_deref().map(str::trim) {
+        if !name.is_empty() && !name.eq_ignore_ascii_case("pip") {
+            if complex.pip.is_some() {
+                warn!("Dependency entry '{}' has a pip list; treating it as both a package and pip dependencies", name);
+            }
+            
+            let mut package = parse_package_spec(name);
+            if let Some(version) = extra_string(complex, "version") {
+                package.set_declared_version(Some(version));
+            }
+            if let Some(build) = extra_string(complex, "build") {
+                package.build = Some(build);
+            }
+            if let Some(channel) = extra_string(complex, "channel") {
+                package.channel = Some(channel);
+            }
+            package.md5 = extra_string(complex, "md5");
+            package.license = extra_string(complex, "license");
+            package.size = complex.extra.get("size").and_then(serde_yaml::Value::as_u64);
+            
+            packages.push(package);
+        }
+    }
+    
+    packages
+}
+
+/// Read a scalar value from the unmodeled keys of a complex dependency as a string
+fn extra_string(complex: &ComplexDependency, key: &str) -> Option<String> {
+    match complex.extra.get(key)? {
+        serde_yaml::Value::String(s) => Some(s.trim().to_string()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(spec: &str) -> Package {
+        let mut package = Package::new("");
+        parse_name_version_build(spec, &mut package);
+        package
+    }
+
+    #[test]
+    fn decode_text_strips_a_byte_order_mark() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(b"name: bom\ndependencies:\n  - numpy\n");
+        let content = decode_text(bytes).unwrap();
+        assert!(content.starts_with("name: bom"));
+
+        let env = parse_environment_str(&content, FileFormat::Yaml).unwrap();
+        assert_eq!(env.name.as_deref(), Some("bom"));
+    }
+
+    #[test]
+    fn decode_text_reports_offsets_in_the_file_with_a_byte_order_mark() {
+        let bytes = vec![0xEF, 0xBB, 0xBF, b'a', 0xFF];
+        let error = decode_text(bytes).unwrap_err().to_string();
+        assert!(error.contains("0xFF at offset 4"), "{}", error);
+    }
+
+    fn pip_packages(content: &str) -> Vec<Package> {
+        let env = parse_environment_str(content, FileFormat::Yaml).unwrap();
+        extract_packages(&env).into_iter()
+            .filter(|p| p.channel.as_deref() == Some("pip"))
+            .collect()
+    }
+
+    #[test]
+    fn pip_block_with_name_pip() {
+        let packages = pip_packages("dependencies:\n  - python=3.11\n  - name: pip\n    pip:\n      - requests==2.31.0\n");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "requests");
+        assert_eq!(packages[0].version.as_deref(), Some("2.31.0"));
+    }
+
+    #[test]
+    fn pip_block_without_name() {
+        let packages = pip_packages("dependencies:\n  - python=3.11\n  - pip\n  - pip:\n      - requests==2.31.0\n      - flask\n");
+        let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["requests", "flask"]);
+    }
+
+    #[test]
+    fn parses_name_version_and_build() {
+        let package = parse("pkg=1.2.3=bld");
+        assert_eq!(package.name, "pkg");
+        assert_eq!(package.version.as_deref(), Some("1.2.3"));
+        assert_eq!(package.build.as_deref(), Some("bld"));
+        assert!(package.is_pinned());
+
+        let package = parse("pkg=1.2.3");
+        assert_eq!(package.name, "pkg");
+        assert_eq!(package.version.as_deref(), Some("1.2.3"));
+        assert_eq!(package.build, None);
+        assert!(package.is_pinned());
+
+        let package = parse("pkg");
+        assert_eq!(package.name, "pkg");
+        assert_eq!(package.version, None);
+        assert_eq!(package.build, None);
+        assert_eq!(package.pin_status, PinStatus::Unpinned);
+    }
+
+    #[test]
+    fn double_equals_and_space_separated_specs_pin_like_a_single_equals() {
+        assert_eq!(parse("pkg==1.2.3").version.as_deref(), Some("1.2.3"));
+        let package = parse("pkg 1.2.3 bld");
+        assert_eq!(package.version.as_deref(), Some("1.2.3"));
+        assert_eq!(package.build.as_deref(), Some("bld"));
+    }
+
+    #[test]
+    fn operator_specs_keep_the_constraint_without_a_version() {
+        let package = parse("numpy>=1.21");
+        assert_eq!(package.name, "numpy");
+        assert_eq!(package.version, None);
+        assert_eq!(package.build, None);
+        assert_eq!(package.constraint.as_deref(), Some(">=1.21"));
+        assert_eq!(package.pin_status, PinStatus::Range);
+
+        let package = parse("numpy >=1.21,<2");
+        assert_eq!(package.version, None);
+        assert_eq!(package.constraint.as_deref(), Some(">=1.21,<2"));
+    }
+
+    #[test]
+    fn wildcard_specs_are_constraints() {
+        for spec in ["numpy 1.21.*", "numpy=1.21.*"] {
+            let package = parse(spec);
+            assert_eq!(package.version, None, "{}", spec);
+            assert_eq!(package.constraint.as_deref(), Some("1.21.*"), "{}", spec);
+            assert!(!package.is_pinned(), "{}", spec);
+        }
+    }
+}
@@ -1,15 +1,379 @@
 use cached::proc_macro::cached;
-use log::{debug, info};
+use indicatif::ProgressBar;
+use log::{debug, info, warn};
 use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::conda_api::PackageInfo;
+use crate::conda_api::{LookupOptions, PackageInfo};
 use crate::models::Package;
 
-/// Enriches package information in parallel using rayon
-pub fn enrich_packages_parallel(packages: &mut Vec<Package>) -> anyhow::Result<()> {
+/// Controls how lookups use the package metadata cache. Reads and writes are
+/// independent so results can be refreshed while still keeping the cache warm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Serve lookups from the cache when an entry exists
+    pub read: bool,
+    /// Store fetched results in the cache
+    pub write: bool,
+}
+
+impl CachePolicy {
+    /// Read from and write to the cache (the default)
+    pub const DEFAULT: CachePolicy = CachePolicy { read: true, write: true };
+    /// Always fetch fresh data, but still store it for later lookups (`--refresh`)
+    pub const REFRESH: CachePolicy = CachePolicy { read: false, write: true };
+    /// Bypass the cache entirely (`--no-cache`)
+    pub const DISABLED: CachePolicy = CachePolicy { read: false, write: false };
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::DEFAULT
+    }
+}
+
+/// Directory for caches persisted across runs (e.g. `~/.cache/conda-env-inspect` on Linux)
+pub fn cache_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("conda-env-inspect"))
+}
+
+/// The on-disk cache shared by all cached lookups that persist across runs (dependency
+/// lookups, the Safety DB). Latest versions and sizes are only cached for a single run
+#[derive(Debug, Clone)]
+pub struct CacheManager {
+    dir: PathBuf,
+}
+
+/// A file in the on-disk cache
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheFileInfo {
+    /// File name within the cache directory
+    pub name: String,
+    /// Number of entries (top-level keys or items), if the file is a JSON object or list
+    pub entries: Option<usize>,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// What the on-disk cache holds (`cache info`)
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheInfo {
+    /// Cache directory
+    pub location: PathBuf,
+    /// Cached files, by name
+    pub files: Vec<CacheFileInfo>,
+    /// Total size in bytes of the cached files
+    pub total_size: u64,
+}
+
+impl CacheManager {
+    /// The cache in the platform's cache directory, or `None` if it can't be determined
+    pub fn new() -> Option<Self> {
+        cache_dir().map(|dir| CacheManager { dir })
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of a cache file, e.g. `dependencies.json`
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// List the cached files with their entry counts and sizes. A missing cache directory is empty
+    pub fn info(&self) -> anyhow::Result<CacheInfo> {
+        let mut files = Vec::new();
+        if self.dir.is_dir() {
+            for entry in std::fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                let entries = std::fs::read_to_string(entry.path()).ok()
+                    .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                    .and_then(|json| match json {
+                        serde_json::Value::Object(map) => Some(map.len()),
+                        serde_json::Value::Array(items) => Some(items.len()),
+                        _ => None,
+                    });
+                files.push(CacheFileInfo {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    entries,
+                    size: metadata.len(),
+                });
+            }
+        }
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total_size = files.iter().map(|file| file.size).sum();
+        Ok(CacheInfo { location: self.dir.clone(), files, total_size })
+    }
+
+    /// Delete everything in the cache directory, returning how many bytes were freed
+    pub fn clear(&self) -> anyhow::Result<u64> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        let freed = self.info()?.total_size;
+        std::fs::remove_dir_all(&self.dir)
+            .map_err(|e| anyhow::anyhow!("Failed to remove cache directory {:?}: {}", self.dir, e))?;
+        info!("Cleared cache {:?}", self.dir);
+        Ok(freed)
+    }
+}
+
+/// Run a lookup according to the cache policy: through the cache, by refreshing the
+/// cache entry (fetch and store), or bypassing the cache entirely
+fn with_cache_policy<T>(
+    policy: CachePolicy,
+    cached: impl FnOnce() -> T,
+    refresh: impl FnOnce() -> T,
+    uncached: impl FnOnce() -> T,
+) -> T {
+    match policy {
+        CachePolicy { read: true, .. } => cached(),
+        CachePolicy { read: false, write: true } => refresh(),
+        CachePolicy { read: false, write: false } => uncached(),
+    }
+}
+
+/// Get the latest version of a package and its source, honoring the cache policy.
+/// The package's declared `channel` is queried before the default channels
+pub fn latest_version(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> anyhow::Result<(String, &'static str)> {
+    with_cache_policy(
+        lookup.cache,
+        || get_latest_version_cached(name, channel, lookup),
+        || get_latest_version_cached_prime_cache(name, channel, lookup),
+        || crate::conda_api::get_latest_version_with_source(name, channel, lookup),
+    )
+}
+
+/// Get the size of a package and its source, honoring the cache policy
+pub fn package_size(name: &str, lookup: &LookupOptions) -> anyhow::Result<(u64, &'static str)> {
+    with_cache_policy(
+        lookup.cache,
+        || get_package_size_cached(name, lookup),
+        || get_package_size_cached_prime_cache(name, lookup),
+        || crate::conda_api::get_package_size_with_source(name, lookup),
+    )
+}
+
+/// Get the compressed download size of a package's archive, honoring the cache policy
+pub fn package_download_size(
+    name: &str,
+    version: Option<&str>,
+    channel: Option<&str>,
+    platform: Option<&str>,
+    lookup: &LookupOptions,
+) -> anyhow::Result<u64> {
+    with_cache_policy(
+        lookup.cache,
+        || get_package_download_size_cached(name, version, channel, platform, lookup),
+        || get_package_download_size_cached_prime_cache(name, version, channel, platform, lookup),
+        || crate::conda_api::get_package_download_size(name, version, channel, platform, lookup),
+    )
+}
+
+/// File in the cache directory that keeps dependency lookups across runs
+const DEPENDENCY_CACHE_FILE: &str = "dependencies.json";
+/// How long a dependency lookup stored on disk stays valid
+const DEPENDENCY_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A dependency lookup stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedDependencies {
+    /// When the lookup was made, in seconds since the Unix epoch
+    fetched_at: u64,
+    dependencies: Vec<String>,
+}
+
+impl CachedDependencies {
+    /// Entries past the TTL are ignored, except offline where any stored lookup beats none
+    fn is_usable(&self, now: u64, offline: bool) -> bool {
+        offline || now.saturating_sub(self.fetched_at) < DEPENDENCY_CACHE_TTL.as_secs()
+    }
+}
+
+/// Dependency lookups keyed by `name:version:channel`, loaded from disk on first use
+#[derive(Debug, Default)]
+struct DependencyDiskCache {
+    entries: HashMap<String, CachedDependencies>,
+    /// Whether entries were added since the cache was loaded
+    dirty: bool,
+}
+
+static DEPENDENCY_DISK_CACHE: OnceLock<Mutex<DependencyDiskCache>> = OnceLock::new();
+
+fn dependency_cache_path() -> Option<PathBuf> {
+    CacheManager::new().map(|cache| cache.file(DEPENDENCY_CACHE_FILE))
+}
+
+fn dependency_disk_cache() -> &'static Mutex<DependencyDiskCache> {
+    DEPENDENCY_DISK_CACHE.get_or_init(|| {
+        let entries = dependency_cache_path()
+            .filter(|path| path.exists())
+            .and_then(|path| {
+                let loaded = std::fs::read_to_string(&path).map_err(|e| e.to_string())
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+                match loaded {
+                    Ok(entries) => {
+                        debug!("Loaded dependency cache from {:?}", path);
+                        Some(entries)
+                    },
+                    Err(e) => {
+                        warn!("Ignoring unreadable dependency cache {:?}: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .unwrap_or_default();
+        Mutex::new(DependencyDiskCache { entries, dirty: false })
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Get the direct dependencies of a package, honoring the cache policy. Lookups are
+/// kept in memory for the run and on disk (keyed by name, version and channel) across runs
+pub fn package_dependencies(name: &str, version: Option<&str>, channel: Option<&str>, lookup: &LookupOptions) -> Vec<String> {
+    let policy = lookup.cache;
+    let key = format!("{}:{}:{}", name, version.unwrap_or("*"), channel.unwrap_or("conda-forge"));
+    
+    if policy.read {
+        if let Ok(cache) = dependency_disk_cache().lock() {
+            if let Some(entry) = cache.entries.get(&key).filter(|entry| entry.is_usable(unix_now(), lookup.offline)) {
+                debug!("Using cached dependencies for {}", key);
+                return entry.dependencies.clone();
+            }
+        }
+    }
+    
+    let dependencies = with_cache_policy(
+        policy,
+        || get_package_dependencies_cached(name, channel, lookup),
+        || get_package_dependencies_cached_prime_cache(name, channel, lookup),
+        || crate::analysis::lookup_package_dependencies(name, channel, lookup),
+    );
+    
+    // An empty result usually means every source failed (e.g. offline), so don't persist it
+    if policy.write && !dependencies.is_empty() {
+        if let Ok(mut cache) = dependency_disk_cache().lock() {
+            cache.entries.insert(key, CachedDependencies {
+                fetched_at: unix_now(),
+                dependencies: dependencies.clone(),
+            });
+            cache.dirty = true;
+        }
+    }
+    
+    dependencies
+}
+
+/// Write dependency lookups made during this run to the on-disk cache, dropping expired entries
+/// (kept offline, like they are for lookups)
+pub fn save_dependency_cache(lookup: &LookupOptions) {
+    let (Some(cache), Some(path)) = (DEPENDENCY_DISK_CACHE.get(), dependency_cache_path()) else {
+        return;
+    };
+    let Ok(mut cache) = cache.lock() else {
+        return;
+    };
+    if !cache.dirty {
+        return;
+    }
+    
+    let now = unix_now();
+    cache.entries.retain(|_, entry| entry.is_usable(now, lookup.offline));
+    
+    match write_dependency_cache(&path, &cache.entries) {
+        Ok(()) => {
+            debug!("Saved {} dependency lookups to {:?}", cache.entries.len(), path);
+            cache.dirty = false;
+        },
+        Err(e) => warn!("Failed to write dependency cache {:?}: {}", path, e),
+    }
+}
+
+fn write_dependency_cache(path: &Path, entries: &HashMap<String, CachedDependencies>) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first so a concurrent run never reads a partial copy
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(entries)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// A pool of `threads` threads for bounding the number of packages processed in parallel.
+/// Parallel lookups (enrichment, outdated checks, dependency and license fetches) run on the
+/// current rayon pool, so calling them inside [`rayon::ThreadPool::install`] caps the number
+/// of simultaneous network requests without touching the global pool
+pub fn thread_pool(threads: usize) -> anyhow::Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to set up a pool of {} threads: {}", threads, e))
+}
+
+/// Per-package progress of a parallel operation. While it runs, the progress bar counts
+/// completed packages (`37/150`); the previous length and position are restored when it is dropped
+pub struct PackageProgress<'a> {
+    bar: Option<&'a ProgressBar>,
+    completed: AtomicU64,
+    restore: (Option<u64>, u64),
+}
+
+impl<'a> PackageProgress<'a> {
+    /// Start counting `total` packages on `bar`, if there is one
+    pub fn new(bar: Option<&'a ProgressBar>, total: usize) -> Self {
+        let restore = bar.map(|bar| (bar.length(), bar.position())).unwrap_or_default();
+        if let Some(bar) = bar {
+            bar.set_length(total as u64);
+            bar.set_position(0);
+        }
+        PackageProgress { bar, completed: AtomicU64::new(0), restore }
+    }
+    
+    /// Record one completed package; safe to call from rayon workers
+    pub fn tick(&self) {
+        let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(bar) = self.bar {
+            bar.set_position(completed);
+        }
+    }
+}
+
+impl Drop for PackageProgress<'_> {
+    fn drop(&mut self) {
+        if let Some(bar) = self.bar {
+            let (length, position) = self.restore;
+            if let Some(length) = length {
+                bar.set_length(length);
+            }
+            bar.set_position(position);
+        }
+    }
+}
+
+/// Enriches package information in parallel using rayon, ticking `progress` once per package
+pub fn enrich_packages_parallel(
+    packages: &mut Vec<Package>,
+    progress: Option<&ProgressBar>,
+    lookup: &LookupOptions,
+) -> anyhow::Result<()> {
     info!("Enriching {} packages in parallel", packages.len());
+    let progress = PackageProgress::new(progress, packages.len());
     
     // Store package information for parallel iteration
     let package_names: Vec<(usize, String, Option<String>)> = packages.iter().enumerate()
@@ -22,15 +386,15 @@ pub fn enrich_packages_parallel(packages: &mut Vec<Package>) -> anyhow::Result<(
     package_names.par_iter()
         .for_each(|(i, name, channel)| {
             // Skip packages without a name or pip packages
-            if name.is_empty() || name.contains('>') {
+            if name.is_empty() || name.contains('>') || channel.as_deref() == Some("pip") {
                 debug!("Skipping package: {}", name);
+                progress.tick();
                 return;
             }
             
             debug!("Enriching package {}/{}: {}", i + 1, package_names.len(), name);
             
-            // Get package info using cached function
-            match get_package_info_cached(name, channel.as_deref()) {
+            match package_info(name, channel.as_deref(), lookup) {
                 Ok(info) => {
                     // Lock the packages for mutation
                     if let Ok(mut packages_guard) = packages_ref.lock() {
@@ -45,25 +409,68 @@ pub fn enrich_packages_parallel(packages: &mut Vec<Package>) -> anyhow::Result<(
                     debug!("Failed to enrich {}: {}", name, e);
                 }
             }
+            progress.tick();
         });
     
     info!("Parallel package enrichment complete");
     Ok(())
 }
 
+/// Get a package's registry metadata, honoring the cache policy
+fn package_info(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> anyhow::Result<PackageInfo> {
+    with_cache_policy(
+        lookup.cache,
+        || get_package_info_cached(name, channel, lookup),
+        || get_package_info_cached_prime_cache(name, channel, lookup),
+        || crate::conda_api::get_package_info(name, channel, lookup),
+    )
+}
+
+/// Iterator over packages that enriches each one (latest version, outdated status, size and
+/// license) only when it is pulled, through the same cache as [`enrich_packages_parallel`].
+/// Packages that already have a latest version, and ones whose lookup fails, are yielded as
+/// they are. Created by [`EnvironmentAnalysis::enriched_packages`](crate::models::EnvironmentAnalysis::enriched_packages)
+pub struct EnrichedPackages<'a> {
+    packages: std::slice::Iter<'a, Package>,
+    lookup: &'a LookupOptions,
+}
+
+impl<'a> EnrichedPackages<'a> {
+    pub fn new(packages: &'a [Package], lookup: &'a LookupOptions) -> Self {
+        EnrichedPackages { packages: packages.iter(), lookup }
+    }
+}
+
+impl Iterator for EnrichedPackages<'_> {
+    type Item = Package;
+    
+    fn next(&mut self) -> Option<Package> {
+        let mut package = self.packages.next()?.clone();
+        
+        // Skip packages without a name, pip-style specs and packages already enriched
+        if package.name.is_empty() || package.name.contains('>') || package.latest_version.is_some() {
+            return Some(package);
+        }
+        
+        match package_info(&package.name, package.channel.as_deref(), self.lookup) {
+            Ok(info) => update_package_with_info(&mut package, &info),
+            Err(e) => debug!("Failed to enrich {}: {}", package.name, e),
+        }
+        Some(package)
+    }
+    
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.packages.size_hint()
+    }
+}
+
+impl ExactSizeIterator for EnrichedPackages<'_> {}
+
 /// Updates a package with information from PackageInfo
 fn update_package_with_info(package: &mut Package, info: &PackageInfo) {
-    // Check if outdated using semantic versioning
+    // Check if outdated using conda's version ordering
     if let Some(version) = &package.version {
-        if let (Some(current), Some(latest)) = (
-            parse_version_cached(version),
-            parse_version_cached(&info.latest_version)
-        ) {
-            package.is_outdated = current < latest;
-        } else {
-            // Fallback to string comparison
-            package.is_outdated = version != &info.latest_version;
-        }
+        package.is_outdated = crate::version::is_newer(version, &info.latest_version);
     }
     
     // Set latest version
@@ -71,6 +478,29 @@ fn update_package_with_info(package: &mut Package, info: &PackageInfo) {
     
     // Set package size
     package.size = info.size;
+    
+    if info.license.is_some() {
+        package.license = info.license.clone();
+    }
+}
+
+/// Fills in missing licenses from the package metadata API, in parallel.
+/// Packages whose license is already known (e.g. from conda-meta) are not looked up
+pub fn fetch_licenses(packages: &mut [Package], lookup: &LookupOptions) {
+    packages.par_iter_mut()
+        .filter(|package| package.license.is_none() && !package.name.is_empty())
+        .for_each(|package| {
+            let channel = package.channel.clone();
+            let license = if channel.as_deref() == Some("pip") {
+                crate::conda_api::get_pypi_license(&package.name, lookup)
+            } else {
+                package_info(&package.name, channel.as_deref(), lookup).map(|info| info.license)
+            };
+            match license {
+                Ok(license) => package.license = license,
+                Err(e) => debug!("No license found for {}: {}", package.name, e),
+            }
+        });
 }
 
 /// Cached version of the package info retrieval
@@ -80,42 +510,69 @@ fn update_package_with_info(package: &mut Package, info: &PackageInfo) {
     convert = r#"{ format!("{}:{}", name, channel.unwrap_or("conda-forge")) }"#,
     result = true
 )]
-fn get_package_info_cached(name: &str, channel: Option<&str>) -> anyhow::Result<PackageInfo> {
-    crate::conda_api::get_package_info(name, channel)
+fn get_package_info_cached(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> anyhow::Result<PackageInfo> {
+    crate::conda_api::get_package_info(name, channel, lookup)
 }
 
-/// Parse a version string
-fn parse_version_cached(version_str: &str) -> Option<semver::Version> {
-    let normalized = normalize_conda_version(version_str);
-    match semver::Version::parse(&normalized) {
-        Ok(version) => Some(version),
-        Err(_) => None
-    }
+/// Cached version of the latest version lookup
+#[cached(
+    time = 3600, // Cache for 1 hour
+    key = "String",
+    convert = r#"{ format!("{}:{}", name, channel.unwrap_or_default()) }"#,
+    result = true
+)]
+fn get_latest_version_cached(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> anyhow::Result<(String, &'static str)> {
+    crate::conda_api::get_latest_version_with_source(name, channel, lookup)
 }
 
-/// Normalize conda version string to semver compatibility (cached version)
-fn normalize_conda_version(version: &str) -> String {
-    // Handle conda specific version formats
-    let version_without_build;
-    
-    // Remove build string if present
-    if let Some(idx) = version.find('+') {
-        version_without_build = &version[0..idx];
-    } else if let Some(idx) = version.find('-') {
-        if !version.starts_with("0-") {
-            version_without_build = &version[0..idx];
-        } else {
-            version_without_build = version;
-        }
-    } else {
-        version_without_build = version;
-    }
-    
-    // Ensure there are at least major.minor.patch components
-    let parts: Vec<&str> = version_without_build.split('.').collect();
-    match parts.len() {
-        1 => format!("{}.0.0", parts[0]),
-        2 => format!("{}.{}.0", parts[0], parts[1]),
-        _ => version_without_build.to_string(),
+/// Cached version of the package size lookup
+#[cached(
+    time = 3600, // Cache for 1 hour
+    key = "String",
+    convert = r#"{ name.to_string() }"#,
+    result = true
+)]
+fn get_package_size_cached(name: &str, lookup: &LookupOptions) -> anyhow::Result<(u64, &'static str)> {
+    crate::conda_api::get_package_size_with_source(name, lookup)
+} 
+
+/// Cached version of the package download size lookup
+#[cached(
+    time = 3600, // Cache for 1 hour
+    key = "String",
+    convert = r#"{ format!("{}:{}:{}:{}", name, version.unwrap_or_default(), channel.unwrap_or_default(), platform.unwrap_or_default()) }"#,
+    result = true
+)]
+fn get_package_download_size_cached(
+    name: &str,
+    version: Option<&str>,
+    channel: Option<&str>,
+    platform: Option<&str>,
+    lookup: &LookupOptions,
+) -> anyhow::Result<u64> {
+    crate::conda_api::get_package_download_size(name, version, channel, platform, lookup)
+}
+
+/// Cached version of the package dependency lookup. Offline lookups can only fall back to
+/// local data, so they are cached apart from online ones
+#[cached(
+    time = 3600, // Cache for 1 hour
+    key = "String",
+    convert = r#"{ format!("{}:{}:{}", name, channel.unwrap_or("conda-forge"), lookup.offline) }"#
+)]
+fn get_package_dependencies_cached(name: &str, channel: Option<&str>, lookup: &LookupOptions) -> Vec<String> {
+    crate::analysis::lookup_package_dependencies(name, channel, lookup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_pools_are_sized_independently() {
+        let small = thread_pool(2).unwrap();
+        let large = thread_pool(5).unwrap();
+        assert_eq!(small.install(rayon::current_num_threads), 2);
+        assert_eq!(large.install(rayon::current_num_threads), 5);
     }
-} 
\ No newline at end of file
+}
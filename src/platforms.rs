@@ -0,0 +1,132 @@
+use log::debug;
+
+use crate::models::{CondaEnvironment, Package, Priority, Recommendation, RecommendationCategory};
+
+/// Packages that are only available on some operating systems, keyed by package name.
+/// Platforms are conda subdir families: "linux", "osx" and "win".
+const SINGLE_PLATFORM_PACKAGES: &[(&str, &[&str])] = &[
+    ("pywin32", &["win"]),
+    ("pywin32-ctypes", &["win"]),
+    ("pywinpty", &["win"]),
+    ("winpty", &["win"]),
+    ("wincertstore", &["win"]),
+    ("menuinst", &["win"]),
+    ("vs2015_runtime", &["win"]),
+    ("vc", &["win"]),
+    ("ucrt", &["win"]),
+    ("m2w64-toolchain", &["win"]),
+    ("m2w64-gcc-libs", &["win"]),
+    ("appnope", &["osx"]),
+    ("pyobjc-core", &["osx"]),
+    ("pyobjc-framework-cocoa", &["osx"]),
+    ("clang_osx-64", &["osx"]),
+    ("clangxx_osx-64", &["osx"]),
+    ("libgcc-ng", &["linux"]),
+    ("libstdcxx-ng", &["linux"]),
+    ("libgomp", &["linux"]),
+    ("gcc_linux-64", &["linux"]),
+    ("gxx_linux-64", &["linux"]),
+    ("sysroot_linux-64", &["linux"]),
+    ("kernel-headers_linux-64", &["linux"]),
+    ("uvloop", &["linux", "osx"]),
+    ("readline", &["linux", "osx"]),
+    ("ncurses", &["linux", "osx"]),
+    ("cudatoolkit", &["linux", "win"]),
+    ("nccl", &["linux"]),
+];
+
+/// Returns the platforms an environment file declares it targets, from a top-level
+/// `platforms:` list (the conda-lock convention), e.g. `[linux-64, osx-arm64, win-64]`
+pub fn declared_platforms(env: &CondaEnvironment) -> Vec<String> {
+    env.extra.get("platforms")
+        .and_then(|value| value.as_sequence())
+        .map(|platforms| {
+            platforms.iter()
+                .filter_map(|p| p.as_str())
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the single platform an environment file was locked for, if it records one
+/// (explicit lock files do, via their `# platform:` header or package URLs)
+pub fn lock_platform(env: &CondaEnvironment) -> Option<String> {
+    env.extra.get("platform")
+        .and_then(|value| value.as_str())
+        .map(|platform| platform.trim().to_string())
+        .filter(|platform| !platform.is_empty())
+}
+
+/// Conda subdir of the machine this tool runs on (e.g. "linux-64" or "osx-arm64"),
+/// or `None` on a combination conda doesn't build for
+pub fn current_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux-64"),
+        ("linux", "aarch64") => Some("linux-aarch64"),
+        ("linux", "powerpc64") => Some("linux-ppc64le"),
+        ("linux", "s390x") => Some("linux-s390x"),
+        ("linux", "x86") => Some("linux-32"),
+        ("macos", "x86_64") => Some("osx-64"),
+        ("macos", "aarch64") => Some("osx-arm64"),
+        ("windows", "x86_64") => Some("win-64"),
+        ("windows", "aarch64") => Some("win-arm64"),
+        ("windows", "x86") => Some("win-32"),
+        _ => None,
+    }
+}
+
+/// Finds packages that are unavailable on some of the target platforms.
+/// Returns each offending package name with the target platforms it is missing on.
+/// Only meaningful for files targeting more than one platform, so a single target yields nothing
+pub fn find_platform_conflicts(packages: &[Package], platforms: &[String]) -> Vec<(String, Vec<String>)> {
+    if platforms.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut conflicts = Vec::new();
+
+    for package in packages {
+        let name = package.name.to_lowercase();
+        let available = match SINGLE_PLATFORM_PACKAGES.iter().find(|(pkg, _)| *pkg == name) {
+            Some((_, available)) => available,
+            None => continue,
+        };
+
+        let missing: Vec<String> = platforms.iter()
+            .filter(|platform| !available.contains(&platform_family(platform)))
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            debug!("{} is not available on {:?}", package.name, missing);
+            conflicts.push((package.name.clone(), missing));
+        }
+    }
+
+    conflicts
+}
+
+/// Builds a recommendation for each package that is unavailable on some target platforms
+pub fn platform_recommendations(packages: &[Package], platforms: &[String]) -> Vec<Recommendation> {
+    find_platform_conflicts(packages, platforms)
+        .into_iter()
+        .map(|(name, missing)| Recommendation {
+            description: format!(
+                "Package {} is not available on {}; consider moving it to a platform-specific overlay file",
+                name, missing.join(", ")
+            ),
+            value: missing.len().to_string(),
+            details: Some(format!("Target platforms: {}", platforms.join(", "))),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::High,
+        })
+        .collect()
+}
+
+/// Maps a conda subdir such as "linux-64" or "osx-arm64" to its OS family
+fn platform_family(platform: &str) -> &str {
+    let platform = platform.trim();
+    platform.split('-').next().unwrap_or(platform)
+}
@@ -0,0 +1,436 @@
+//! A tiny predicate language for selecting packages, used by `--select`.
+//!
+//! Grammar:
+//!
+//! ```text
+//! expr       := and_expr ( "or" and_expr )*
+//! and_expr   := not_expr ( "and" not_expr )*
+//! not_expr   := "not" not_expr | primary
+//! primary    := "(" expr ")" | field op value | field
+//! op         := "==" | "!=" | "<" | "<=" | ">" | ">=" | "~"
+//! value      := "quoted string" | number [unit] | true | false | bare-word
+//! unit       := B | KB | MB | GB (case-insensitive, 1024-based)
+//! ```
+//!
+//! Fields: `name`, `version`, `channel`, `size`, `outdated`, `pinned`, `source`
+//! (`pip` or `conda`). A bare boolean field such as `outdated` is shorthand for
+//! `outdated == true`. `~` tests whether a text field contains the value; it is an error on
+//! `size`, `outdated` and `pinned`. Versions are compared component-wise, so
+//! `version < 1.10` behaves as expected. Comparisons on a
+//! missing value (e.g. an unknown size) are false, except `!=` which is true.
+//!
+//! Example: `outdated and size > 50MB and channel == "conda-forge"`
+
+use anyhow::Result;
+use std::cmp::Ordering;
+
+use crate::models::Package;
+use crate::version::CondaVersion;
+
+/// A parsed `--select` expression that can be evaluated against packages
+#[derive(Debug, Clone)]
+pub struct Selector {
+    expr: Expr,
+}
+
+impl Selector {
+    /// Parse a selection expression, failing with a message that points at the problem
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(anyhow::anyhow!(
+                "Invalid --select expression: unexpected '{}' at position {}",
+                token.text, token.offset
+            ));
+        }
+
+        Ok(Selector { expr })
+    }
+
+    /// Check whether a package satisfies the expression
+    pub fn matches(&self, package: &Package) -> bool {
+        self.expr.eval(package)
+    }
+
+    /// Whether the expression compares package sizes, which then have to be looked up
+    pub fn uses_size(&self) -> bool {
+        self.expr.uses(Field::Size)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Name,
+    Version,
+    Channel,
+    Size,
+    Outdated,
+    Pinned,
+    Source,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "name" => Some(Field::Name),
+            "version" => Some(Field::Version),
+            "channel" => Some(Field::Channel),
+            "size" => Some(Field::Size),
+            "outdated" => Some(Field::Outdated),
+            "pinned" => Some(Field::Pinned),
+            "source" => Some(Field::Source),
+            _ => None,
+        }
+    }
+
+    fn is_bool(&self) -> bool {
+        matches!(self, Field::Outdated | Field::Pinned)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Text(String),
+    Number(u64),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Value),
+}
+
+impl Expr {
+    fn eval(&self, package: &Package) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(package) || b.eval(package),
+            Expr::And(a, b) => a.eval(package) && b.eval(package),
+            Expr::Not(e) => !e.eval(package),
+            Expr::Compare(field, op, value) => compare(package, *field, *op, value),
+        }
+    }
+
+    fn uses(&self, field: Field) -> bool {
+        match self {
+            Expr::Or(a, b) | Expr::And(a, b) => a.uses(field) || b.uses(field),
+            Expr::Not(e) => e.uses(field),
+            Expr::Compare(compared, _, _) => *compared == field,
+        }
+    }
+}
+
+/// Evaluate a single comparison against a package
+fn compare(package: &Package, field: Field, op: Op, value: &Value) -> bool {
+    let ordering = match field {
+        Field::Outdated | Field::Pinned => {
+            let actual = if field == Field::Outdated { package.is_outdated } else { package.is_pinned() };
+            let expected = match value {
+                Value::Bool(b) => *b,
+                _ => return false,
+            };
+            Some(actual.cmp(&expected))
+        },
+        Field::Size => {
+            let expected = match value {
+                Value::Number(n) => *n,
+                _ => return false,
+            };
+            package.size.map(|size| size.cmp(&expected))
+        },
+        Field::Version => {
+            let expected = value_text(value);
+            if op == Op::Contains {
+                return package.version.as_deref().is_some_and(|v| v.contains(&expected));
+            }
+            // Versions that aren't valid conda versions compare like missing ones
+            package.version.as_deref()
+                .and_then(CondaVersion::parse)
+                .zip(CondaVersion::parse(&expected))
+                .map(|(actual, expected)| actual.cmp(&expected))
+        },
+        Field::Name | Field::Channel | Field::Source => {
+            let actual = match field {
+                Field::Name => Some(package.name.to_lowercase()),
+                Field::Channel => package.channel.as_ref().map(|c| c.to_lowercase()),
+                _ => Some(if package.channel.as_deref() == Some("pip") { "pip" } else { "conda" }.to_string()),
+            };
+            let expected = value_text(value).to_lowercase();
+            if op == Op::Contains {
+                return actual.is_some_and(|a| a.contains(&expected));
+            }
+            actual.map(|a| a.cmp(&expected))
+        },
+    };
+
+    match ordering {
+        Some(ordering) => match op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+            Op::Contains => false,
+        },
+        // Missing values only satisfy "not equal"
+        None => op == Op::Ne,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Text(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Op(Op),
+    Str(String),
+    Word(String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    offset: usize,
+}
+
+/// Split an expression into tokens
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let (kind, len) = match (c, next) {
+            ('(', _) => (TokenKind::LParen, 1),
+            (')', _) => (TokenKind::RParen, 1),
+            ('=', Some('=')) => (TokenKind::Op(Op::Eq), 2),
+            ('!', Some('=')) => (TokenKind::Op(Op::Ne), 2),
+            ('<', Some('=')) => (TokenKind::Op(Op::Le), 2),
+            ('>', Some('=')) => (TokenKind::Op(Op::Ge), 2),
+            ('<', _) => (TokenKind::Op(Op::Lt), 1),
+            ('>', _) => (TokenKind::Op(Op::Gt), 1),
+            ('~', _) => (TokenKind::Op(Op::Contains), 1),
+            ('"', _) | ('\'', _) => {
+                let end = chars[i + 1..].iter().position(|&(_, ch)| ch == c).ok_or_else(|| {
+                    anyhow::anyhow!("Invalid --select expression: unterminated string at position {}", offset)
+                })?;
+                let text: String = chars[i + 1..i + 1 + end].iter().map(|&(_, ch)| ch).collect();
+                (TokenKind::Str(text), end + 2)
+            },
+            _ if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '*' | '/') => {
+                let len = chars[i..].iter()
+                    .take_while(|&&(_, ch)| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.' | ':' | '*' | '/'))
+                    .count();
+                let text: String = chars[i..i + len].iter().map(|&(_, ch)| ch).collect();
+                (TokenKind::Word(text), len)
+            },
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Invalid --select expression: unexpected character '{}' at position {}",
+                    c, offset
+                ));
+            }
+        };
+
+        let text: String = chars[i..i + len].iter().map(|&(_, ch)| ch).collect();
+        tokens.push(Token { kind, text, offset });
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over the token stream
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token { kind: TokenKind::Word(w), .. }) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn end_error(&self, expected: &str) -> anyhow::Error {
+        anyhow::anyhow!("Invalid --select expression: expected {} but the expression ended", expected)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_not()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let token = self.next().ok_or_else(|| self.end_error("a field or '('"))?;
+
+        match token.kind {
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => Ok(expr),
+                    Some(other) => Err(anyhow::anyhow!(
+                        "Invalid --select expression: expected ')' at position {} but found '{}'",
+                        other.offset, other.text
+                    )),
+                    None => Err(self.end_error("')'")),
+                }
+            },
+            TokenKind::Word(word) => {
+                let field = Field::from_name(&word).ok_or_else(|| anyhow::anyhow!(
+                    "Invalid --select expression: unknown field '{}' at position {} (available: name, version, channel, size, outdated, pinned, source)",
+                    word, token.offset
+                ))?;
+
+                let op = match self.peek() {
+                    Some(Token { kind: TokenKind::Op(op), .. }) => *op,
+                    _ if field.is_bool() => return Ok(Expr::Compare(field, Op::Eq, Value::Bool(true))),
+                    Some(other) => return Err(anyhow::anyhow!(
+                        "Invalid --select expression: expected an operator after '{}' at position {} but found '{}'",
+                        word, other.offset, other.text
+                    )),
+                    None => return Err(self.end_error(&format!("an operator after '{}'", word))),
+                };
+                if op == Op::Contains && (field.is_bool() || field == Field::Size) {
+                    let offset = self.peek().map_or(0, |token| token.offset);
+                    return Err(anyhow::anyhow!(
+                        "Invalid --select expression: '~' only applies to text fields (name, version, channel, source), not '{}' at position {}",
+                        word, offset
+                    ));
+                }
+                self.pos += 1;
+
+                let value_token = self.next().ok_or_else(|| self.end_error("a value"))?;
+                let value = parse_value(field, &value_token)?;
+                Ok(Expr::Compare(field, op, value))
+            },
+            _ => Err(anyhow::anyhow!(
+                "Invalid --select expression: unexpected '{}' at position {}",
+                token.text, token.offset
+            )),
+        }
+    }
+}
+
+/// Interpret a value token according to the type of the field it is compared with
+fn parse_value(field: Field, token: &Token) -> Result<Value> {
+    let text = match &token.kind {
+        TokenKind::Str(s) | TokenKind::Word(s) => s.clone(),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Invalid --select expression: expected a value at position {} but found '{}'",
+                token.offset, token.text
+            ));
+        }
+    };
+
+    match field {
+        Field::Outdated | Field::Pinned => match text.to_lowercase().as_str() {
+            "true" | "yes" => Ok(Value::Bool(true)),
+            "false" | "no" => Ok(Value::Bool(false)),
+            _ => Err(anyhow::anyhow!(
+                "Invalid --select expression: expected true or false at position {} but found '{}'",
+                token.offset, text
+            )),
+        },
+        Field::Size => parse_size(&text).map(Value::Number).ok_or_else(|| anyhow::anyhow!(
+            "Invalid --select expression: invalid size '{}' at position {} (e.g. 500KB, 50MB, 1.5GB)",
+            text, token.offset
+        )),
+        _ => Ok(Value::Text(text)),
+    }
+}
+
+/// Parse a size such as `50MB` or `1.5GB` into bytes (1024-based units)
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim().to_uppercase();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier: u64 = match unit {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    Some((number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_is_an_error_on_numeric_and_boolean_fields() {
+        for input in ["size ~ 5", "outdated ~ true", "pinned ~ yes"] {
+            assert!(Selector::parse(input).is_err(), "{}", input);
+        }
+        assert!(Selector::parse("name ~ num and version ~ 1.2").is_ok());
+    }
+}
@@ -1,143 +1,598 @@
 use anyhow::{Context, Result};
-use log::{debug, warn};
+use indicatif::ProgressBar;
+use log::{debug, info, warn};
 use petgraph::Direction;
 use rayon::prelude::*;
 use regex::Regex;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use crate::analysis;
-use crate::conda_api;
-use crate::models::{EnvironmentAnalysis, Package, Recommendation};
-use crate::parsers;
+use crate::conda_api::LookupOptions;
+use crate::models::{CondaEnvironment, DownloadSize, EnrichmentReport, EnvironmentAnalysis, Package, PinStatus, Priority, Recommendation, RecommendationCategory, ReportMetadata};
+use crate::parsers::{self, FileFormat};
+use crate::performance;
+use crate::platforms;
+use crate::select::Selector;
+use crate::version;
 use crate::advanced_analysis::AdvancedDependencyGraph;
 
+/// Size assumed for a package whose size can't be looked up, with `--estimate-sizes`
+const ESTIMATED_PACKAGE_SIZE: u64 = 5_000_000;
+
+/// Whether the user asked for no colors, with `no_color` (`--no-color`) or a non-empty
+/// `NO_COLOR` environment variable (see no-color.org)
+pub fn color_disabled(no_color: bool) -> bool {
+    no_color || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Whether output written to stdout should be colored: not disabled by the user, and
+/// stdout is a terminal
+pub fn should_use_color(no_color: bool) -> bool {
+    !color_disabled(no_color) && std::io::stdout().is_terminal()
+}
+
+/// Options of one analysis. Every analysis function takes its own, so callers analyzing
+/// several environments at once (e.g. a web service) can each use different settings
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    /// Check for outdated packages
+    pub check_outdated: bool,
+    /// Flag pinned packages
+    pub flag_pinned: bool,
+    /// Treat the environment as a `conda env export --from-history` spec, for files the
+    /// detection heuristic misses
+    pub from_history: bool,
+    /// Look up package sizes. Outdated checks and `estimate_sizes` always do; otherwise only
+    /// sizes already known (e.g. from an installed environment's metadata) are reported
+    pub lookup_sizes: bool,
+    /// Give packages whose size can't be looked up a placeholder estimate. Estimated sizes are
+    /// flagged with `size_estimated` and never presented as real data
+    pub estimate_sizes: bool,
+    /// Packages to leave out of the analysis (`--include` / `--exclude`)
+    pub filters: PackageFilters,
+    /// Packages to keep once their outdated status and size are known (`--select`)
+    pub select: Option<Selector>,
+    /// 1-based document to read from multi-document YAML files; `None` picks it automatically
+    pub document: Option<usize>,
+    /// How packages are looked up (offline mode)
+    pub lookup: LookupOptions,
+}
+
+/// Name globs from `--include` and `--exclude`, applied to the packages before enrichment.
+/// The default keeps every package
+#[derive(Debug, Clone, Default)]
+pub struct PackageFilters {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl PackageFilters {
+    /// Keep the packages matching one of the `include` globs (all of them when empty) and none
+    /// of the `exclude` globs. Patterns with `::` match `channel::name`
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<glob::Pattern>> {
+            patterns.iter()
+                .map(|pattern| glob::Pattern::new(pattern)
+                    .with_context(|| format!("Invalid package pattern: {}", pattern)))
+                .collect()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+    
+    /// Drops the packages left out by the filters
+    fn apply(&self, packages: Vec<Package>) -> Vec<Package> {
+        let (include, exclude) = (&self.include, &self.exclude);
+        if include.is_empty() && exclude.is_empty() {
+            return packages;
+        }
+        
+        let total = packages.len();
+        let kept: Vec<Package> = packages.into_iter()
+            .filter(|package| include.is_empty() || include.iter().any(|pattern| package_pattern_matches(pattern, package)))
+            .filter(|package| !exclude.iter().any(|pattern| package_pattern_matches(pattern, package)))
+            .collect();
+        info!("Package filters kept {} of {} packages", kept.len(), total);
+        kept
+    }
+}
+
+/// Whether a package name glob matches the package, as `channel::name` for patterns with `::`
+fn package_pattern_matches(pattern: &glob::Pattern, package: &Package) -> bool {
+    if pattern.as_str().contains("::") {
+        let channel = package.channel.as_deref().unwrap_or("defaults");
+        pattern.matches(&format!("{}::{}", channel, package.name))
+    } else {
+        pattern.matches(&package.name)
+    }
+}
+
+/// Formats the total size of an analysis, noting when it includes estimated package sizes
+pub fn format_total_size(analysis: &EnvironmentAnalysis, size: u64) -> String {
+    if analysis.has_estimated_sizes() {
+        format!("{} (partially estimated)", format_size(size))
+    } else {
+        format_size(size)
+    }
+}
+
+/// Formats an environment's download size, noting how many packages it leaves out
+pub fn format_download_size(download: &DownloadSize) -> String {
+    if download.missing.is_empty() {
+        format!("{} (compressed)", format_size(download.bytes))
+    } else {
+        format!("{} (compressed, {} packages not counted)", format_size(download.bytes), download.missing.len())
+    }
+}
+
 /// Analyzes a Conda environment file and returns the analysis results
 pub fn analyze_environment<P: AsRef<Path>>(
     file_path: P,
-    should_check_outdated: bool,
-    flag_pinned: bool,
+    options: &AnalysisOptions,
 ) -> Result<EnvironmentAnalysis> {
-    // Parse the environment file
-    let env = parsers::parse_environment_file(&file_path)?;
-    
-    // Process and enrich all packages
-    let mut packages = extract_packages_from_environment(&env)?;
-    
-    // Flag pinned packages if requested
-    if flag_pinned {
-        for package in &mut packages {
-            package.is_pinned = is_pinned_package(&package.name, &env)?;
-        }
-    }
+    // Parsed from the file (not its contents) so pip `-r`/`-c` includes resolve next to it
+    let env = parsers::parse_environment_file_with_document(&file_path, options.document, &options.lookup)?;
     
-    // Check for outdated packages if requested
-    if should_check_outdated {
-        for package in &mut packages {
-            if let Some((is_outdated, latest)) = check_outdated(&package.name, package.version.as_deref()) {
-                package.is_outdated = is_outdated;
-                package.latest_version = latest;
-            }
-        }
+    analyze_parsed_environment(&env, options)
+        .with_context(|| format!("Failed to analyze environment file: {:?}", file_path.as_ref()))
+}
+
+/// A custom recommendation rule that runs alongside the built-in ones, e.g. "flag any package
+/// from an internal channel". Closures taking the analysis are rules too
+pub trait RecommendationRule: Send + Sync {
+    /// Recommendations for an analyzed environment; empty when the rule has nothing to report
+    fn evaluate(&self, analysis: &EnvironmentAnalysis) -> Vec<Recommendation>;
+}
+
+impl<F> RecommendationRule for F
+where
+    F: Fn(&EnvironmentAnalysis) -> Vec<Recommendation> + Send + Sync,
+{
+    fn evaluate(&self, analysis: &EnvironmentAnalysis) -> Vec<Recommendation> {
+        self(analysis)
     }
+}
+
+/// Runs custom rules over an analysis and adds their recommendations after the built-in ones.
+/// Each rule sees the analysis as it was before any rule ran
+pub fn apply_recommendation_rules(analysis: &mut EnvironmentAnalysis, rules: &[&dyn RecommendationRule]) {
+    let custom: Vec<Recommendation> = rules.iter()
+        .flat_map(|rule| rule.evaluate(analysis))
+        .collect();
+    debug!("Custom rules added {} recommendations", custom.len());
+    analysis.recommendations.extend(custom);
+}
+
+/// Analyzes a Conda environment file like [`analyze_environment`], then runs the given custom
+/// recommendation rules alongside the built-in recommendations
+pub fn analyze_environment_with_rules<P: AsRef<Path>>(
+    file_path: P,
+    options: &AnalysisOptions,
+    rules: &[&dyn RecommendationRule],
+) -> Result<EnvironmentAnalysis> {
+    let mut analysis = analyze_environment(file_path, options)?;
+    apply_recommendation_rules(&mut analysis, rules);
+    Ok(analysis)
+}
+
+/// Analyzes environment file contents held in memory (e.g. an upload) without touching disk
+pub fn analyze_environment_from_str(
+    content: &str,
+    format: FileFormat,
+    options: &AnalysisOptions,
+) -> Result<EnvironmentAnalysis> {
+    let env = parsers::parse_environment_str_with_document(content, format, options.document)
+        .with_context(|| format!("Failed to parse {} content", format))?;
     
-    // Get package sizes
-    let total_size = get_packages_sizes(&mut packages);
-    
-    // Count pinned and outdated packages
-    let pinned_count = packages.iter().filter(|p| p.is_pinned).count();
-    let outdated_count = packages.iter().filter(|p| p.is_outdated).count();
-    
-    // Generate simple dependency graph
-    let dependency_graph = analysis::create_dependency_graph(&packages);
+    analyze_parsed_environment(&env, options)
+}
+
+/// Analyzes a Conda environment file without any registry lookups: packages are listed as
+/// declared, without sizes or outdated status, and the recommendations only cover what the
+/// file itself shows. Enrich the packages one at a time with
+/// [`EnvironmentAnalysis::enriched_packages`] rather than all up front like [`analyze_environment`].
+/// The outdated, pinned and size options don't apply, as nothing is looked up
+pub fn analyze_environment_lazy<P: AsRef<Path>>(file_path: P, options: &AnalysisOptions) -> Result<EnvironmentAnalysis> {
+    let env = parsers::parse_environment_file_with_document(&file_path, options.document, &options.lookup)?;
+    let packages = extract_packages_from_environment(&env, &options.filters)?;
     
-    // Generate recommendations
-    let recommendations = generate_simple_recommendations(&packages, pinned_count, outdated_count);
+    let pinned_count = packages.iter().filter(|p| p.is_pinned()).count();
+    let is_history_export = options.from_history || analysis::is_history_export(&packages);
+    let recommendations = generate_simple_recommendations(&packages, pinned_count, 0, is_history_export);
     
     Ok(EnvironmentAnalysis {
+        metadata: Some(ReportMetadata::new(Some(file_path.as_ref().display().to_string()), false, false)),
         name: env.name.clone(),
+        prefix: env.prefix.clone(),
+        platform: platforms::lock_platform(&env),
+        variable_count: env.variables.as_ref().map_or(0, HashMap::len),
         packages,
-        total_size,
+        total_size: None,
+        total_size_with_dependencies: None,
+        download_size: None,
         pinned_count,
-        outdated_count,
+        outdated_count: 0,
         recommendations,
+        check_failures: Vec::new(),
+        enrichment: None,
+        graph_metrics: None,
+        is_history_export,
     })
 }
 
+/// Analyzes an already parsed Conda environment and returns the analysis results
+pub fn analyze_parsed_environment(
+    env: &CondaEnvironment,
+    options: &AnalysisOptions,
+) -> Result<EnvironmentAnalysis> {
+    analyze_packages(env, options, false, None)
+}
+
 /// Analyzes a Conda environment file using parallel processing for better performance
 pub fn analyze_environment_parallel<P: AsRef<Path>>(
     file_path: P,
-    should_check_outdated: bool,
-    flag_pinned: bool,
+    options: &AnalysisOptions,
 ) -> Result<EnvironmentAnalysis> {
     // Parse the environment file
-    let env = parsers::parse_environment_file(&file_path)?;
+    let env = parsers::parse_environment_file_with_document(&file_path, options.document, &options.lookup)?;
     
-    // Process and enrich all packages
-    let mut packages = extract_packages_from_environment(&env)?;
+    analyze_parsed_environment_parallel(&env, options)
+}
+
+/// Analyzes an already parsed Conda environment using parallel processing
+pub fn analyze_parsed_environment_parallel(
+    env: &CondaEnvironment,
+    options: &AnalysisOptions,
+) -> Result<EnvironmentAnalysis> {
+    analyze_parsed_environment_parallel_with_progress(env, options, None)
+}
+
+/// Analyzes an already parsed Conda environment using parallel processing, ticking
+/// `progress` once per package as the outdated checks complete
+pub fn analyze_parsed_environment_parallel_with_progress(
+    env: &CondaEnvironment,
+    options: &AnalysisOptions,
+    progress: Option<&ProgressBar>,
+) -> Result<EnvironmentAnalysis> {
+    analyze_packages(env, options, true, progress)
+}
+
+/// Builds the analysis of an environment's packages: runs the outdated checks if requested
+/// (concurrently when `parallel` is set, ticking `progress` once per package), then looks up
+/// sizes and derives the counts, enrichment report and recommendations
+fn analyze_packages(
+    env: &CondaEnvironment,
+    options: &AnalysisOptions,
+    parallel: bool,
+    progress: Option<&ProgressBar>,
+) -> Result<EnvironmentAnalysis> {
+    let should_check_outdated = options.check_outdated;
     
-    // Flag pinned packages if requested
-    if flag_pinned {
-        packages.par_iter_mut().for_each(|package| {
-            package.is_pinned = is_pinned_package(&package.name, &env).unwrap_or(false);
-        });
-    }
+    // Process and enrich all packages
+    let mut packages = extract_packages_from_environment(env, &options.filters)?;
     
     // Check for outdated packages if requested
-    if should_check_outdated {
-        packages.par_iter_mut().for_each(|package| {
-            if let Some((is_outdated, latest)) = check_outdated(&package.name, package.version.as_deref()) {
+    let checks: Vec<Result<Option<&'static str>, String>> = if should_check_outdated {
+        let progress = performance::PackageProgress::new(progress, packages.len());
+        let check = |package: &mut Package| {
+            let checked = check_outdated(&package.name, package.channel.as_deref(), package.version.as_deref(), &options.lookup);
+            progress.tick();
+            checked.map(|checked| checked.map(|(is_outdated, latest, source)| {
                 package.is_outdated = is_outdated;
-                package.latest_version = latest;
-            }
-        });
+                package.latest_version = Some(latest);
+                source
+            }))
+        };
+        if parallel {
+            packages.par_iter_mut().map(check).collect()
+        } else {
+            packages.iter_mut().map(check).collect()
+        }
+    } else {
+        vec![Ok(None); packages.len()]
+    };
+    let mut check_failures: Vec<(String, String)> = packages.iter().zip(&checks)
+        .filter_map(|(package, check)| check.as_ref().err().map(|reason| (package.name.clone(), reason.clone())))
+        .collect();
+    let mut version_sources: Vec<Option<&'static str>> = checks.into_iter()
+        .map(|check| check.ok().flatten())
+        .collect();
+    
+    // Get package sizes, when the output shows them or the enrichment report counts their sources
+    let mut size_sources = vec![None; packages.len()];
+    if options.lookup_sizes || options.estimate_sizes || should_check_outdated {
+        get_packages_sizes(&mut packages, &mut size_sources, options.estimate_sizes, &options.lookup);
     }
     
-    // Get package sizes
-    let total_size = get_packages_sizes(&mut packages);
+    // Apply the selection before anything is derived from the packages, so the totals,
+    // enrichment report and recommendations only describe the selected ones
+    if let Some(selector) = &options.select {
+        let before = packages.len();
+        let selected: Vec<bool> = packages.iter().map(|p| selector.matches(p)).collect();
+        retain_selected(&mut packages, &selected);
+        retain_selected(&mut version_sources, &selected);
+        retain_selected(&mut size_sources, &selected);
+        debug!("Selected packages: {} of {} kept", packages.len(), before);
+        
+        let selected: HashSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        check_failures.retain(|(name, _)| selected.contains(name.as_str()));
+    }
+    let total_size = Some(packages.iter().filter_map(|p| p.size).sum()).filter(|&size| size > 0);
+    
+    let enrichment = if should_check_outdated {
+        Some(build_enrichment_report(&packages, &version_sources, &size_sources))
+    } else {
+        None
+    };
     
     // Count pinned and outdated packages
-    let pinned_count = packages.iter().filter(|p| p.is_pinned).count();
+    let pinned_count = packages.iter().filter(|p| p.is_pinned()).count();
     let outdated_count = packages.iter().filter(|p| p.is_outdated).count();
     
-    // Generate simple dependency graph
-    let dependency_graph = analysis::create_dependency_graph(&packages);
+    let is_history_export = options.from_history || analysis::is_history_export(&packages);
     
     // Generate recommendations
-    let recommendations = generate_simple_recommendations(&packages, pinned_count, outdated_count);
+    let recommendations = generate_simple_recommendations(&packages, pinned_count, outdated_count, is_history_export);
     
     Ok(EnvironmentAnalysis {
+        metadata: Some(ReportMetadata::new(None, should_check_outdated, false)),
         name: env.name.clone(),
+        prefix: env.prefix.clone(),
+        platform: platforms::lock_platform(env),
+        variable_count: env.variables.as_ref().map_or(0, HashMap::len),
         packages,
         total_size,
+        total_size_with_dependencies: None,
+        download_size: None,
         pinned_count,
         outdated_count,
         recommendations,
+        check_failures,
+        enrichment,
+        graph_metrics: None,
+        is_history_export,
     })
 }
 
-/// Generate a dependency graph for an environment and save it to a file
-pub fn generate_dependency_graph<P1: AsRef<Path>, P2: AsRef<Path>>(
-    file_path: P1,
-    output_path: P2,
-) -> Result<()> {
-    // Parse the environment file
-    let env = parsers::parse_environment_file(&file_path)?;
+/// Keeps the items whose entry in `selected` is true
+fn retain_selected<T>(items: &mut Vec<T>, selected: &[bool]) {
+    let mut selected = selected.iter();
+    items.retain(|_| selected.next().copied().unwrap_or(false));
+}
+
+/// Unions the analyses of several environment files into one. Packages declared with the
+/// same name and version in several files are listed once, with their metadata merged
+/// across the records, counts and sizes are
+/// recomputed over the union, and each recommendation or check failure is prefixed with
+/// the name of the file it came from
+pub fn combine_analyses(analyses: &[(PathBuf, EnvironmentAnalysis)]) -> EnvironmentAnalysis {
+    let label = |file: &PathBuf| file.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.display().to_string());
+    
+    let mut seen: HashMap<Package, usize> = HashMap::new();
+    let mut packages: Vec<Package> = Vec::new();
+    let mut recommendations = Vec::new();
+    let mut check_failures = Vec::new();
+    for (file, analysis) in analyses {
+        for package in &analysis.packages {
+            match seen.get(package) {
+                Some(&index) => packages[index].merge(package),
+                None => {
+                    seen.insert(package.clone(), packages.len());
+                    packages.push(package.clone());
+                }
+            }
+        }
+        recommendations.extend(analysis.recommendations.iter().map(|rec| Recommendation {
+            description: format!("{}: {}", label(file), rec.description),
+            ..rec.clone()
+        }));
+        check_failures.extend(analysis.check_failures.iter()
+            .map(|(name, reason)| (name.clone(), format!("{}: {}", label(file), reason))));
+    }
+    
+    let total_size: u64 = packages.iter().filter_map(|p| p.size).sum();
+    let platform = analyses.first()
+        .and_then(|(_, first)| first.platform.clone())
+        .filter(|platform| analyses.iter().all(|(_, a)| a.platform.as_ref() == Some(platform)));
+    
+    let inputs: Vec<String> = analyses.iter()
+        .map(|(file, analysis)| analysis.metadata.as_ref()
+            .and_then(|metadata| metadata.input.clone())
+            .unwrap_or_else(|| file.display().to_string()))
+        .collect();
+    let outdated_check = analyses.iter()
+        .all(|(_, a)| a.metadata.as_ref().is_some_and(|metadata| metadata.outdated_check));
+    
+    EnvironmentAnalysis {
+        metadata: Some(ReportMetadata::new(Some(inputs.join(", ")), outdated_check, false)),
+        name: Some("combined".to_string()),
+        prefix: None,
+        platform,
+        variable_count: analyses.iter().map(|(_, a)| a.variable_count).sum(),
+        pinned_count: packages.iter().filter(|p| p.is_pinned()).count(),
+        outdated_count: packages.iter().filter(|p| p.is_outdated).count(),
+        total_size: if total_size > 0 { Some(total_size) } else { None },
+        total_size_with_dependencies: None,
+        download_size: None,
+        packages,
+        recommendations,
+        check_failures,
+        enrichment: None,
+        graph_metrics: None,
+        is_history_export: analyses.iter().all(|(_, a)| a.is_history_export),
+    }
+}
+
+/// Expands the analyzed packages through their dependencies and records the size of the
+/// whole closure in `total_size_with_dependencies`. Dependencies are looked up one level
+/// at a time, with each level fetched in parallel
+pub fn add_transitive_size(analysis: &mut EnvironmentAnalysis, lookup: &LookupOptions) {
+    let declared: HashSet<&str> = analysis.packages.iter().map(|p| p.name.as_str()).collect();
+    let dependency_map = analysis::get_real_package_dependencies(&analysis.packages, lookup);
+    
+    // Virtual packages (e.g. __glibc) aren't installed, so they have no size
+    let is_new = |name: &String, seen: &HashSet<String>| {
+        !name.starts_with("__") && !declared.contains(name.as_str()) && !seen.contains(name)
+    };
+    
+    let mut transitive: HashSet<String> = HashSet::new();
+    let mut frontier: Vec<String> = dependency_map.values().flatten()
+        .map(|dep| crate::parsers::spec_name(dep).to_string())
+        .filter(|name| is_new(name, &transitive))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    
+    while !frontier.is_empty() {
+        let found: Vec<Vec<String>> = frontier.par_iter()
+            .map(|name| match dependency_map.get(name) {
+                Some(deps) => deps.clone(),
+                None => performance::package_dependencies(name, None, None, lookup),
+            })
+            .collect();
+        transitive.extend(frontier);
+        
+        frontier = found.into_iter().flatten()
+            .map(|dep| crate::parsers::spec_name(&dep).to_string())
+            .filter(|name| is_new(name, &transitive))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+    }
+    performance::save_dependency_cache(lookup);
+    
+    let dependency_size: u64 = transitive.par_iter()
+        .filter_map(|name| performance::package_size(name, lookup).ok())
+        .map(|(size, _)| size)
+        .sum();
+    
+    info!("Found {} transitive dependencies ({})", transitive.len(), format_size(dependency_size));
+    analysis.total_size_with_dependencies = Some(analysis.total_size.unwrap_or(0) + dependency_size);
+}
+
+/// Looks up the compressed archive size of every conda package on anaconda.org, in
+/// parallel, and records their sum in `download_size`. Unlike the installed size this
+/// needs no local environment, so it works for a bare spec file. Archives are matched
+/// to the analysis platform (this machine's by default) and each package's version spec
+pub fn add_download_size(analysis: &mut EnvironmentAnalysis, lookup: &LookupOptions) {
+    let platform = analysis.platform.clone()
+        .or_else(|| platforms::current_platform().map(str::to_string));
+    
+    let sizes: Vec<Option<u64>> = analysis.packages.par_iter()
+        .map(|package| {
+            if package.channel.as_deref() == Some("pip") {
+                return None;
+            }
+            match performance::package_download_size(
+                &package.name, package.version.as_deref(), package.channel.as_deref(), platform.as_deref(), lookup) {
+                Ok(size) => Some(size),
+                Err(e) => {
+                    debug!("No download size for {}: {}", package.name, e);
+                    None
+                }
+            }
+        })
+        .collect();
     
-    // Extract packages
-    let packages = parsers::extract_packages(&env);
+    let bytes = sizes.iter().flatten().sum();
+    let missing: Vec<String> = analysis.packages.iter().zip(&sizes)
+        .filter(|(_, size)| size.is_none())
+        .map(|(package, _)| package.name.clone())
+        .collect();
+    if !missing.is_empty() {
+        warn!("Download size unknown for {} packages: {}", missing.len(), missing.join(", "));
+    }
     
+    info!("Download size: {} over {} packages", format_size(bytes), sizes.len() - missing.len());
+    analysis.download_size = Some(DownloadSize { bytes, missing });
+}
+
+/// Generate a dependency graph for the analyzed packages and save it to a file
+pub fn generate_dependency_graph<P: AsRef<Path>>(
+    packages: &[Package],
+    output_path: P,
+    options: &analysis::DotOptions,
+    lookup: &LookupOptions,
+) -> Result<()> {
     // Create dependency graph
-    let graph = analysis::create_dependency_graph(&packages);
+    let graph = analysis::create_dependency_graph(packages, lookup);
     
     // Export graph to DOT format
-    analysis::export_dependency_graph(&graph, output_path)?;
+    analysis::export_dependency_graph(&graph, packages, output_path, options)?;
     
     Ok(())
 }
 
+/// Returns the base file name for artifacts of an analysis: the environment name,
+/// or the environment file stem when the file does not declare one
+pub fn artifact_stem<P: AsRef<Path>>(analysis: &EnvironmentAnalysis, file_path: P) -> String {
+    analysis.name.clone()
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| {
+            file_path.as_ref()
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "environment".to_string())
+}
+
+/// Resolves where an artifact should be written.
+///
+/// An explicit path always wins. Otherwise, if an output directory is given, the artifact
+/// is placed there as `<stem>.<extension>` (creating the directory if needed).
+pub fn resolve_artifact_path(
+    explicit_path: Option<&PathBuf>,
+    output_dir: Option<&PathBuf>,
+    stem: &str,
+    extension: &str,
+) -> Result<Option<PathBuf>> {
+    if let Some(path) = explicit_path {
+        return Ok(Some(path.clone()));
+    }
+    
+    match output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create output directory: {:?}", dir))?;
+            Ok(Some(dir.join(format!("{}.{}", stem, extension))))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Current UTC time as an RFC 3339 timestamp (e.g. `2024-05-01T12:30:00Z`)
+pub fn utc_timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, time) = (secs / 86_400, secs % 86_400);
+    
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3_600, time % 3_600 / 60, time % 60
+    )
+}
+
 /// Formats a file size to a human-readable string
 pub fn format_size(size: u64) -> String {
     const KB: u64 = 1024;
@@ -167,6 +622,8 @@ pub fn generate_recommendations(packages: &[Package], dependency_graph: &Advance
                     package.version.as_deref().unwrap_or("unknown"), 
                     package.latest_version.as_deref().unwrap_or("unknown"))),
                 value: "1.0".to_string(),
+                category: RecommendationCategory::Update,
+                priority: Priority::Medium,
             });
         }
 
@@ -177,6 +634,8 @@ pub fn generate_recommendations(packages: &[Package], dependency_graph: &Advance
                 description: format!("Potential security vulnerabilities in {}", package.name),
                 details: Some("Significantly outdated packages may contain security vulnerabilities".to_string()),
                 value: "2.0".to_string(),
+                category: RecommendationCategory::Security,
+                priority: Priority::Medium,
             });
         }
 
@@ -186,10 +645,16 @@ pub fn generate_recommendations(packages: &[Package], dependency_graph: &Advance
                 description: format!("Package {} is deprecated", package.name),
                 details: Some("Consider finding an alternative package".to_string()),
                 value: "1.0".to_string(),
+                category: RecommendationCategory::Update,
+                priority: Priority::Medium,
             });
         }
     }
 
+    // Packages without any version constraint resolve differently every time the environment is created
+    recommendations.extend(unpinned_recommendation(packages));
+    recommendations.extend(prerelease_recommendation(packages));
+    
     // Analyze dependency graph for unused dependencies
     let unused = find_unused_dependencies(dependency_graph);
     if !unused.is_empty() {
@@ -197,6 +662,8 @@ pub fn generate_recommendations(packages: &[Package], dependency_graph: &Advance
             description: "Unused dependencies detected".to_string(),
             details: Some(format!("Consider removing: {}", unused.join(", "))),
             value: format!("{}.0", unused.len()),
+            category: RecommendationCategory::SizeOptimization,
+            priority: Priority::Low,
         });
     }
 
@@ -228,11 +695,13 @@ fn find_unused_dependencies(graph: &AdvancedDependencyGraph) -> Vec<String> {
     unused
 }
 
-// Generate simple text recommendations instead of structured Recommendation objects
+// Generate simple text recommendations instead of structured Recommendation objects.
+// A `--from-history` export is loosely pinned by design, so its unpinned packages aren't flagged
 fn generate_simple_recommendations(
     packages: &[Package], 
     pinned_count: usize, 
-    outdated_count: usize
+    outdated_count: usize,
+    is_history_export: bool,
 ) -> Vec<Recommendation> {
     let mut recommendations = Vec::new();
     
@@ -244,6 +713,8 @@ fn generate_simple_recommendations(
                 outdated_count, percent as u32),
             value: format!("{}", outdated_count),
             details: None,
+            category: RecommendationCategory::Update,
+            priority: Priority::Medium,
         });
         
         // Add specific update recommendations for each outdated package
@@ -253,11 +724,80 @@ fn generate_simple_recommendations(
                     description: format!("Update {} from {} to {}", package.name, version, latest),
                     value: "1.0".to_string(),
                     details: None,
+                    category: RecommendationCategory::Update,
+                    priority: Priority::Low,
                 });
             }
         }
     }
     
+    // Flag packages declared more than once
+    recommendations.extend(duplicate_declarations(packages).into_iter().map(|(name, specs)| {
+        let conflicting = specs.iter().collect::<HashSet<_>>().len() > 1;
+        let kind = if conflicting { " with conflicting versions" } else { "" };
+        Recommendation {
+            description: format!("{} is declared {} times{}: {}", name, specs.len(), kind, specs.join(", ")),
+            value: specs.len().to_string(),
+            details: Some(if conflicting {
+                "The declarations ask for different versions; remove all but the intended one".to_string()
+            } else {
+                "Remove the repeated declarations".to_string()
+            }),
+            category: RecommendationCategory::Redundancy,
+            priority: if conflicting { Priority::High } else { Priority::Low },
+        }
+    }));
+    
+    // Flag packages declared in both the conda and pip sections
+    for package in packages.iter().filter(|p| p.declared_in_both) {
+        let description = if package.has_conflicting_pip_version() {
+            format!(
+                "{} is declared in both the conda ({}) and pip ({}) sections with conflicting versions",
+                package.name,
+                package.version.as_deref().unwrap_or_default(),
+                package.pip_version.as_deref().unwrap_or_default()
+            )
+        } else {
+            format!("{} is declared in both the conda and pip sections", package.name)
+        };
+        recommendations.push(Recommendation {
+            description,
+            value: "2".to_string(),
+            details: Some(
+                "The conda package is used; remove the pip declaration to avoid pip overwriting it".to_string()
+            ),
+            category: RecommendationCategory::Redundancy,
+            priority: if package.has_conflicting_pip_version() { Priority::High } else { Priority::Medium },
+        });
+    }
+    
+    // Packages without any version constraint resolve differently every time the environment is created
+    let unpinned: Vec<&str> = packages.iter()
+        .filter(|p| p.pin_status == PinStatus::Unpinned)
+        .map(|p| p.name.as_str())
+        .collect();
+    if is_history_export {
+        recommendations.push(Recommendation {
+            description: format!(
+                "The environment looks like a `conda env export --from-history` spec ({} of {} packages unconstrained)",
+                unpinned.len(), packages.len()
+            ),
+            value: unpinned.len().to_string(),
+            details: Some(
+                "Loose pins are expected in a history export, since the solver picks the rest. To reproduce \
+                 the exact environment, commit a lock file (e.g. from conda-lock or a full `conda env export`) \
+                 alongside it".to_string()
+            ),
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::Low,
+        });
+    } else {
+        recommendations.extend(unpinned_recommendation(packages));
+    }
+    
+    // Dev, nightly and pre-release builds aren't fit for production
+    recommendations.extend(prerelease_recommendation(packages));
+    
     // Add recommendation about pinned packages
     if pinned_count > 0 {
         let percent = (pinned_count as f64 / packages.len() as f64) * 100.0;
@@ -266,170 +806,284 @@ fn generate_simple_recommendations(
                 percent as u32),
             value: format!("{}", pinned_count),
             details: None,
+            category: RecommendationCategory::Reproducibility,
+            priority: Priority::Low,
         });
     }
     
     recommendations
 }
 
-/// Extracts packages from a conda environment
-fn extract_packages_from_environment(env: &crate::models::CondaEnvironment) -> Result<Vec<Package>> {
+/// Flags the packages declared without any version constraint (`- numpy` rather than
+/// `- numpy=1.21`), listing each by name. When a package's latest version is known, the
+/// details suggest an exact pin for it
+pub fn unpinned_recommendation(packages: &[Package]) -> Option<Recommendation> {
+    let unpinned: Vec<&Package> = packages.iter()
+        .filter(|p| p.pin_status == PinStatus::Unpinned)
+        .collect();
+    if unpinned.is_empty() {
+        return None;
+    }
+    
+    let names: Vec<&str> = unpinned.iter().map(|p| p.name.as_str()).collect();
+    let pins: Vec<String> = unpinned.iter()
+        .filter_map(|p| {
+            let operator = if p.channel.as_deref() == Some("pip") { "==" } else { "=" };
+            p.latest_version.as_deref().map(|latest| format!("{}{}{}", p.name, operator, latest))
+        })
+        .collect();
+    
+    let mut details = "They resolve to whatever is newest when the environment is created. Pin them to an \
+                       exact version (e.g. `numpy=1.26.4`) or at least constrain them to a range".to_string();
+    if !pins.is_empty() {
+        details.push_str(&format!(". Current versions: {}", pins.join(", ")));
+    }
+    
+    Some(Recommendation {
+        description: format!("{} packages have no version constraint: {}", names.len(), names.join(", ")),
+        value: names.len().to_string(),
+        details: Some(details),
+        category: RecommendationCategory::Reproducibility,
+        priority: Priority::High,
+    })
+}
+
+/// Flags the packages whose version is a development, nightly or pre-release build
+/// (`2.0.0.dev20230101`, `1.5.0a1`, `nightly`), listing each with its version. Such builds
+/// can change or disappear without notice, so they don't belong in a production environment
+pub fn prerelease_recommendation(packages: &[Package]) -> Option<Recommendation> {
+    let unstable: Vec<String> = packages.iter()
+        .filter_map(|p| {
+            let version = p.version.as_deref()?;
+            let tag = version::CondaVersion::parse(version)?.prerelease_tag()?.to_string();
+            Some(format!("{} {} ({})", p.name, version, tag))
+        })
+        .collect();
+    if unstable.is_empty() {
+        return None;
+    }
+    
+    Some(Recommendation {
+        description: format!("{} packages use development or pre-release versions: {}", unstable.len(), unstable.join(", ")),
+        value: unstable.len().to_string(),
+        details: Some(
+            "Dev, nightly and pre-release builds are not stable releases and may be yanked or replaced. \
+             Pin the packages to a stable release before promoting the environment to production".to_string()
+        ),
+        category: RecommendationCategory::Reproducibility,
+        priority: Priority::High,
+    })
+}
+
+/// Finds packages declared more than once, returning each name with the spec of every
+/// declaration in file order (e.g. `numpy` with `["1.21", "1.20"]`). Conda and pip
+/// declarations are checked separately, and names are compared case-insensitively
+pub(crate) fn duplicate_declarations(packages: &[Package]) -> Vec<(String, Vec<String>)> {
+    let mut declarations: Vec<((String, bool), Vec<String>)> = Vec::new();
+    
+    for package in packages {
+        let key = (package.name.to_lowercase(), package.channel.as_deref() == Some("pip"));
+        let spec = match (&package.version, &package.build) {
+            (Some(version), Some(build)) => format!("{}={}", version, build),
+            (Some(version), None) => version.clone(),
+            (None, _) => "any version".to_string(),
+        };
+        
+        match declarations.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, specs)) => specs.push(spec),
+            None => declarations.push((key, vec![spec])),
+        }
+    }
+    
+    declarations.into_iter()
+        .filter(|(_, specs)| specs.len() > 1)
+        .map(|((name, is_pip), specs)| {
+            let name = if is_pip { format!("{} (pip)", name) } else { name };
+            (name, specs)
+        })
+        .collect()
+}
+
+/// Extracts packages from a conda environment, keeping those selected by `filters`
+fn extract_packages_from_environment(env: &crate::models::CondaEnvironment, filters: &PackageFilters) -> Result<Vec<Package>> {
     let mut packages = Vec::new();
     
     // Extract normal dependencies
     for dep in &env.dependencies {
         match dep {
             crate::models::Dependency::Simple(spec) => {
-                let parts: Vec<&str> = spec.split('=').collect();
-                let name = parts[0].trim().to_string();
-                let version = if parts.len() > 1 { Some(parts[1].trim().to_string()) } else { None };
-                let is_pinned = version.is_some();
-                
-                packages.push(Package {
-                    name,
-                    version,
-                    build: None,
-                    channel: None,
-                    size: None,
-                    is_pinned,
-                    is_outdated: false,
-                    latest_version: None,
-                });
+                packages.push(parsers::parse_package_spec(spec));
             },
             crate::models::Dependency::Complex(complex) => {
-                // Handle pip packages
-                if let Some(pip_pkgs) = &complex.pip {
-                    for pip_spec in pip_pkgs {
-                        let parts: Vec<&str> = pip_spec.split('=').collect();
-                        let name = parts[0].trim().to_string();
-                        let version = if parts.len() > 1 { 
-                            Some(parts[1].trim().to_string()) 
-                        } else { 
-                            None 
-                        };
-                        let is_pinned = version.is_some();
-                        
-                        packages.push(Package {
-                            name,
-                            version,
-                            build: None,
-                            channel: Some("pip".to_string()),
-                            size: None,
-                            is_pinned,
-                            is_outdated: false,
-                            latest_version: None,
-                        });
-                    }
-                }
+                packages.extend(parsers::extract_complex_dependency(complex));
             }
         }
     }
     
-    Ok(packages)
+    for (name, specs) in duplicate_declarations(&packages) {
+        warn!("{} is declared {} times: {}", name, specs.len(), specs.join(", "));
+    }
+    
+    Ok(filters.apply(merge_pip_overrides(packages)))
 }
 
-/// Checks if a package is pinned in the environment
-fn is_pinned_package(pkg_name: &str, env: &crate::models::CondaEnvironment) -> Result<bool> {
-    for dep in &env.dependencies {
-        match dep {
-            crate::models::Dependency::Simple(spec) => {
-                let parts: Vec<&str> = spec.split('=').collect();
-                if parts[0].trim() == pkg_name {
-                    return Ok(parts.len() > 1);
-                }
-            },
-            crate::models::Dependency::Complex(complex) => {
-                if let Some(pip_pkgs) = &complex.pip {
-                    for pip_spec in pip_pkgs {
-                        let parts: Vec<&str> = pip_spec.split('=').collect();
-                        if parts[0].trim() == pkg_name {
-                            return Ok(parts.len() > 1);
-                        }
-                    }
-                }
+/// Normalizes a package name for comparisons across conda and pip (PEP 503 style):
+/// case-insensitive, with `_` and `.` treated like `-`
+pub(crate) fn normalized_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Merges pip declarations into the conda entry of the same package, so a package listed
+/// in both sections is only counted once. The conda entry wins; it is marked as
+/// `declared_in_both` and keeps the pip version in `pip_version`
+pub(crate) fn merge_pip_overrides(packages: Vec<Package>) -> Vec<Package> {
+    let conda_names: HashMap<String, usize> = packages.iter()
+        .enumerate()
+        .filter(|(_, p)| p.channel.as_deref() != Some("pip"))
+        .rev()
+        .map(|(i, p)| (normalized_name(&p.name), i))
+        .collect();
+    
+    let mut overrides: Vec<(usize, Option<String>)> = Vec::new();
+    let mut merged: Vec<Option<Package>> = packages.into_iter().map(Some).collect();
+    
+    for slot in merged.iter_mut() {
+        let Some(package) = slot else { continue };
+        if package.channel.as_deref() != Some("pip") {
+            continue;
+        }
+        if let Some(&conda_index) = conda_names.get(&normalized_name(&package.name)) {
+            debug!("{} is declared in both the conda and pip sections; keeping the conda entry", package.name);
+            overrides.push((conda_index, package.version.clone()));
+            *slot = None;
+        }
+    }
+    
+    for (conda_index, pip_version) in overrides {
+        if let Some(conda) = merged[conda_index].as_mut() {
+            conda.declared_in_both = true;
+            if conda.pip_version.is_none() {
+                conda.pip_version = pip_version;
             }
         }
     }
     
-    Ok(false)
+    merged.into_iter().flatten().collect()
 }
 
-/// Checks if a package is outdated by querying the conda API
-fn check_outdated(pkg_name: &str, current_version: Option<&str>) -> Option<(bool, Option<String>)> {
-    if let Some(current) = current_version {
-        // Query the conda API for the latest version
-        match conda_api::get_latest_version(pkg_name) {
-            Ok(latest) => {
-                // Compare versions using semver if possible
-                let is_outdated = match (semver::Version::parse(current), semver::Version::parse(&latest)) {
-                    (Ok(curr_ver), Ok(latest_ver)) => latest_ver > curr_ver,
-                    _ => latest != current.to_string() // Fallback to string comparison if parsing fails
-                };
-                
-                Some((is_outdated, Some(latest)))
-            },
-            Err(_) => Some((false, None)) // Couldn't determine, assume not outdated
+/// Checks if a package is outdated by querying the conda API, returning whether it is,
+/// the latest version and the source that provided it. Packages without a version can't
+/// be compared and give `Ok(None)`; a failed lookup gives the reason
+fn check_outdated(
+    pkg_name: &str,
+    channel: Option<&str>,
+    current_version: Option<&str>,
+    lookup: &LookupOptions,
+) -> Result<Option<(bool, String, &'static str)>, String> {
+    let Some(current) = current_version else {
+        return Ok(None);
+    };
+    
+    // Query the conda API for the latest version, on the package's own channel first
+    let (latest, source) = performance::latest_version(pkg_name, channel, lookup).map_err(|e| e.to_string())?;
+    
+    // Compare using conda version ordering (falls back to string comparison)
+    let is_outdated = version::is_newer(current, &latest);
+    Ok(Some((is_outdated, latest, source)))
+}
+
+/// Summarizes which sources provided version and size data for each package.
+/// `version_sources` and `size_sources` are indexed like `packages`
+fn build_enrichment_report(
+    packages: &[Package],
+    version_sources: &[Option<&'static str>],
+    size_sources: &[Option<&'static str>],
+) -> EnrichmentReport {
+    let mut report = EnrichmentReport {
+        total_packages: packages.len(),
+        ..Default::default()
+    };
+    
+    for (i, package) in packages.iter().enumerate() {
+        let version_source = version_sources.get(i).copied().flatten();
+        let size_source = size_sources.get(i).copied().flatten();
+        
+        if let Some(source) = version_source {
+            *report.version_sources.entry(source.to_string()).or_insert(0) += 1;
+        }
+        if let Some(source) = size_source {
+            *report.size_sources.entry(source.to_string()).or_insert(0) += 1;
+        }
+        
+        let has_real_size = size_source.is_some_and(|source| source != "estimate");
+        if version_source.is_none() && !has_real_size {
+            report.failed.push(package.name.clone());
         }
-    } else {
-        Some((false, None)) // No current version, can't determine
     }
+    
+    info!("Enrichment summary: {}", report);
+    report
 }
 
-/// Get package sizes by reading package metadata
-fn get_packages_sizes(packages: &mut [Package]) -> Option<u64> {
-    let mut total_size = 0;
-    
+/// Get package sizes from the unpacked packages of the active environment (`CONDA_PREFIX`),
+/// else from package metadata. Sizes already known, e.g. from an installed environment's
+/// metadata, are kept. Records the source of each package's size into `size_sources`
+/// (indexed like `packages`). With `estimate_sizes`, packages whose size can't be looked up
+/// get a placeholder estimate
+fn get_packages_sizes(
+    packages: &mut [Package],
+    size_sources: &mut [Option<&'static str>],
+    estimate_sizes: bool,
+    lookup: &LookupOptions,
+) {
     let active_env = std::env::var("CONDA_PREFIX").ok();
     
-    if let Some(env_path) = active_env {
-        // Get sizes from actual conda packages in the environment
-        for package in packages {
-            // Look for package in pkgs directory
-            let pkg_paths = glob::glob(&format!("{}/pkgs/{}*", env_path, package.name))
-                .ok()?
-                .filter_map(Result::ok);
-            
-            for path in pkg_paths {
-                if path.is_dir() && path.file_name().unwrap().to_string_lossy().contains(&package.name) {
-                    // Walk the directory and calculate size
-                    let pkg_size = walkdir::WalkDir::new(&path)
-                        .into_iter()
-                        .filter_map(|e| e.ok())
-                        .filter_map(|e| e.metadata().ok())
-                        .filter(|m| m.is_file())
-                        .fold(0, |acc, m| acc + m.len());
-                    
-                    package.size = Some(pkg_size);
-                    total_size += pkg_size;
-                    break;
-                }
-            }
-            
-            // If size still not determined, query conda API
-            if package.size.is_none() {
-                if let Ok(size) = conda_api::get_package_size(&package.name) {
-                    package.size = Some(size);
-                    total_size += size;
-                }
-            }
+    for (package, size_source) in packages.iter_mut().zip(size_sources.iter_mut()) {
+        if package.size.is_some() {
+            *size_source = Some("installed");
+            continue;
         }
-    } else {
-        // Fallback to conda API if no active environment
-        for package in packages {
-            if let Ok(size) = conda_api::get_package_size(&package.name) {
-                package.size = Some(size);
-                total_size += size;
-            } else {
-                // Estimate size if API fails (better than having nothing)
-                package.size = Some(5_000_000); // Default guess 5MB
-                total_size += 5_000_000;
-            }
+        
+        if let Some(size) = active_env.as_deref().and_then(|env_path| local_package_size(env_path, &package.name)) {
+            package.size = Some(size);
+            *size_source = Some("local");
+        } else if let Ok((size, source)) = performance::package_size(&package.name, lookup) {
+            package.size = Some(size);
+            *size_source = Some(source);
+        } else if estimate_sizes {
+            estimate_size(package, size_source);
         }
     }
+}
+
+/// Size on disk of a package unpacked in the environment's `pkgs` directory, if it is there
+fn local_package_size(env_path: &str, name: &str) -> Option<u64> {
+    let pattern = format!("{}/pkgs/{}*", env_path, name);
+    let paths = match glob::glob(&pattern) {
+        Ok(paths) => paths,
+        Err(e) => {
+            warn!("Invalid package path pattern {}: {}", pattern, e);
+            return None;
+        }
+    };
     
-    if total_size > 0 {
-        Some(total_size)
-    } else {
-        None
-    }
+    let path = paths
+        .filter_map(|entry| entry.map_err(|e| warn!("Skipping unreadable package path: {}", e)).ok())
+        .find(|path| path.is_dir() && path.file_name().is_some_and(|file| file.to_string_lossy().contains(name)))?;
+    
+    // Walk the directory and calculate size
+    Some(walkdir::WalkDir::new(&path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .fold(0, |acc, m| acc + m.len()))
+}
+
+/// Gives a package whose size couldn't be looked up the placeholder size, flagged as estimated
+fn estimate_size(package: &mut Package, size_source: &mut Option<&'static str>) {
+    debug!("Estimating size of {} as {}", package.name, format_size(ESTIMATED_PACKAGE_SIZE));
+    package.size = Some(ESTIMATED_PACKAGE_SIZE);
+    package.size_estimated = true;
+    *size_source = Some("estimate");
 }
@@ -0,0 +1,422 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use crate::conda_api::LookupOptions;
+use crate::exporters::ExportFormat;
+use crate::models::{Channel, CondaEnvironment, Dependency, Package};
+use crate::parsers::{self, FileFormat};
+use crate::version::CondaVersion;
+
+/// How serious a problem found by `validate` is: errors make the file invalid, warnings don't
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueLevel {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for IssueLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssueLevel::Warning => write!(f, "warning"),
+            IssueLevel::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in an environment file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub level: IssueLevel,
+    /// 1-based line of the file the problem is on, when it can be located
+    pub line: Option<usize>,
+    /// Text of that line
+    pub context: Option<String>,
+    pub message: String,
+}
+
+/// Structural problems of an environment file, found without any lookups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Path of the validated file
+    pub file: String,
+    /// Number of packages declared, or `None` if the file couldn't be parsed
+    pub packages: Option<usize>,
+    /// Problems in file order, with problems that can't be located last
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    /// Number of issues of the given level
+    pub fn count(&self, level: IssueLevel) -> usize {
+        self.issues.iter().filter(|issue| issue.level == level).count()
+    }
+
+    /// Whether the file has no errors (warnings are allowed)
+    pub fn is_valid(&self) -> bool {
+        self.count(IssueLevel::Error) == 0
+    }
+}
+
+/// Lines of the file, to point issues at the declaration they are about. Each line is
+/// matched at most once, so repeated declarations are found in file order
+struct SourceLines<'a> {
+    lines: Vec<&'a str>,
+    used: Vec<bool>,
+}
+
+impl<'a> SourceLines<'a> {
+    fn new(content: &'a str) -> Self {
+        let lines: Vec<&str> = content.lines().collect();
+        let used = vec![false; lines.len()];
+        Self { lines, used }
+    }
+
+    /// The first unmatched line declaring `spec` as a list item, requirement or string
+    /// (e.g. `  - "numpy=1.21"  # comment` for "numpy=1.21")
+    fn claim(&mut self, spec: &str) -> Option<usize> {
+        let spec = spec.trim();
+        let index = self.lines.iter()
+            .enumerate()
+            .find(|(i, line)| !self.used[*i] && declared_value(line) == spec)
+            .map(|(i, _)| i)?;
+        self.used[index] = true;
+        Some(index + 1)
+    }
+
+    /// The first line starting with the top-level `key`
+    fn key(&self, key: &str) -> Option<usize> {
+        self.lines.iter()
+            .position(|line| line.trim_start_matches('"').strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start_matches('"').trim_start().starts_with(':')))
+            .map(|i| i + 1)
+    }
+
+    fn issue(&self, level: IssueLevel, line: Option<usize>, message: String) -> Issue {
+        let context = line.and_then(|line| self.lines.get(line - 1)).map(|text| text.trim_end().to_string());
+        Issue { level, line, context, message }
+    }
+}
+
+/// The value a line declares, without list dash, quotes, trailing comma or comment
+fn declared_value(line: &str) -> &str {
+    let line = line.trim();
+    let line = line.strip_prefix("- ").unwrap_or(line);
+    let line = match line.find(" #") {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+    line.trim().trim_end_matches(',').trim().trim_matches(['"', '\'']).trim()
+}
+
+/// Validate an environment file: parse it and check it for invalid package specs, duplicate
+/// or conflicting declarations, unknown or malformed channels and a hard-coded prefix. Nothing
+/// is looked up, so only the file itself is checked. `document` selects the YAML document of
+/// multi-document files (`None` picks it automatically), and `lookup` how a file given as a URL
+/// is downloaded. Fails only if the file can't be read
+pub fn validate_file<P: AsRef<Path>>(file_path: P, document: Option<usize>, lookup: &LookupOptions) -> Result<ValidationReport> {
+    let (content, format) = parsers::read_environment_file(&file_path, lookup)?;
+    let file = file_path.as_ref().display().to_string();
+    let mut lines = SourceLines::new(&content);
+
+    let env = match parsers::parse_environment_str_with_document(&content, format, document) {
+        Ok(env) => env,
+        Err(e) => {
+            let line = error_line(&e, &content);
+            let message = format!("Failed to parse {} content: {:#}", format, e);
+            return Ok(ValidationReport {
+                file,
+                packages: None,
+                issues: vec![lines.issue(IssueLevel::Error, line, message)],
+            });
+        },
+    };
+
+    let mut issues = Vec::new();
+    check_channels(&env, &mut lines, &mut issues);
+    let packages = check_dependencies(&env, format, &mut lines, &mut issues);
+
+    if crate::analysis::hardcoded_prefix_recommendation(&env).is_some() {
+        let line = lines.key("prefix");
+        issues.push(lines.issue(
+            IssueLevel::Warning,
+            line,
+            "Absolute `prefix:` ties the environment to the machine it was exported on".to_string(),
+        ));
+    }
+
+    issues.sort_by_key(|issue| issue.line.unwrap_or(usize::MAX));
+    Ok(ValidationReport { file, packages: Some(packages), issues })
+}
+
+/// Line of a parse error, from the YAML, JSON or TOML parser that reported it
+fn error_line(error: &anyhow::Error, content: &str) -> Option<usize> {
+    error.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<serde_yaml::Error>() {
+            e.location().map(|location| location.line())
+        } else if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            Some(e.line()).filter(|line| *line > 0)
+        } else if let Some(e) = cause.downcast_ref::<toml::de::Error>() {
+            e.span().map(|span| content[..span.start.min(content.len())].matches('\n').count() + 1)
+        } else {
+            None
+        }
+    })
+}
+
+/// Empty, malformed and repeated entries of `channels:`
+fn check_channels(env: &CondaEnvironment, lines: &mut SourceLines, issues: &mut Vec<Issue>) {
+    let mut seen = HashSet::new();
+
+    for channel in &env.channels {
+        let line = lines.claim(channel);
+        let name = channel.trim();
+        if name.is_empty() {
+            issues.push(lines.issue(IssueLevel::Error, line, "Empty channel name".to_string()));
+        } else if matches!(Channel::parse(name), Channel::Named(_)) && name.contains(char::is_whitespace) {
+            issues.push(lines.issue(IssueLevel::Error, line, format!("Invalid channel name `{}`", name)));
+        } else if !seen.insert(name.trim_end_matches('/').to_lowercase()) {
+            issues.push(lines.issue(IssueLevel::Warning, line, format!("Channel `{}` is listed more than once", name)));
+        }
+    }
+}
+
+/// Invalid specs, `channel::` prefixes for channels not in `channels:`, repeated declarations
+/// and packages pinned differently in the conda and pip sections. Returns the number of
+/// packages declared
+fn check_dependencies(env: &CondaEnvironment, format: FileFormat, lines: &mut SourceLines, issues: &mut Vec<Issue>) -> usize {
+    let declared_channels: HashSet<String> = env.channels.iter()
+        .map(|channel| channel.trim().trim_end_matches('/').to_lowercase())
+        .collect();
+    // Specs of explicit lock files are generated URLs, and pyproject.toml specs are rebuilt
+    // from Poetry tables, so neither is checked as written
+    let check_specs = !matches!(format, FileFormat::Explicit | FileFormat::Pyproject);
+    let mut declared: Vec<(Package, Option<usize>)> = Vec::new();
+
+    for dependency in &env.dependencies {
+        match dependency {
+            Dependency::Simple(spec) => {
+                let line = lines.claim(spec);
+                if check_specs {
+                    if let Some(problem) = conda_spec_problem(spec) {
+                        issues.push(lines.issue(IssueLevel::Error, line, problem));
+                        continue;
+                    }
+                }
+                let package = parsers::parse_package_spec(spec);
+                if let Some(channel) = package.channel.as_deref().map(|c| c.trim().trim_end_matches('/')) {
+                    if !declared_channels.contains(&channel.to_lowercase()) {
+                        issues.push(lines.issue(
+                            IssueLevel::Warning,
+                            line,
+                            format!("`{}` uses channel `{}`, which is not listed in `channels:`", package.name, channel),
+                        ));
+                    }
+                }
+                declared.push((package, line));
+            },
+            Dependency::Complex(complex) => {
+                for spec in complex.pip.iter().flatten() {
+                    let line = lines.claim(spec);
+                    // Options such as `-r requirements.txt`, `-e .` or `--index-url`
+                    if spec.trim_start().starts_with('-') {
+                        continue;
+                    }
+                    let problem = if check_specs { pip_spec_problem(spec) } else { None };
+                    match (problem, parsers::parse_pip_spec(spec)) {
+                        (Some(problem), _) => issues.push(lines.issue(IssueLevel::Error, line, problem)),
+                        (None, Some(package)) => declared.push((package, line)),
+                        (None, None) => issues.push(lines.issue(
+                            IssueLevel::Error,
+                            line,
+                            format!("Invalid pip requirement `{}`: no package name", spec.trim()),
+                        )),
+                    }
+                }
+                if complex.pip.is_none() {
+                    let line = complex.name.as_deref().and_then(|name| lines.claim(&format!("name: {}", name)));
+                    match complex.name.as_deref().map(str::trim) {
+                        Some(name) if name_problem(name).is_none() => {
+                            declared.extend(parsers::extract_complex_dependency(complex).into_iter().map(|p| (p, line)));
+                        },
+                        Some(name) => issues.push(lines.issue(
+                            IssueLevel::Error, line, name_problem(name).unwrap_or_default())),
+                        None => issues.push(lines.issue(
+                            IssueLevel::Error, line, "Dependency mapping has neither a `name` nor a `pip` list".to_string())),
+                    }
+                }
+            },
+        }
+    }
+
+    let packages: Vec<Package> = declared.iter().map(|(package, _)| package.clone()).collect();
+    let line_of = |name: &str, nth: usize| {
+        let (name, pip) = match name.strip_suffix(" (pip)") {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        declared.iter()
+            .filter(|(p, _)| p.name.to_lowercase() == name && (p.channel.as_deref() == Some("pip")) == pip)
+            .nth(nth)
+            .and_then(|(_, line)| *line)
+    };
+
+    for (name, specs) in crate::utils::duplicate_declarations(&packages) {
+        let line = line_of(&name, 1);
+        let distinct: HashSet<&String> = specs.iter().collect();
+        let issue = if distinct.len() > 1 {
+            lines.issue(IssueLevel::Error, line, format!("{} is pinned to conflicting versions: {}", name, specs.join(", ")))
+        } else {
+            lines.issue(IssueLevel::Warning, line, format!("{} is declared {} times", name, specs.len()))
+        };
+        issues.push(issue);
+    }
+
+    for package in crate::utils::merge_pip_overrides(packages.clone()).iter().filter(|p| p.has_conflicting_pip_version()) {
+        let line = declared.iter()
+            .find(|(p, _)| p.channel.as_deref() == Some("pip") && crate::utils::normalized_name(&p.name) == crate::utils::normalized_name(&package.name))
+            .and_then(|(_, line)| *line);
+        issues.push(lines.issue(
+            IssueLevel::Error,
+            line,
+            format!(
+                "{} is pinned to {} in the conda section but {} in the pip section",
+                package.name,
+                package.version.as_deref().unwrap_or("unknown"),
+                package.pip_version.as_deref().unwrap_or("unknown")
+            ),
+        ));
+    }
+
+    packages.len()
+}
+
+/// Why a package name is invalid: conda and pip names are letters, digits, `-`, `_` and `.`
+fn name_problem(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return Some("Missing package name".to_string());
+    }
+    name.chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .map(|c| format!("Invalid character '{}' in package name `{}`", c, name))
+}
+
+/// Comparison operators of conda and pip version constraints, longest first
+const VERSION_OPERATORS: &[&str] = &["===", "==", "!=", "<=", ">=", "~=", "<", ">", "="];
+
+/// Why a version constraint such as `>=1.20,<2`, `1.21.*` or `1.2|1.3` is invalid
+fn constraint_problem(constraint: &str) -> Option<String> {
+    for part in constraint.split([',', '|']) {
+        let part = part.trim();
+        let version = VERSION_OPERATORS.iter()
+            .find_map(|op| part.strip_prefix(op))
+            .unwrap_or(part)
+            .trim();
+        if version == "*" {
+            continue;
+        }
+        let version = version.trim_end_matches('*').trim_end_matches('.');
+        if version.is_empty() {
+            return Some(format!("Missing version in constraint `{}`", constraint));
+        }
+        if let Err(e) = version.parse::<CondaVersion>() {
+            return Some(format!("Invalid version constraint `{}`: {}", constraint, e));
+        }
+    }
+    None
+}
+
+/// Why a conda spec (`name`, `name=1.0=build`, `name>=1.0`, `channel::name 1.0 build`) is invalid
+fn conda_spec_problem(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    let rest = match spec.split_once("::") {
+        Some((channel, _)) if channel.trim().is_empty() => {
+            return Some(format!("Empty channel prefix in `{}`", spec));
+        },
+        Some((_, rest)) => rest.trim(),
+        None => spec,
+    };
+    let name = parsers::spec_name(rest);
+    if let Some(problem) = name_problem(name) {
+        return Some(format!("Invalid package spec `{}`: {}", spec, problem));
+    }
+
+    let constraint = rest[rest.find(name).map_or(0, |idx| idx + name.len())..].trim();
+    if constraint.is_empty() || constraint.starts_with('[') {
+        return None;
+    }
+    // "=1.0=build" / "==1.0" pins, else "1.0 build" or a range, with the build string last
+    let version = match constraint.strip_prefix("==").or_else(|| constraint.strip_prefix('=')) {
+        Some(pin) => pin.split(['=', ' ']).next().unwrap_or(pin),
+        None => constraint.split_whitespace().next().unwrap_or(constraint),
+    };
+    constraint_problem(if version.is_empty() { "=" } else { version })
+        .map(|problem| format!("Invalid package spec `{}`: {}", spec, problem))
+}
+
+/// Why a pip requirement (PEP 508, e.g. `requests[socks]>=2.28; python_version >= "3.8"`) is
+/// invalid. URL requirements are only checked for a name
+fn pip_spec_problem(spec: &str) -> Option<String> {
+    let spec = spec.trim();
+    if spec.contains("://") {
+        return None;
+    }
+    let requirement = spec.split(';').next().unwrap_or(spec).trim();
+    let name = parsers::spec_name(requirement);
+    if let Some(problem) = name_problem(name) {
+        return Some(format!("Invalid pip requirement `{}`: {}", spec, problem));
+    }
+
+    let constraint = requirement[requirement.find(name).map_or(0, |idx| idx + name.len())..].trim();
+    let constraint = match constraint.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((_, rest)) => rest.trim(),
+            None => return Some(format!("Invalid pip requirement `{}`: unclosed extras bracket", spec)),
+        },
+        None => constraint,
+    };
+    if constraint.is_empty() {
+        return None;
+    }
+    constraint_problem(constraint).map(|problem| format!("Invalid pip requirement `{}`: {}", spec, problem))
+}
+
+/// Format a validation report as JSON, YAML or text (the default for other formats)
+pub fn format_validation_report(report: &ValidationReport, format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(report)
+            .with_context(|| "Failed to serialize validation report to JSON"),
+        ExportFormat::Yaml => serde_yaml::to_string(report)
+            .with_context(|| "Failed to serialize validation report to YAML"),
+        _ => Ok(format_validation_text(report)),
+    }
+}
+
+/// Compiler-style text: `file:line: level: message`, followed by the offending line
+fn format_validation_text(report: &ValidationReport) -> String {
+    let mut output = String::new();
+
+    for issue in &report.issues {
+        match issue.line {
+            Some(line) => output.push_str(&format!("{}:{}: {}: {}\n", report.file, line, issue.level, issue.message)),
+            None => output.push_str(&format!("{}: {}: {}\n", report.file, issue.level, issue.message)),
+        }
+        if let (Some(line), Some(context)) = (issue.line, &issue.context) {
+            output.push_str(&format!("{:>5} | {}\n", line, context));
+        }
+    }
+
+    let errors = report.count(IssueLevel::Error);
+    let warnings = report.count(IssueLevel::Warning);
+    let packages = report.packages.map(|n| format!(" ({} packages)", n)).unwrap_or_default();
+    if errors == 0 {
+        output.push_str(&format!("{} is valid{}, {} warnings\n", report.file, packages, warnings));
+    } else {
+        output.push_str(&format!("{} is invalid{}: {} errors, {} warnings\n", report.file, packages, errors, warnings));
+    }
+
+    output
+}
@@ -0,0 +1,365 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single piece of a version component. The variant order is the sort order:
+/// `dev` sorts before any other tag, tags sort before numbers, and `post` after everything
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Dev,
+    Tag(String),
+    Number(u64),
+    Post,
+}
+
+/// A conda (and PEP 440 compatible) package version with conda's ordering rules.
+///
+/// Versions are compared component by component, so `1.10.0 > 1.9.0`, any number of
+/// components is allowed (`1.2.3.4`), an epoch prefix (`2!1.0`) wins over everything
+/// else, and pre-release tags sort before the release (`1.0rc1 < 1.0 < 1.0.post1`)
+#[derive(Debug, Clone)]
+pub struct CondaVersion {
+    original: String,
+    epoch: u64,
+    release: Vec<Vec<Part>>,
+    local: Vec<Vec<Part>>,
+}
+
+/// Tags that mark a pre-release wherever they appear (`1.0rc1`, `2.0beta`, `nightly`)
+const PRERELEASE_TAGS: &[&str] = &["alpha", "beta", "rc", "pre", "preview", "nightly"];
+
+/// One-letter PEP 440 pre-release tags, which only count with a number after them
+/// (`1.5.0a1`), so letter-suffixed releases such as openssl's `1.1.1k` or `1.0.2a` stay stable
+const SHORT_PRERELEASE_TAGS: &[&str] = &["a", "b", "c"];
+
+impl CondaVersion {
+    /// Parse a version string, returning `None` if it is not a valid conda version
+    pub fn parse(version: &str) -> Option<Self> {
+        version.parse().ok()
+    }
+    
+    /// The development or pre-release marker of the version, e.g. "dev" for
+    /// `2.0.0.dev20230101`, "a" for `1.5.0a1` or "nightly", or `None` for a stable release.
+    /// Local version labels (after `+`) don't count
+    pub fn prerelease_tag(&self) -> Option<&str> {
+        self.release.iter().find_map(|parts| {
+            parts.iter().enumerate().find_map(|(i, part)| match part {
+                Part::Dev => Some("dev"),
+                Part::Tag(tag) if PRERELEASE_TAGS.contains(&tag.as_str()) => Some(tag.as_str()),
+                Part::Tag(tag) if SHORT_PRERELEASE_TAGS.contains(&tag.as_str())
+                    && matches!(parts.get(i + 1), Some(Part::Number(_))) => Some(tag.as_str()),
+                _ => None,
+            })
+        })
+    }
+}
+
+impl FromStr for CondaVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let original = s.trim();
+        if original.is_empty() {
+            return Err(anyhow::anyhow!("Empty version string"));
+        }
+
+        let lowered = original.to_lowercase();
+        if let Some(c) = lowered.chars().find(|c| !(c.is_ascii_alphanumeric() || "._-!+".contains(*c))) {
+            return Err(anyhow::anyhow!("Invalid character '{}' in version '{}'", c, original));
+        }
+
+        let (epoch, rest) = match lowered.split_once('!') {
+            Some((epoch, rest)) => {
+                let epoch = epoch.parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("Invalid epoch in version '{}'", original))?;
+                (epoch, rest)
+            },
+            None => (0, lowered.as_str()),
+        };
+
+        let (release, local) = match rest.split_once('+') {
+            Some((release, local)) => (release, local),
+            None => (rest, ""),
+        };
+
+        let release = parse_components(release)
+            .ok_or_else(|| anyhow::anyhow!("Invalid version '{}'", original))?;
+        if release.is_empty() {
+            return Err(anyhow::anyhow!("Invalid version '{}'", original));
+        }
+        let local = parse_components(local)
+            .ok_or_else(|| anyhow::anyhow!("Invalid local version in '{}'", original))?;
+
+        Ok(CondaVersion {
+            original: original.to_string(),
+            epoch,
+            release,
+            local,
+        })
+    }
+}
+
+/// Split a version into `.`/`-`/`_` separated components, and each component into
+/// alternating numeric and alphabetic parts. A component that starts with a tag gets an
+/// implicit leading zero, so `1.0.rc1` compares like `1.0.0rc1`
+fn parse_components(version: &str) -> Option<Vec<Vec<Part>>> {
+    if version.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut components = Vec::new();
+    for component in version.split(['.', '-', '_']) {
+        if component.is_empty() {
+            return None;
+        }
+
+        let mut parts = Vec::new();
+        let mut chars = component.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let numeric = c.is_ascii_digit();
+            let mut chunk = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() != numeric {
+                    break;
+                }
+                chunk.push(c);
+                chars.next();
+            }
+
+            let part = if numeric {
+                Part::Number(chunk.parse().ok()?)
+            } else {
+                match chunk.as_str() {
+                    "dev" => Part::Dev,
+                    "post" => Part::Post,
+                    _ => Part::Tag(chunk),
+                }
+            };
+
+            if parts.is_empty() && !numeric {
+                parts.push(Part::Number(0));
+            }
+            parts.push(part);
+        }
+        components.push(parts);
+    }
+
+    Some(components)
+}
+
+/// Compare two lists, padding the shorter one with `fill`
+fn cmp_padded<T>(a: &[T], b: &[T], fill: &T, cmp: impl Fn(&T, &T) -> Ordering) -> Ordering {
+    for i in 0..a.len().max(b.len()) {
+        let ordering = cmp(a.get(i).unwrap_or(fill), b.get(i).unwrap_or(fill));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn cmp_components(a: &[Vec<Part>], b: &[Vec<Part>]) -> Ordering {
+    let zero = Part::Number(0);
+    let empty = Vec::new();
+    cmp_padded(a, b, &empty, |x, y| cmp_padded(x, y, &zero, Part::cmp))
+}
+
+impl Ord for CondaVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch.cmp(&other.epoch)
+            .then_with(|| cmp_components(&self.release, &other.release))
+            .then_with(|| cmp_components(&self.local, &other.local))
+    }
+}
+
+impl PartialOrd for CondaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for CondaVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CondaVersion {}
+
+impl fmt::Display for CondaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.original)
+    }
+}
+
+/// Whether `latest` is a newer version than `current`. Falls back to a plain string
+/// comparison when either side can't be parsed
+pub fn is_newer(current: &str, latest: &str) -> bool {
+    match (CondaVersion::parse(current), CondaVersion::parse(latest)) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => latest != current,
+    }
+}
+
+/// Whether two version strings name the same version (`1.24` matches `1.24.0`). Falls back
+/// to a plain string comparison when either side can't be parsed
+pub fn versions_match(a: &str, b: &str) -> bool {
+    match (CondaVersion::parse(a), CondaVersion::parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.trim() == b.trim(),
+    }
+}
+
+/// Whether `version` satisfies a conda/pip version spec such as `>=1.19,<2`, `1.19.*`,
+/// `=1.19` (same as `1.19.*`), `==1.19.0`, `!=1.20`, `~=1.4.2` or `1.2|1.4`.
+/// An empty spec or `*` matches every version; a bound never matches an unparseable version
+pub fn matches_spec(version: &str, spec: &str) -> bool {
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "*" {
+        return true;
+    }
+
+    spec.split('|')
+        .any(|alternative| alternative.split(',').all(|constraint| matches_constraint(version, constraint.trim())))
+}
+
+/// Whether some version satisfies both specs (see [`matches_spec`]). Every bound either
+/// spec mentions is tried, along with a version just below and just above it, which finds
+/// any overlap between two unions of ranges (`>=1.5` and `<1.3` have none). `None` when a
+/// bound isn't a valid version, so the answer would only be a guess
+pub fn specs_overlap(a: &str, b: &str) -> Option<bool> {
+    let mut candidates = vec!["0".to_string()];
+    for bound in spec_bounds(a).chain(spec_bounds(b)) {
+        CondaVersion::parse(bound)?;
+        candidates.push(bound.to_string());
+        // `dev` sorts before every other tag, and extra zero components don't change a version
+        candidates.push(format!("{}dev0", bound));
+        candidates.push(format!("{}.0.0.1", bound));
+    }
+
+    Some(candidates.iter().any(|version| matches_spec(version, a) && matches_spec(version, b)))
+}
+
+/// The versions a spec's constraints compare against, without operators or wildcards
+fn spec_bounds(spec: &str) -> impl Iterator<Item = &str> {
+    spec.split(['|', ','])
+        .map(|constraint| split_constraint(constraint.trim()).1)
+        .map(|bound| bound.trim_end_matches('*').trim_end_matches('.'))
+        .filter(|bound| !bound.is_empty())
+}
+
+/// Split a single constraint into its operator (empty for a bare version) and bound
+fn split_constraint(constraint: &str) -> (&'static str, &str) {
+    const OPERATORS: [&str; 9] = ["===", "==", "!=", "~=", ">=", "<=", ">", "<", "="];
+
+    OPERATORS.iter()
+        .find_map(|op| constraint.strip_prefix(op).map(|rest| (*op, rest.trim())))
+        .unwrap_or(("", constraint))
+}
+
+fn matches_constraint(version: &str, constraint: &str) -> bool {
+    if constraint.is_empty() || constraint == "*" {
+        return true;
+    }
+
+    let (operator, bound) = split_constraint(constraint);
+
+    match operator {
+        "=" => matches_prefix(version, bound.trim_end_matches('*').trim_end_matches('.')),
+        "!=" => !matches_exact(version, bound),
+        "~=" => {
+            // Compatible release: `~=1.4.2` means `>=1.4.2,==1.4.*`
+            let prefix = bound.rsplit_once('.').map(|(prefix, _)| prefix).unwrap_or(bound);
+            compare(version, bound).is_some_and(|ord| ord != Ordering::Less) && matches_prefix(version, prefix)
+        }
+        ">=" => compare(version, bound).is_some_and(|ord| ord != Ordering::Less),
+        "<=" => compare(version, bound).is_some_and(|ord| ord != Ordering::Greater),
+        ">" => compare(version, bound) == Some(Ordering::Greater),
+        "<" => compare(version, bound) == Some(Ordering::Less),
+        _ => matches_exact(version, bound),
+    }
+}
+
+/// Exact match, or a prefix match when the bound ends in `*` (`1.19.*`)
+fn matches_exact(version: &str, bound: &str) -> bool {
+    if bound.ends_with('*') {
+        matches_prefix(version, bound.trim_end_matches('*').trim_end_matches('.'))
+    } else {
+        versions_match(version, bound)
+    }
+}
+
+/// Whether the version's leading components are `prefix` (`1.19.2` starts with `1.19`, `1.190` doesn't)
+fn matches_prefix(version: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || versions_match(version, prefix)
+        || version.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn compare(version: &str, bound: &str) -> Option<Ordering> {
+    Some(CondaVersion::parse(version)?.cmp(&CondaVersion::parse(bound)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inclusive_and_exclusive_bounds() {
+        assert!(matches_spec("1.19.0", ">=1.19"));
+        assert!(!matches_spec("1.19.0", ">1.19"));
+        assert!(matches_spec("2.0", "<=2.0.0"));
+        assert!(!matches_spec("2.0", "<2.0"));
+        assert!(matches_spec("1.99.99", "<2"));
+        assert!(matches_spec("1.20.0", ">=1.19,<2"));
+        assert!(!matches_spec("2.0.0", ">=1.19,<2"));
+    }
+
+    #[test]
+    fn wildcard_and_prefix_boundaries() {
+        assert!(matches_spec("1.19.0", "1.19.*"));
+        assert!(matches_spec("1.19", "=1.19"));
+        assert!(!matches_spec("1.190", "1.19.*"));
+        assert!(!matches_spec("1.2", "1.19.*"));
+    }
+
+    #[test]
+    fn compatible_release_boundaries() {
+        assert!(matches_spec("1.4.2", "~=1.4.2"));
+        assert!(matches_spec("1.4.9", "~=1.4.2"));
+        assert!(!matches_spec("1.4.1", "~=1.4.2"));
+        assert!(!matches_spec("1.5.0", "~=1.4.2"));
+    }
+
+    #[test]
+    fn exclusions_alternatives_and_any() {
+        assert!(!matches_spec("1.20.0", "!=1.20"));
+        assert!(matches_spec("1.20.1", "!=1.20"));
+        assert!(matches_spec("1.4", "1.2|1.4"));
+        assert!(!matches_spec("1.3", "1.2|1.4"));
+        assert!(matches_spec("0.1", ""));
+        assert!(matches_spec("0.1", "*"));
+        assert!(!matches_spec("not a version!", ">=1.0"));
+    }
+
+    #[test]
+    fn disjoint_ranges_do_not_overlap() {
+        assert_eq!(specs_overlap(">=1.5", "<1.3"), Some(false));
+        assert_eq!(specs_overlap(">=2.0", "<2.0"), Some(false));
+        assert_eq!(specs_overlap(">=1.21,<2", ">=2"), Some(false));
+        assert_eq!(specs_overlap("1.21.*", "1.22.*"), Some(false));
+    }
+
+    #[test]
+    fn intersecting_ranges_overlap() {
+        assert_eq!(specs_overlap(">=1.21,<2", ">=1.5"), Some(true));
+        assert_eq!(specs_overlap("<=2.0", ">=2.0"), Some(true));
+        assert_eq!(specs_overlap("*", "<1.3"), Some(true));
+        assert_eq!(specs_overlap("<1.0|>=3", ">=2,<4"), Some(true));
+    }
+
+    #[test]
+    fn invalid_bound_gives_no_answer() {
+        assert_eq!(specs_overlap(">=abc!", "<1.3"), None);
+    }
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Runs `--check --offline` on an environment of `pkga` and `pkgb`, whose dependencies
+/// are seeded into a private dependency cache
+fn run_check(dir: &Path, pkga_deps: &[&str], pkgb_deps: &[&str]) -> Output {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let cache_dir = dir.join("cache").join("conda-env-inspect");
+    fs::create_dir_all(&cache_dir).unwrap();
+    let cache = serde_json::json!({
+        "pkga:1.0:conda-forge": { "fetched_at": now, "dependencies": pkga_deps },
+        "pkgb:1.0:conda-forge": { "fetched_at": now, "dependencies": pkgb_deps },
+    });
+    fs::write(cache_dir.join("dependencies.json"), cache.to_string()).unwrap();
+
+    let env_file = dir.join("environment.yml");
+    fs::write(&env_file, "name: check\ndependencies:\n  - pkga=1.0\n  - pkgb=1.0\n").unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_conda-env-inspect"))
+        .args(["--offline", "--check"])
+        .arg(&env_file)
+        .current_dir(dir)
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_fails_on_incompatible_pins() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_check(dir.path(), &["libz >=2.0"], &["libz <1.5"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("libz"));
+}
+
+#[test]
+fn check_passes_on_compatible_pins() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = run_check(dir.path(), &["libz >=1.2"], &["libz <1.5"]);
+
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stdout));
+}